@@ -0,0 +1,42 @@
+//! Insert many startup trees into an app.
+//!
+//! Run with `cargo bench --bench insert_trees`.
+
+use bevy::prelude::*;
+use bevy_startup_tree::{startup_tree, AddStartupTree};
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+
+const TREES: usize = 100;
+
+fn load() {}
+fn spawn_a() {}
+fn spawn_b() {}
+fn finish() {}
+
+fn insert_trees(app: &mut App) {
+    for _ in 0..TREES {
+        app.add_startup_tree(startup_tree! {
+            load => {
+                spawn_a => finish,
+                spawn_b,
+            },
+        });
+    }
+}
+
+fn bench_insert_trees(c: &mut Criterion) {
+    c.bench_function("insert 100 trees", |b| {
+        // Return the app so that dropping it isn't measured
+        b.iter_batched(
+            App::new,
+            |mut app| {
+                insert_trees(&mut app);
+                app
+            },
+            BatchSize::LargeInput,
+        );
+    });
+}
+
+criterion_group!(benches, bench_insert_trees);
+criterion_main!(benches);
@@ -0,0 +1,53 @@
+use std::{thread, time::Duration};
+
+use bevy::{ecs::world::CommandQueue, log::LogPlugin, prelude::*};
+
+use bevy_startup_tree::{startup_tree, AddStartupTree, StartupTasks};
+
+#[derive(Resource)]
+struct Level {
+    name: String,
+    enemies: u32,
+}
+
+fn main() {
+    App::new()
+        .add_plugins((TaskPoolPlugin::default(), LogPlugin::default()))
+        .add_startup_tree(startup_tree! {
+            async load_level => {
+                spawn_enemies,
+                announce_level,
+            },
+            setup_camera,
+        })
+        .run();
+}
+
+fn load_level(mut tasks: ResMut<StartupTasks>) {
+    info!("Loading level...");
+    tasks.spawn(async {
+        // Stand-in for reading and parsing a level file
+        thread::sleep(Duration::from_millis(500));
+        let level = Level { name: String::from("The Forest"), enemies: 3 };
+
+        let mut queue = CommandQueue::default();
+        queue.push(move |world: &mut World| {
+            world.insert_resource(level);
+        });
+        queue
+    });
+}
+
+fn setup_camera() {
+    info!("Camera ready");
+}
+
+fn spawn_enemies(level: Res<Level>) {
+    for i in 0..level.enemies {
+        info!("Spawned enemy {i}");
+    }
+}
+
+fn announce_level(level: Res<Level>) {
+    info!("Welcome to {}", level.name);
+}
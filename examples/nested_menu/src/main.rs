@@ -0,0 +1,114 @@
+//! The `bevy_ui` example spawns a shallow tree: one parent with three children. Real menus tend to
+//! be much deeper. This example spawns a nested menu where each depth of the startup tree spawns
+//! the entities for one level of the menu as children of the entities spawned by the depth before
+//! it. The flush after each depth makes sure that the parent entities exist by the time their
+//! children are spawned.
+//!
+//! The menu is headless to keep the example small, each menu entity only has a [`Name`].
+
+use bevy::{log::LogPlugin, prelude::*};
+use bevy_startup_tree::{startup_tree, AddStartupTree};
+
+fn main() {
+    App::new()
+        .add_plugins((TaskPoolPlugin::default(), LogPlugin::default()))
+        .add_startup_tree(startup_tree! {
+            spawn_menu_root
+                => spawn_main_menu
+                => spawn_settings_menu
+                => {
+                    spawn_audio_section,
+                    spawn_graphics_section => spawn_resolution_options => spawn_resolution_labels,
+                }
+        })
+        .add_systems(PostStartup, print_menu)
+        .run();
+}
+
+#[derive(Component)]
+struct MenuRoot;
+
+#[derive(Component)]
+struct MainMenu;
+
+#[derive(Component)]
+struct SettingsMenu;
+
+#[derive(Component)]
+struct GraphicsSection;
+
+#[derive(Component)]
+struct ResolutionOption;
+
+fn spawn_menu_root(mut commands: Commands) {
+    commands.spawn((MenuRoot, Name::new("Menu")));
+}
+
+fn spawn_main_menu(mut commands: Commands, q_root: Query<Entity, With<MenuRoot>>) {
+    commands.entity(q_root.single()).with_children(|parent| {
+        parent.spawn((MainMenu, Name::new("Main Menu")));
+    });
+}
+
+fn spawn_settings_menu(mut commands: Commands, q_main_menu: Query<Entity, With<MainMenu>>) {
+    commands.entity(q_main_menu.single()).with_children(|parent| {
+        parent.spawn(Name::new("Play"));
+        parent.spawn((SettingsMenu, Name::new("Settings")));
+        parent.spawn(Name::new("Quit"));
+    });
+}
+
+fn spawn_audio_section(mut commands: Commands, q_settings: Query<Entity, With<SettingsMenu>>) {
+    commands.entity(q_settings.single()).with_children(|parent| {
+        parent.spawn(Name::new("Audio"));
+    });
+}
+
+fn spawn_graphics_section(mut commands: Commands, q_settings: Query<Entity, With<SettingsMenu>>) {
+    commands.entity(q_settings.single()).with_children(|parent| {
+        parent.spawn((GraphicsSection, Name::new("Graphics")));
+    });
+}
+
+fn spawn_resolution_options(
+    mut commands: Commands,
+    q_graphics: Query<Entity, With<GraphicsSection>>,
+) {
+    commands.entity(q_graphics.single()).with_children(|parent| {
+        for resolution in ["1280x720", "1920x1080", "2560x1440"] {
+            parent.spawn((ResolutionOption, Name::new(resolution)));
+        }
+    });
+}
+
+fn spawn_resolution_labels(
+    mut commands: Commands,
+    q_options: Query<(Entity, &Name), With<ResolutionOption>>,
+) {
+    for (option, name) in &q_options {
+        commands.entity(option).with_children(|parent| {
+            parent.spawn(Name::new(format!("Select {name}")));
+        });
+    }
+}
+
+fn print_menu(
+    q_root: Query<Entity, With<MenuRoot>>,
+    q_children: Query<&Children>,
+    q_names: Query<&Name>,
+) {
+    fn print_entity(
+        entity: Entity,
+        depth: usize,
+        q_children: &Query<&Children>,
+        q_names: &Query<&Name>,
+    ) {
+        let name = q_names.get(entity).map_or("<unnamed>", Name::as_str);
+        info!("{:indent$}{name}", "", indent = depth * 2);
+        for &child in q_children.get(entity).into_iter().flatten() {
+            print_entity(child, depth + 1, q_children, q_names);
+        }
+    }
+
+    print_entity(q_root.single(), 0, &q_children, &q_names);
+}
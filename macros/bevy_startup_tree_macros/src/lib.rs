@@ -1,4 +1,7 @@
-use bevy_startup_tree_macros_core::StartupTree;
+use bevy_startup_tree_macros_core::{
+    RelaxedStartupTree, StartupLayers, StartupLayersMeta, StartupTeardownTree, StartupTree,
+    StartupTreeFn, StartupTreeMeta, StartupTreeSets, StartupTreeShape, StrictStartupTree,
+};
 use proc_macro::TokenStream;
 use quote::quote;
 use syn::parse_macro_input;
@@ -11,3 +14,94 @@ pub fn startup_tree(input: TokenStream) -> TokenStream {
     }
     .into()
 }
+
+#[proc_macro]
+pub fn strict_startup_tree(input: TokenStream) -> TokenStream {
+    let tree: StrictStartupTree = parse_macro_input!(input);
+    quote! {
+        #tree
+    }
+    .into()
+}
+
+#[proc_macro]
+pub fn relaxed_startup_tree(input: TokenStream) -> TokenStream {
+    let tree: RelaxedStartupTree = parse_macro_input!(input);
+    quote! {
+        #tree
+    }
+    .into()
+}
+
+#[proc_macro]
+pub fn startup_teardown_tree(input: TokenStream) -> TokenStream {
+    let tree: StartupTeardownTree = parse_macro_input!(input);
+    quote! {
+        #tree
+    }
+    .into()
+}
+
+#[proc_macro]
+pub fn startup_layers(input: TokenStream) -> TokenStream {
+    let layers: StartupLayers = parse_macro_input!(input);
+    quote! {
+        #layers
+    }
+    .into()
+}
+
+#[proc_macro]
+pub fn startup_layers_meta(input: TokenStream) -> TokenStream {
+    let meta: StartupLayersMeta = parse_macro_input!(input);
+    quote! {
+        #meta
+    }
+    .into()
+}
+
+#[proc_macro]
+pub fn startup_tree_fn(input: TokenStream) -> TokenStream {
+    let tree_fn: StartupTreeFn = parse_macro_input!(input);
+    quote! {
+        #tree_fn
+    }
+    .into()
+}
+
+#[proc_macro]
+pub fn startup_tree_sets(input: TokenStream) -> TokenStream {
+    let tree_sets: StartupTreeSets = parse_macro_input!(input);
+    quote! {
+        #tree_sets
+    }
+    .into()
+}
+
+#[proc_macro]
+pub fn startup_tree_meta(input: TokenStream) -> TokenStream {
+    let tree_meta: StartupTreeMeta = parse_macro_input!(input);
+    quote! {
+        #tree_meta
+    }
+    .into()
+}
+
+#[proc_macro]
+pub fn assert_startup_tree_shape(input: TokenStream) -> TokenStream {
+    let tree_shape: StartupTreeShape = parse_macro_input!(input);
+    quote! {
+        #tree_shape
+    }
+    .into()
+}
+
+#[cfg(feature = "file")]
+#[proc_macro]
+pub fn startup_tree_from_file(input: TokenStream) -> TokenStream {
+    let tree_file: bevy_startup_tree_macros_core::StartupTreeFile = parse_macro_input!(input);
+    quote! {
+        #tree_file
+    }
+    .into()
+}
@@ -1,17 +1,81 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::ToTokens;
 use syn::{
-    braced,
-    parse::{Parse, ParseStream},
-    token::Brace,
-    Path, Result, Token,
+    braced, bracketed,
+    parse::{Parse, ParseBuffer, ParseStream},
+    punctuated::Punctuated,
+    token::{Brace, Bracket},
+    Attribute, Ident, Meta, Path, Result, Token,
 };
 
-use crate::{Node, Tree};
+use crate::{node::placeholder_expr, DepthPin, Node, Tree, TreeParseError};
 
-#[derive(PartialEq)]
+#[derive(Clone, PartialEq)]
 pub enum Branch {
     Leaf(Node),
-    Arm(Node, Token![=>], Box<Branch>),
-    Tree(Node, Token![=>], Tree),
+    Arm(Node, Arrow, Box<Branch>),
+    Tree(Node, Arrow, Tree),
+}
+
+/// The `=>` between a node and its children, optionally guarded by a `#[cfg(...)]` attribute.
+///
+/// When the cfg predicate is false, the children are siblings of the node instead, e.g.
+/// `a #[cfg(feature = "x")] => b` is `a, b` without the feature.
+#[derive(Clone, Default, PartialEq)]
+pub struct Arrow {
+    pub cfg: Option<Attribute>,
+    pub fat_arrow_token: Token![=>],
+}
+
+impl Arrow {
+    /// The predicate of the arrow's `#[cfg(...)]` attribute.
+    pub fn cfg_predicate(&self) -> Option<&TokenStream2> {
+        cfg_predicate(self.cfg.as_ref()?)
+    }
+
+    /// Parse an arrow if the input starts with one, with or without a `#[cfg(...)]` attribute.
+    fn try_parse(input: ParseStream) -> std::result::Result<Option<Self>, TreeParseError> {
+        if input.peek(Token![#]) {
+            let mut attrs = input.call(Attribute::parse_outer)?.into_iter();
+            let attr = attrs.next().unwrap();
+            if let Some(extra) = attrs.next() {
+                return Err(TreeParseError::InvalidArrowAttribute(extra.pound_token.span));
+            }
+            if !is_cfg(&attr) {
+                return Err(TreeParseError::InvalidArrowAttribute(attr.pound_token.span));
+            }
+            if !input.peek(Token![=>]) || peek_fan_in(input) {
+                return Err(TreeParseError::InvalidArrowAttribute(attr.pound_token.span));
+            }
+            Ok(Some(Self { cfg: Some(attr), fat_arrow_token: input.parse()? }))
+        } else if input.peek(Token![=>]) && !peek_fan_in(input) {
+            Ok(Some(Self { cfg: None, fat_arrow_token: input.parse()? }))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// The predicate of a `#[cfg(...)]` attribute.
+pub(crate) fn cfg_predicate(cfg: &Attribute) -> Option<&TokenStream2> {
+    match &cfg.meta {
+        Meta::List(list) => Some(&list.tokens),
+        _ => None,
+    }
+}
+
+/// Whether `attr` is a `#[cfg(...)]` attribute.
+fn is_cfg(attr: &Attribute) -> bool {
+    matches!(&attr.meta, Meta::List(list) if list.path.is_ident("cfg"))
+}
+
+impl ToTokens for Arrow {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        if let Some(cfg) = &self.cfg {
+            cfg.to_tokens(tokens);
+        }
+        self.fat_arrow_token.to_tokens(tokens);
+    }
 }
 
 impl Branch {
@@ -33,6 +97,48 @@ impl Branch {
         }
     }
 
+    pub fn node_mut(&mut self) -> &mut Node {
+        match self {
+            Self::Leaf(node) | Self::Arm(node, _, _) | Self::Tree(node, _, _) => node,
+        }
+    }
+
+    /// The number of nodes on the longest path from this branch's node to a leaf, inclusive.
+    pub fn depth(&self) -> usize {
+        match self {
+            Self::Leaf(_) => 1,
+            Self::Arm(_, _, child) => 1 + child.depth(),
+            Self::Tree(_, _, child) => 1 + child.depth(),
+        }
+    }
+
+    /// The total number of nodes in this branch.
+    pub fn node_count(&self) -> usize {
+        match self {
+            Self::Leaf(_) => 1,
+            Self::Arm(_, _, child) => 1 + child.node_count(),
+            Self::Tree(_, _, child) => 1 + child.node_count(),
+        }
+    }
+
+    /// The `@` of the first spliced tree in this branch, if any.
+    pub fn splice_token(&self) -> Option<Token![@]> {
+        match self {
+            Self::Leaf(node) => node.splice_token,
+            Self::Arm(node, _, child) => node.splice_token.or_else(|| child.splice_token()),
+            Self::Tree(node, _, child) => node.splice_token.or_else(|| child.splice_token()),
+        }
+    }
+
+    /// The `@depth(n)` annotation of the first pinned node in this branch, if any.
+    pub fn depth_pin(&self) -> Option<&DepthPin> {
+        match self {
+            Self::Leaf(node) => node.depth_pin.as_ref(),
+            Self::Arm(node, _, child) => node.depth_pin.as_ref().or_else(|| child.depth_pin()),
+            Self::Tree(node, _, child) => node.depth_pin.as_ref().or_else(|| child.depth_pin()),
+        }
+    }
+
     pub fn sub_tree_mut(&mut self) -> Option<&mut Tree> {
         match self {
             Self::Tree(_, _, sub_tree) => Some(sub_tree),
@@ -47,23 +153,202 @@ impl From<Path> for Branch {
     }
 }
 
-impl Parse for Branch {
-    fn parse(input: ParseStream) -> Result<Self> {
+impl Branch {
+    /// Parse a branch, reporting failures as a structured [`TreeParseError`].
+    pub fn try_parse(input: ParseStream) -> std::result::Result<Self, TreeParseError> {
+        Self::try_parse_with(input, false)
+    }
+
+    /// Parse a branch whose groups may be `relaxed`, i.e. don't require a `,` between branches.
+    pub(crate) fn try_parse_with(
+        input: ParseStream,
+        relaxed: bool,
+    ) -> std::result::Result<Self, TreeParseError> {
+        if input.peek(Token![#]) {
+            let attr = input.call(Attribute::parse_outer)?.remove(0);
+            return Err(TreeParseError::InvalidBranchAttribute(attr.pound_token.span));
+        }
+        if peek_chain(input) {
+            return Self::try_parse_chain(input, relaxed);
+        }
+
         let node = input.parse()?;
+        Self::try_parse_children(node, input, relaxed)
+    }
 
-        Ok(if input.peek(Token![=>]) {
-            let fat_arrow_token = input.parse()?;
-            if input.peek(Brace) {
-                let brace_contents;
-                braced!(brace_contents in input);
-                Self::Tree(node, fat_arrow_token, brace_contents.call(Tree::parse)?)
-            } else {
-                Self::Arm(node, fat_arrow_token, Box::new(input.parse()?))
+    /// Parse the children of `node`, if any.
+    fn try_parse_children(
+        node: Node,
+        input: ParseStream,
+        relaxed: bool,
+    ) -> std::result::Result<Self, TreeParseError> {
+        Ok(match Arrow::try_parse(input)? {
+            Some(_) if node.is_splice() => {
+                return Err(TreeParseError::SplicedParent(node.splice_token.unwrap().span));
+            }
+            Some(arrow) => {
+                let parent = node.to_token_stream().to_string();
+                Self::try_parse_child(node, arrow, &parent, input, relaxed)?
             }
+            None => Self::Leaf(node),
+        })
+    }
+
+    /// Parse the child after the `arrow` of `node`. `parent` are the tokens of the parent for
+    /// error messages.
+    fn try_parse_child(
+        node: Node,
+        arrow: Arrow,
+        parent: &str,
+        input: ParseStream,
+        relaxed: bool,
+    ) -> std::result::Result<Self, TreeParseError> {
+        if input.is_empty() {
+            let span = arrow.fat_arrow_token.spans[0];
+            return Err(TreeParseError::DanglingArrow(span, parent.to_owned()));
+        }
+        Ok(if peek_group(input) {
+            Self::Tree(node, arrow, Self::try_parse_group(input, relaxed)?)
+        } else {
+            Self::Arm(node, arrow, Box::new(Self::try_parse_with(input, relaxed)?))
+        })
+    }
+
+    /// Parse `{ a, b } => c` or `par![a, b] => c`, where `c` runs after all nodes of the group.
+    ///
+    /// Returns the group with a chain of `pure` placeholders, one for each depth of the group,
+    /// added to it that leads to the children of the group. Without a `=>`, the braces only group
+    /// the branches.
+    pub(crate) fn try_parse_group(
+        input: ParseStream,
+        relaxed: bool,
+    ) -> std::result::Result<Tree, TreeParseError> {
+        let (mut group, parent) = if peek_par(input) {
+            (Self::try_parse_par(input)?, "par![..]")
         } else {
-            Self::Leaf(node)
+            (Tree::try_parse_branches(&parse_braced(input)?, false, relaxed)?, "{ .. }")
+        };
+
+        if let Some(arrow) = Arrow::try_parse(input)? {
+            if let Some(splice_token) = group.splice_token() {
+                return Err(TreeParseError::SplicedParent(splice_token.span));
+            }
+            if let Some(depth_pin) = group.depth_pin() {
+                return Err(TreeParseError::PinnedGroupParent(depth_pin.at_token.span));
+            }
+            let depth = group.depth();
+            let placeholder = || Node::new_pure(placeholder_expr());
+            let branch = Self::try_parse_child(placeholder(), arrow, parent, input, relaxed)?;
+            if !group.branches.empty_or_trailing() {
+                group.branches.push_punct(Default::default());
+            }
+            group
+                .branches
+                .push_value((1..depth).fold(branch, |child, _| Self::arm(placeholder(), child)));
+        }
+
+        Ok(group)
+    }
+
+    /// Parse `chain![a, b, c]` into the equivalent of `a => b => c`.
+    ///
+    /// Any children of the chain are children of its last node.
+    fn try_parse_chain(
+        input: ParseStream,
+        relaxed: bool,
+    ) -> std::result::Result<Self, TreeParseError> {
+        let chain_ident: Ident = input.parse()?;
+        let _: Token![!] = input.parse()?;
+        let bracket_contents = parse_bracketed(input)?;
+        let nodes = Punctuated::<Node, Token![,]>::parse_terminated(&bracket_contents)?;
+
+        let mut nodes = nodes.into_iter().rev();
+        let Some(last_node) = nodes.next() else {
+            return Err(TreeParseError::EmptyChain(chain_ident.span()));
+        };
+
+        let branch = Self::try_parse_children(last_node, input, relaxed)?;
+        nodes.try_fold(branch, |child, node| match node.splice_token {
+            Some(splice_token) => Err(TreeParseError::SplicedParent(splice_token.span)),
+            None => Ok(Self::arm(node, child)),
         })
     }
+
+    /// Parse `par![a, b, c]` into the equivalent of `{ a, b, c }`, the nodes all run at the same
+    /// depth.
+    fn try_parse_par(input: ParseStream) -> std::result::Result<Tree, TreeParseError> {
+        let _: Ident = input.parse()?;
+        let _: Token![!] = input.parse()?;
+        let bracket_contents = parse_bracketed(input)?;
+        if bracket_contents.is_empty() {
+            return Err(TreeParseError::EmptyTree(bracket_contents.span()));
+        }
+        let nodes = Punctuated::<Node, Token![,]>::parse_terminated(&bracket_contents)?;
+
+        let trailing_comma = nodes.trailing_punct();
+        Ok(Tree::from_branches(nodes.into_iter().map(Self::leaf).collect(), trailing_comma))
+    }
+}
+
+/// Parse the `#[cfg(...)]` attribute of a top-level branch, if any.
+pub(crate) fn try_parse_branch_cfg(
+    input: ParseStream,
+) -> std::result::Result<Option<Attribute>, TreeParseError> {
+    if !input.peek(Token![#]) {
+        return Ok(None);
+    }
+    let mut attrs = input.call(Attribute::parse_outer)?.into_iter();
+    let attr = attrs.next().unwrap();
+    if let Some(extra) = attrs.next() {
+        return Err(TreeParseError::InvalidBranchAttribute(extra.pound_token.span));
+    }
+    if !is_cfg(&attr) {
+        return Err(TreeParseError::InvalidBranchAttribute(attr.pound_token.span));
+    }
+    Ok(Some(attr))
+}
+
+fn peek_chain(input: ParseStream) -> bool {
+    let fork = input.fork();
+    fork.parse::<Ident>().is_ok_and(|ident| ident == "chain")
+        && fork.peek(Token![!])
+        && fork.peek2(Bracket)
+}
+
+fn peek_par(input: ParseStream) -> bool {
+    let fork = input.fork();
+    fork.parse::<Ident>().is_ok_and(|ident| ident == "par")
+        && fork.peek(Token![!])
+        && fork.peek2(Bracket)
+}
+
+/// Whether the input starts with a group of branches, i.e. `{ .. }` or `par![..]`.
+pub(crate) fn peek_group(input: ParseStream) -> bool {
+    input.peek(Brace) || peek_par(input)
+}
+
+/// Whether the input starts with the `=>>` of a fan-in node.
+pub(crate) fn peek_fan_in(input: ParseStream) -> bool {
+    let fork = input.fork();
+    fork.parse::<Token![=>]>().is_ok() && fork.peek(Token![>])
+}
+
+fn parse_braced<'a>(input: ParseStream<'a>) -> Result<ParseBuffer<'a>> {
+    let brace_contents;
+    braced!(brace_contents in input);
+    Ok(brace_contents)
+}
+
+fn parse_bracketed<'a>(input: ParseStream<'a>) -> Result<ParseBuffer<'a>> {
+    let bracket_contents;
+    bracketed!(bracket_contents in input);
+    Ok(bracket_contents)
+}
+
+impl Parse for Branch {
+    fn parse(input: ParseStream) -> Result<Self> {
+        Self::try_parse(input).map_err(Into::into)
+    }
 }
 
 #[cfg(debug_assertions)]
@@ -83,19 +368,18 @@ impl std::fmt::Debug for Branch {
     }
 }
 
-#[cfg(debug_assertions)]
 impl std::fmt::Display for Branch {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         std::fmt::Display::fmt(&self.node(), f)?;
 
         match self {
             Branch::Leaf(_) => {}
-            Branch::Arm(_, _, child) => {
-                f.write_str(" => ")?;
+            Branch::Arm(_, arrow, child) => {
+                write!(f, " {} ", arrow.to_token_stream())?;
                 std::fmt::Display::fmt(child, f)?;
             }
-            Branch::Tree(_, _, child) => {
-                f.write_str(" => ")?;
+            Branch::Tree(_, arrow, child) => {
+                write!(f, " {} ", arrow.to_token_stream())?;
                 std::fmt::Display::fmt(child, f)?;
             }
         }
@@ -0,0 +1,111 @@
+use std::fmt::Write;
+
+use quote::ToTokens;
+
+use crate::{Branch, Node, Tree};
+
+impl Tree {
+    /// Render the tree as a [graphviz](https://graphviz.org) digraph in the DOT language.
+    ///
+    /// Each node of the tree is a vertex labeled with its tokens, each `=>` is an edge from the
+    /// parent to the child, and the nodes of each layer are grouped in a cluster.
+    pub fn to_dot(&self) -> String {
+        let mut graph = DotGraph::default();
+        graph.add_tree(self, None, 0);
+        graph.finish()
+    }
+}
+
+#[derive(Default)]
+struct DotGraph {
+    layers: Vec<Vec<(usize, String)>>,
+    edges: Vec<(usize, usize)>,
+    node_count: usize,
+}
+
+impl DotGraph {
+    fn add_tree(&mut self, tree: &Tree, parent: Option<usize>, depth: usize) {
+        for branch in &tree.branches {
+            self.add_branch(branch, parent, depth);
+        }
+    }
+
+    fn add_branch(&mut self, branch: &Branch, parent: Option<usize>, depth: usize) {
+        let id = self.add_node(branch.node(), depth);
+        if let Some(parent) = parent {
+            self.edges.push((parent, id));
+        }
+        match branch {
+            Branch::Leaf(_) => {}
+            Branch::Arm(_, _, child) => self.add_branch(child, Some(id), depth + 1),
+            Branch::Tree(_, _, sub_tree) => self.add_tree(sub_tree, Some(id), depth + 1),
+        }
+    }
+
+    fn add_node(&mut self, node: &Node, depth: usize) -> usize {
+        let id = self.node_count;
+        self.node_count += 1;
+        if depth >= self.layers.len() {
+            self.layers.resize_with(depth + 1, Vec::new);
+        }
+        let label = node.to_token_stream().to_string();
+        self.layers[depth].push((id, label.replace('\\', "\\\\").replace('"', "\\\"")));
+        id
+    }
+
+    fn finish(self) -> String {
+        let mut dot = String::from("digraph startup_tree {\n");
+        for (depth, nodes) in self.layers.iter().enumerate() {
+            writeln!(dot, "    subgraph cluster_layer_{depth} {{").unwrap();
+            writeln!(dot, "        label = \"layer {depth}\";").unwrap();
+            for (id, label) in nodes {
+                writeln!(dot, "        n{id} [label = \"{label}\"];").unwrap();
+            }
+            dot.push_str("    }\n");
+        }
+        for (parent, child) in &self.edges {
+            writeln!(dot, "    n{parent} -> n{child};").unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quote::quote;
+    use syn::parse2;
+
+    use crate::Tree;
+
+    #[test]
+    fn renders_tree_as_dot() {
+        let tree: Tree = parse2(quote! {
+            a => { b, "named": c => d },
+            pure e,
+        })
+        .unwrap();
+
+        let expected = r#"digraph startup_tree {
+    subgraph cluster_layer_0 {
+        label = "layer 0";
+        n0 [label = "a"];
+        n4 [label = "pure e"];
+    }
+    subgraph cluster_layer_1 {
+        label = "layer 1";
+        n1 [label = "b"];
+        n2 [label = "\"named\" : c"];
+    }
+    subgraph cluster_layer_2 {
+        label = "layer 2";
+        n3 [label = "d"];
+    }
+    n0 -> n1;
+    n0 -> n2;
+    n2 -> n3;
+}
+"#;
+        assert_eq!(tree.to_dot(), expected);
+    }
+}
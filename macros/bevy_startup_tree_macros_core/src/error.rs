@@ -0,0 +1,175 @@
+use std::fmt;
+
+use proc_macro2::Span;
+
+/// An error encountered while parsing a tree.
+///
+/// The [`Display`](fmt::Display) output of each variant is the message reported to users of the
+/// macros. Convert into a [`syn::Error`] to report the error at its span.
+#[derive(Debug)]
+pub enum TreeParseError {
+    /// The tree or one of its subtrees has no branches.
+    EmptyTree(Span),
+    /// Two branches are not separated by a comma.
+    ExpectedComma(Span),
+    /// A fat arrow is not followed by a child. Contains the tokens of the parent node.
+    DanglingArrow(Span, String),
+    /// A `chain![]` has no nodes.
+    EmptyChain(Span),
+    /// An attribute before a `=>` is not a `#[cfg(...)]` attribute followed by a `=>`.
+    InvalidArrowAttribute(Span),
+    /// An attribute before a branch is not a single `#[cfg(...)]` attribute, or the branch is not
+    /// a top-level branch.
+    InvalidBranchAttribute(Span),
+    /// The tree has more distinct `#[cfg(...)]` attributes than [`MAX_CFGS`](crate::MAX_CFGS), each
+    /// of which doubles the code generated for the tree. Contains the number of attributes.
+    TooManyCfgs(Span, usize),
+    /// Nodes at the same depth give the layer different names. Contains the first name and the
+    /// conflicting one.
+    ConflictingLayerNames(Span, String, String),
+    /// Nodes at different depths give their layers the same name. Contains the name.
+    DuplicateLayerName(Span, String),
+    /// Two nodes have the same label. Contains the label.
+    DuplicateNodeLabel(Span, String),
+    /// Ordering a node against the labeled node creates a cycle, either with other orderings or
+    /// with the layers of the tree. Contains the label.
+    OrderingCycle(Span, String),
+    /// A node is ordered against a labeled node that a `#[cfg]` branch leaves out while the
+    /// ordered node is kept. Contains the label.
+    CfgLabel(Span, String),
+    /// A node follows a spliced tree, e.g. `@sub => a`, or the fan-in node of a tree with a
+    /// spliced tree.
+    SplicedParent(Span),
+    /// A node is pinned with `@depth(n)` to a depth before the depth after its parent. Contains the
+    /// pinned depth and the first depth after the parent.
+    ShallowDepthPin(Span, usize, usize),
+    /// A `{ .. } => child` group contains a node pinned with `@depth(n)`, so its children can't
+    /// know when the group is done.
+    PinnedGroupParent(Span),
+    /// A spliced tree is part of a startup teardown tree, whose layers must have the same shape in
+    /// both trees.
+    SplicedTeardown(Span),
+    /// A spliced tree is part of a tree with generated layer sets, whose number of layers must be
+    /// known at compile time.
+    SplicedLayerSets(Span),
+    /// A node of a strict tree is not a path to a system, e.g. a closure or a method call.
+    NonPathNode(Span),
+    /// A tree file is not valid RON or does not describe a tree. Contains the reason.
+    #[cfg(feature = "file")]
+    Ron(Span, String),
+    /// A system name in a tree file is not a valid path. Contains the name.
+    #[cfg(feature = "file")]
+    InvalidSystemName(Span, String),
+    /// Any other error, e.g. a node that is not a valid expression.
+    Syn(syn::Error),
+}
+
+impl TreeParseError {
+    /// The span at which the error occurred.
+    pub fn span(&self) -> Span {
+        match self {
+            Self::EmptyTree(span)
+            | Self::ExpectedComma(span)
+            | Self::DanglingArrow(span, _)
+            | Self::EmptyChain(span)
+            | Self::InvalidArrowAttribute(span)
+            | Self::InvalidBranchAttribute(span)
+            | Self::TooManyCfgs(span, _)
+            | Self::ConflictingLayerNames(span, _, _)
+            | Self::DuplicateLayerName(span, _)
+            | Self::DuplicateNodeLabel(span, _)
+            | Self::OrderingCycle(span, _)
+            | Self::CfgLabel(span, _)
+            | Self::SplicedParent(span)
+            | Self::ShallowDepthPin(span, _, _)
+            | Self::PinnedGroupParent(span)
+            | Self::SplicedTeardown(span)
+            | Self::SplicedLayerSets(span)
+            | Self::NonPathNode(span) => *span,
+            #[cfg(feature = "file")]
+            Self::Ron(span, _) | Self::InvalidSystemName(span, _) => *span,
+            Self::Syn(err) => err.span(),
+        }
+    }
+}
+
+impl fmt::Display for TreeParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::EmptyTree(_) => f.write_str("tree may not be empty"),
+            Self::ExpectedComma(_) => f.write_str("expected `,`"),
+            Self::DanglingArrow(_, parent) => write!(f, "expected a child after `{parent} =>`"),
+            Self::EmptyChain(_) => f.write_str("chain may not be empty"),
+            Self::InvalidArrowAttribute(_) => {
+                f.write_str("only a `#[cfg(...)]` attribute followed by `=>` is allowed here")
+            }
+            Self::InvalidBranchAttribute(_) => f.write_str(
+                "only a single `#[cfg(...)]` attribute on a top-level branch is allowed",
+            ),
+            Self::TooManyCfgs(_, count) => write!(
+                f,
+                "a tree can have at most {} distinct `#[cfg(...)]` attributes, found {count}",
+                crate::MAX_CFGS
+            ),
+            Self::ConflictingLayerNames(_, name, conflicting) => {
+                write!(
+                    f,
+                    "layer is already named \"{name}\", it can't also be named \"{conflicting}\""
+                )
+            }
+            Self::DuplicateLayerName(_, name) => {
+                write!(f, "layer name \"{name}\" is already used by another layer")
+            }
+            Self::DuplicateNodeLabel(_, label) => {
+                write!(f, "node label `{label}` is already used by another node")
+            }
+            Self::CfgLabel(_, label) => {
+                write!(f, "node labeled `{label}` is left out by a `#[cfg]` that this node isn't")
+            }
+            Self::OrderingCycle(_, label) => {
+                write!(f, "ordering against `{label}` creates a cycle")
+            }
+            Self::SplicedParent(_) => f.write_str(
+                "nothing can run after a spliced tree, its depth is only known at runtime",
+            ),
+            Self::ShallowDepthPin(_, depth, min_depth) => write!(
+                f,
+                "node can't be pinned to depth {depth}, it must run at depth {min_depth} or later to run after its parent"
+            ),
+            Self::PinnedGroupParent(_) => {
+                f.write_str("a group with children can't contain nodes pinned with `@depth(..)`")
+            }
+            Self::SplicedTeardown(_) => {
+                f.write_str("a startup teardown tree can't contain spliced trees")
+            }
+            Self::SplicedLayerSets(_) => {
+                f.write_str("a tree with generated layer sets can't contain spliced trees")
+            }
+            Self::NonPathNode(_) => {
+                f.write_str("only paths to systems are allowed as nodes of a strict tree")
+            }
+            #[cfg(feature = "file")]
+            Self::Ron(_, reason) => write!(f, "invalid tree file: {reason}"),
+            #[cfg(feature = "file")]
+            Self::InvalidSystemName(_, name) => write!(f, "`{name}` is not a valid system name"),
+            Self::Syn(err) => fmt::Display::fmt(err, f),
+        }
+    }
+}
+
+impl std::error::Error for TreeParseError {}
+
+impl From<syn::Error> for TreeParseError {
+    fn from(err: syn::Error) -> Self {
+        Self::Syn(err)
+    }
+}
+
+impl From<TreeParseError> for syn::Error {
+    fn from(err: TreeParseError) -> Self {
+        match err {
+            TreeParseError::Syn(err) => err,
+            err => syn::Error::new(err.span(), err),
+        }
+    }
+}
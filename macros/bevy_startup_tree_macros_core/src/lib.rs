@@ -1,10 +1,29 @@
 mod branch;
 pub use branch::*;
 
+#[cfg(feature = "graphviz")]
+mod dot;
+mod error;
 mod node;
 mod tree;
+#[cfg(feature = "file")]
+mod tree_file;
+mod tree_fn;
+mod tree_layers;
+mod tree_meta;
+mod tree_relaxed;
+mod tree_sets;
+mod tree_shape;
+mod tree_strict;
+mod tree_teardown;
 
-pub use self::{node::*, tree::*};
+pub use self::{
+    error::*, node::*, tree::*, tree_fn::*, tree_layers::*, tree_meta::*, tree_relaxed::*,
+    tree_sets::*, tree_shape::*, tree_strict::*, tree_teardown::*,
+};
+
+#[cfg(feature = "file")]
+pub use self::tree_file::*;
 
 #[cfg(test)]
 mod test_utils;
@@ -1,23 +1,539 @@
-use proc_macro2::TokenStream as TokenStream2;
-use quote::{quote, ToTokens};
+use proc_macro2::{Spacing, TokenStream as TokenStream2};
+use quote::{quote, quote_spanned, ToTokens};
 use syn::{
-    parse::{Parse, ParseStream},
-    Expr, ExprPath, Path, Result,
+    braced,
+    buffer::Cursor,
+    parenthesized,
+    parse::{Parse, ParseStream, Parser},
+    parse_quote,
+    punctuated::Punctuated,
+    spanned::Spanned,
+    token::{Brace, Paren},
+    Attribute, BinOp, Expr, ExprBinary, ExprCast, ExprMethodCall, ExprParen, ExprPath, Ident,
+    LitInt, LitStr, Path, Result, Token, Type, TypePath,
 };
 
-#[derive(PartialEq)]
-pub struct Node(Expr);
+use crate::TreeParseError;
+
+pub mod kw {
+    syn::custom_keyword!(pure);
+    syn::custom_keyword!(flush_after);
+    syn::custom_keyword!(pipe);
+    syn::custom_keyword!(depth);
+}
+
+#[derive(Clone, PartialEq)]
+pub struct Node {
+    /// Names the layer that contains the node, e.g. `"load": sys`.
+    pub layer_name: Option<(LitStr, Token![:])>,
+    /// Marks a node whose startup tasks must complete before the next depth runs.
+    pub async_token: Option<Token![async]>,
+    /// Marks a node that doesn't use deferred system parameters like `Commands`.
+    pub pure_token: Option<kw::pure>,
+    /// Marks a node whose deferred commands are flushed right after it runs.
+    pub flush_after_token: Option<kw::flush_after>,
+    pub expr: Expr,
+    /// Orderings against other nodes by label, e.g. `sys.after(setup)`.
+    ///
+    /// These are taken out of the expression once the labels of the whole tree are known, see
+    /// [`resolve_orderings`](Self::resolve_orderings).
+    pub orderings: Vec<(NodeOrdering, Ident)>,
+    /// Labels the node so other nodes can be ordered against it, e.g. `sys as setup`.
+    pub label: Option<(Token![as], Ident)>,
+    /// A system set that the node's systems are added to, so that systems outside of the tree can
+    /// be ordered against them, e.g. `spawn_player in PlayerSet`.
+    pub in_set: Option<(Token![in], Expr)>,
+    /// The systems of a `pipe { a => b }` node, whose [`expr`](Self::expr) pipes them together.
+    pub pipe: Option<NodePipe>,
+    /// Marks a node whose expression is another tree whose layers are spliced in at the node's
+    /// depth, e.g. `@sub`.
+    pub splice_token: Option<Token![@]>,
+    /// The `#[cfg(...)]` attribute of a top-level branch, which is left out of the tree along with
+    /// this node when the predicate is false, e.g. `#[cfg(feature = "web")] sys => child`.
+    pub cfg: Option<Attribute>,
+    /// Pins the node to a depth regardless of its position in the tree, e.g. `@depth(2) sys`.
+    pub depth_pin: Option<DepthPin>,
+}
+
+/// A `@depth(n)` annotation that puts a node in the layer at depth `n`.
+///
+/// The children of the node are at the depths after `n`.
+#[derive(Clone, PartialEq)]
+pub struct DepthPin {
+    pub at_token: Token![@],
+    pub depth_token: kw::depth,
+    pub paren_token: Paren,
+    pub depth: LitInt,
+}
+
+impl DepthPin {
+    /// The depth that the node is pinned to.
+    pub fn depth(&self) -> usize {
+        self.depth.base10_parse().unwrap()
+    }
+}
+
+impl Parse for DepthPin {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let content;
+        let this = Self {
+            at_token: input.parse()?,
+            depth_token: input.parse()?,
+            paren_token: parenthesized!(content in input),
+            depth: content.parse()?,
+        };
+        this.depth.base10_parse::<usize>()?;
+        if !content.is_empty() {
+            return Err(content.error("expected `)`"));
+        }
+        Ok(this)
+    }
+}
+
+impl ToTokens for DepthPin {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        self.at_token.to_tokens(tokens);
+        self.depth_token.to_tokens(tokens);
+        self.paren_token.surround(tokens, |tokens| self.depth.to_tokens(tokens));
+    }
+}
+
+/// A `pipe { a => b =?> c }` block, where the output of each system is the input of the next.
+///
+/// The systems are combined with `IntoSystem::pipe` into a single system.
+#[derive(Clone, PartialEq)]
+pub struct NodePipe {
+    pub pipe_token: kw::pipe,
+    pub brace_token: Brace,
+    pub systems: Punctuated<Expr, PipeArrow>,
+}
+
+impl NodePipe {
+    /// The expression that pipes the systems together.
+    pub fn to_expr(&self) -> Expr {
+        let mut pairs = self.systems.pairs();
+        let first = pairs.next().unwrap();
+        let mut piped = Expr::clone(first.value());
+        let mut arrow = first.punct().copied();
+        for pair in pairs {
+            let system = match arrow {
+                Some(PipeArrow::Optional(..)) => {
+                    let system = pair.value();
+                    quote! { ::bevy_startup_tree::optional_step(#system) }
+                }
+                _ => pair.value().to_token_stream(),
+            };
+            piped = parse_quote! { ::bevy::prelude::IntoSystem::pipe(#piped, #system) };
+            arrow = pair.punct().copied();
+        }
+        piped
+    }
+}
+
+impl Parse for NodePipe {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let pipe_token = input.parse()?;
+        let content;
+        let brace_token = braced!(content in input);
+        if content.is_empty() {
+            return Err(syn::Error::new(brace_token.span.join(), "pipe may not be empty"));
+        }
+        let mut systems = Punctuated::new();
+        loop {
+            systems.push_value(parse_pipe_system(&content)?);
+            if content.is_empty() {
+                break;
+            }
+            systems.push_punct(content.parse()?);
+        }
+        Ok(Self { pipe_token, brace_token, systems })
+    }
+}
+
+/// Parse the expression of a pipe system, up to the next [`PipeArrow`].
+///
+/// The tokens are split off before parsing the expression, as the `=` of a `=?>` would otherwise
+/// be parsed as an assignment.
+fn parse_pipe_system(input: ParseStream) -> Result<Expr> {
+    let tokens = input.step(|cursor| {
+        let mut tokens = TokenStream2::new();
+        let mut rest = *cursor;
+        while !rest.eof() && !PipeArrow::peek_cursor(rest) {
+            let (tt, next) = rest.token_tree().unwrap();
+            tokens.extend([tt]);
+            rest = next;
+        }
+        Ok((tokens, rest))
+    })?;
+    let parser = |input: ParseStream| {
+        let expr = input.parse()?;
+        if !input.is_empty() {
+            return Err(input.error("expected `=>`"));
+        }
+        Ok(expr)
+    };
+    if tokens.is_empty() {
+        // Report the missing expression at the arrow or the end of the pipe.
+        return input.parse();
+    }
+    parser.parse2(tokens)
+}
+
+/// The link between two systems of a pipe.
+#[derive(Clone, Copy, PartialEq)]
+pub enum PipeArrow {
+    /// `=>`: the next system always runs with the output of the previous one.
+    Required(Token![=>]),
+    /// `=?>`: the next system returns a `Result`, and its input is passed on if it fails.
+    Optional(Token![=], Token![?], Token![>]),
+}
+
+impl PipeArrow {
+    fn peek_cursor(cursor: Cursor) -> bool {
+        let Some((eq, rest)) = cursor.punct() else {
+            return false;
+        };
+        if eq.as_char() != '=' {
+            return false;
+        }
+        let next = match rest.punct() {
+            Some((q, rest)) if q.as_char() == '?' => rest.punct(),
+            next if eq.spacing() == Spacing::Joint => next,
+            _ => return false,
+        };
+        matches!(next, Some((gt, _)) if gt.as_char() == '>')
+    }
+}
+
+impl Default for PipeArrow {
+    fn default() -> Self {
+        Self::Required(Default::default())
+    }
+}
+
+impl Parse for PipeArrow {
+    fn parse(input: ParseStream) -> Result<Self> {
+        if input.peek(Token![=]) && input.peek2(Token![?]) {
+            Ok(Self::Optional(input.parse()?, input.parse()?, input.parse()?))
+        } else {
+            Ok(Self::Required(input.parse()?))
+        }
+    }
+}
+
+impl ToTokens for PipeArrow {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        match self {
+            Self::Required(arrow) => arrow.to_tokens(tokens),
+            Self::Optional(eq, question, gt) => {
+                eq.to_tokens(tokens);
+                question.to_tokens(tokens);
+                gt.to_tokens(tokens);
+            }
+        }
+    }
+}
+
+impl ToTokens for NodePipe {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        self.pipe_token.to_tokens(tokens);
+        self.brace_token.surround(tokens, |tokens| self.systems.to_tokens(tokens));
+    }
+}
+
+/// The direction of an ordering between two nodes of a tree.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NodeOrdering {
+    Before,
+    After,
+}
+
+impl NodeOrdering {
+    fn from_method(method: &Ident) -> Option<Self> {
+        if method == "before" {
+            Some(Self::Before)
+        } else if method == "after" {
+            Some(Self::After)
+        } else {
+            None
+        }
+    }
+
+    fn method(self) -> &'static str {
+        match self {
+            Self::Before => "before",
+            Self::After => "after",
+        }
+    }
+}
 
 impl Node {
     pub fn new(expr: Expr) -> Self {
-        Self(expr)
+        Self {
+            layer_name: None,
+            async_token: None,
+            pure_token: None,
+            flush_after_token: None,
+            expr,
+            orderings: Vec::new(),
+            label: None,
+            in_set: None,
+            pipe: None,
+            splice_token: None,
+            cfg: None,
+            depth_pin: None,
+        }
+    }
+
+    pub fn new_async(expr: Expr) -> Self {
+        Self { async_token: Some(Default::default()), ..Self::new(expr) }
+    }
+
+    pub fn new_pure(expr: Expr) -> Self {
+        Self { pure_token: Some(Default::default()), ..Self::new(expr) }
+    }
+
+    pub fn new_flush_after(expr: Expr) -> Self {
+        Self { flush_after_token: Some(Default::default()), ..Self::new(expr) }
+    }
+
+    pub fn new_splice(expr: Expr) -> Self {
+        Self { splice_token: Some(Default::default()), ..Self::new(expr) }
+    }
+
+    pub fn with_layer_name(self, name: LitStr) -> Self {
+        Self { layer_name: Some((name, Default::default())), ..self }
+    }
+
+    pub fn with_label(self, label: Ident) -> Self {
+        Self { label: Some((Default::default(), label)), ..self }
+    }
+
+    pub fn label(&self) -> Option<&Ident> {
+        self.label.as_ref().map(|(_, label)| label)
+    }
+
+    /// Take the `.before(label)` and `.after(label)` calls on the expression whose argument is one
+    /// of `labels` out of the expression and into [`orderings`](Self::orderings).
+    ///
+    /// Other calls are left in place, so e.g. `.after(system)` is still a regular Bevy ordering.
+    pub fn resolve_orderings(&mut self, labels: &[&Ident]) {
+        if self.is_splice() {
+            return;
+        }
+
+        fn resolve(
+            expr: Expr,
+            labels: &[&Ident],
+            orderings: &mut Vec<(NodeOrdering, Ident)>,
+        ) -> Expr {
+            let Expr::MethodCall(mut call) = expr else {
+                return expr;
+            };
+            *call.receiver = resolve(*call.receiver, labels, orderings);
+            match as_label_ordering(&call, labels) {
+                Some(ordering) => {
+                    orderings.push(ordering);
+                    *call.receiver
+                }
+                None => Expr::MethodCall(call),
+            }
+        }
+
+        let expr = std::mem::replace(&mut self.expr, Expr::Verbatim(TokenStream2::new()));
+        self.expr = resolve(expr, labels, &mut self.orderings);
+    }
+
+    /// Split a `setup / teardown` node into its setup node and the returned teardown node.
+    ///
+    /// The teardown node takes the place of the node in the teardown tree: it keeps the layer
+    /// name, label, and depth pin of the node, but none of the annotations of its system. A node
+    /// without a teardown system is a placeholder in the teardown tree. The label may also be
+    /// given after the teardown system, e.g. `setup / teardown as label`.
+    pub fn split_teardown(&mut self) -> std::result::Result<Self, TreeParseError> {
+        if let Some(splice_token) = self.splice_token {
+            return Err(TreeParseError::SplicedTeardown(splice_token.span));
+        }
+
+        let expr = std::mem::replace(&mut self.expr, placeholder_expr());
+        let expr = match expr {
+            Expr::Paren(ExprParen { expr, .. }) if is_teardown_pair(&expr) => *expr,
+            expr => expr,
+        };
+        let teardown = match expr {
+            Expr::Binary(ExprBinary { left, op: BinOp::Div(_), right, .. }) => {
+                self.expr = *left;
+                match *right {
+                    Expr::Cast(cast) if self.label.is_none() => {
+                        let (teardown, label) = split_label(cast)?;
+                        self.label = label;
+                        teardown
+                    }
+                    teardown => teardown,
+                }
+            }
+            expr => {
+                self.expr = expr;
+                placeholder_expr()
+            }
+        };
+
+        Ok(Self {
+            layer_name: self.layer_name.clone(),
+            label: self.label.clone(),
+            cfg: self.cfg.clone(),
+            depth_pin: self.depth_pin.clone(),
+            ..Self::new(teardown)
+        })
+    }
+
+    pub fn layer_name(&self) -> Option<&LitStr> {
+        self.layer_name.as_ref().map(|(name, _)| name)
+    }
+
+    pub fn is_async(&self) -> bool {
+        self.async_token.is_some()
+    }
+
+    pub fn is_pure(&self) -> bool {
+        self.pure_token.is_some()
+    }
+
+    pub fn is_flush_after(&self) -> bool {
+        self.flush_after_token.is_some()
+    }
+
+    /// Whether the node splices the layers of another tree into this one.
+    pub fn is_splice(&self) -> bool {
+        self.splice_token.is_some()
+    }
+
+    /// The depth that the node is pinned to with `@depth(n)`, if any.
+    pub fn pinned_depth(&self) -> Option<usize> {
+        self.depth_pin.as_ref().map(DepthPin::depth)
+    }
+
+    /// Whether the node is a `_` placeholder that has no systems.
+    pub fn is_placeholder(&self) -> bool {
+        matches!(&self.expr, Expr::Verbatim(tokens) if tokens.to_string() == "_")
+    }
+
+    /// The first expression of the node that isn't a path to a system, if any.
+    ///
+    /// Placeholders have no systems, and the systems of a `pipe` block are checked one by one.
+    pub fn non_path_expr(&self) -> Option<&Expr> {
+        match &self.pipe {
+            Some(pipe) => pipe.systems.iter().find(|expr| !matches!(expr, Expr::Path(_))),
+            None if self.is_placeholder() => None,
+            None => (!matches!(self.expr, Expr::Path(_))).then_some(&self.expr),
+        }
+    }
+
+    /// A readable name for the node's systems.
+    ///
+    /// This is the path of the system without whitespace if the node is a path, like
+    /// `spawn::<Enemy>`, and the tokens of the expression or `pipe` block otherwise.
+    pub fn name(&self) -> String {
+        if self.is_splice() {
+            return format!("@{}", self.expr.to_token_stream());
+        }
+        match (&self.pipe, &self.expr) {
+            (Some(pipe), _) => pipe.to_token_stream().to_string(),
+            (None, Expr::Path(path)) => path.to_token_stream().to_string().replace(' ', ""),
+            (None, expr) => expr.to_token_stream().to_string(),
+        }
+    }
+
+    /// Whether the node has annotations that `add_startup_tree` needs to know about.
+    pub fn has_metadata(&self) -> bool {
+        self.layer_name.is_some()
+            || self.is_pure()
+            || self.is_flush_after()
+            || self.is_placeholder()
+            || self.label.is_some()
+            || !self.orderings.is_empty()
+            || self.is_splice()
     }
 
     pub fn as_into_descriptor_call(&self) -> TokenStream2 {
-        let receiver = &self.0;
-        quote! {
-            ::bevy::prelude::IntoSystemConfigs::into_configs(#receiver)
+        self.as_into_descriptor_call_with(None, None)
+    }
+
+    /// Like [`as_into_descriptor_call`](Self::as_into_descriptor_call), with the `each` suffix
+    /// of the tree appended to the call, e.g. `.run_if(condition)`, and the systems added to the
+    /// `layer_set` of the node's depth, if any.
+    pub fn as_into_descriptor_call_with(
+        &self,
+        each: Option<&TokenStream2>,
+        layer_set: Option<&TokenStream2>,
+    ) -> TokenStream2 {
+        let receiver = &self.expr;
+        // Span the call at the node so that errors from the trait bound point at the node instead
+        // of the whole macro invocation
+        let span = receiver.span();
+        let call = if self.is_async() {
+            quote_spanned! {span=>
+                ::bevy::prelude::IntoSystemConfigs::chain(
+                    (#receiver, ::bevy_startup_tree::await_startup_tasks)
+                )
+            }
+        } else {
+            quote_spanned! {span=>
+                ::bevy::prelude::IntoSystemConfigs::into_configs(#receiver)
+            }
+        };
+        let call = match &self.in_set {
+            Some((_, set)) => quote_spanned! {set.span()=>
+                ::bevy::prelude::IntoSystemConfigs::in_set(#call, #set)
+            },
+            None => call,
+        };
+        let call = match layer_set {
+            Some(layer_set) => quote_spanned! {span=>
+                ::bevy::prelude::IntoSystemConfigs::in_set(#call, #layer_set)
+            },
+            None => call,
+        };
+        quote! { #call #each }
+    }
+
+    pub fn as_tree_node_call(&self) -> TokenStream2 {
+        self.as_tree_node_call_with(None, None)
+    }
+
+    /// Like [`as_tree_node_call`](Self::as_tree_node_call), with the `each` suffix of the tree
+    /// appended to the systems of the node and the systems added to the `layer_set` of the node's
+    /// depth, if any. Placeholders have no systems and are left as is.
+    pub fn as_tree_node_call_with(
+        &self,
+        each: Option<&TokenStream2>,
+        layer_set: Option<&TokenStream2>,
+    ) -> TokenStream2 {
+        let mut node = if self.is_placeholder() {
+            quote! { ::bevy_startup_tree::StartupTreeNode::placeholder() }
+        } else {
+            let call = self.as_into_descriptor_call_with(each, layer_set);
+            quote! { ::bevy_startup_tree::StartupTreeNode::new(#call) }
+        };
+        if self.is_pure() {
+            node = quote! { #node.pure() };
+        }
+        if self.is_flush_after() {
+            node = quote! { #node.flush_after() };
+        }
+        if let Some(name) = self.layer_name() {
+            node = quote! { #node.with_layer_name(#name) };
         }
+        if let Some(label) = self.label() {
+            let label = LitStr::new(&label.to_string(), label.span());
+            node = quote! { #node.with_label(#label) };
+        }
+        for (ordering, label) in &self.orderings {
+            let method = Ident::new(&format!("{}_label", ordering.method()), label.span());
+            let label = LitStr::new(&label.to_string(), label.span());
+            node = quote! { #node.#method(#label) };
+        }
+        node
     }
 }
 
@@ -29,31 +545,259 @@ impl From<Path> for Node {
 
 impl Parse for Node {
     fn parse(input: ParseStream) -> Result<Self> {
-        Ok(Self(input.parse()?))
+        let depth_pin = if peek_depth_pin(input) { Some(input.parse()?) } else { None };
+        Ok(Self { depth_pin, ..Self::parse_unpinned(input)? })
     }
 }
 
+impl Node {
+    /// Parse a node after its `@depth(n)` annotation, if any.
+    fn parse_unpinned(input: ParseStream) -> Result<Self> {
+        if input.peek(Token![@]) {
+            let splice_token = input.parse()?;
+            return Ok(Self { splice_token: Some(splice_token), ..Self::new(input.parse()?) });
+        }
+
+        // Annotations that are identifiers are only annotations when followed by another node,
+        // otherwise they are the path of a system with that name. A following `::` starts an
+        // absolute path unless it's a turbofish, so `pure ::a::b` is an annotated node; a module
+        // named like an annotation must be written as e.g. `self::pure::b`. A following `_` is an
+        // annotated placeholder, like the `pure _` nodes that `{ .. } => a` adds to the tree.
+        let is_annotated = |input: ParseStream| {
+            input.peek2(Ident)
+                || input.peek2(Token![async])
+                || input.peek2(Token![_])
+                || peek_absolute_path(input)
+        };
+
+        let layer_name = if input.peek(LitStr) && input.peek2(Token![:]) {
+            Some((input.parse()?, input.parse()?))
+        } else {
+            None
+        };
+
+        let mut async_token = None;
+        let mut pure_token = None;
+        let mut flush_after_token = None;
+        loop {
+            if async_token.is_none() && input.peek(Token![async]) {
+                async_token = Some(input.parse()?);
+            } else if pure_token.is_none() && input.peek(kw::pure) && is_annotated(input) {
+                pure_token = Some(input.parse()?);
+            } else if flush_after_token.is_none()
+                && input.peek(kw::flush_after)
+                && is_annotated(input)
+            {
+                flush_after_token = Some(input.parse()?);
+            } else {
+                break;
+            }
+        }
+        if input.peek(kw::pipe) && input.peek2(Brace) {
+            let pipe: NodePipe = input.parse()?;
+            let mut label =
+                if input.peek(Token![as]) { Some((input.parse()?, input.parse()?)) } else { None };
+            let in_set = parse_in_set(input, &mut label)?;
+            return Ok(Self {
+                layer_name,
+                async_token,
+                pure_token,
+                flush_after_token,
+                expr: pipe.to_expr(),
+                orderings: Vec::new(),
+                label,
+                in_set,
+                pipe: Some(pipe),
+                splice_token: None,
+                cfg: None,
+                depth_pin: None,
+            });
+        }
+        let (expr, mut label) = if input.peek(Token![_]) {
+            // Parsed by hand since `_` is only an expression with syn's "full" feature.
+            input.parse::<Token![_]>()?;
+            let label =
+                if input.peek(Token![as]) { Some((input.parse()?, input.parse()?)) } else { None };
+            (placeholder_expr(), label)
+        } else {
+            match input.parse()? {
+                Expr::Cast(cast) => split_label(cast)?,
+                expr => (expr, None),
+            }
+        };
+        let in_set = parse_in_set(input, &mut label)?;
+        Ok(Self {
+            layer_name,
+            async_token,
+            pure_token,
+            flush_after_token,
+            expr,
+            orderings: Vec::new(),
+            label,
+            in_set,
+            pipe: None,
+            splice_token: None,
+            cfg: None,
+            depth_pin: None,
+        })
+    }
+}
+
+/// The `_` expression of a placeholder node.
+pub(crate) fn placeholder_expr() -> Expr {
+    Expr::Verbatim(quote! { _ })
+}
+
+/// Whether `expr` is a `setup / teardown` pair of systems.
+fn is_teardown_pair(expr: &Expr) -> bool {
+    matches!(expr, Expr::Binary(ExprBinary { op: BinOp::Div(_), .. }))
+}
+
+/// Split `sys as label` into the system and its label.
+///
+/// Systems are never cast, so anything other than a single identifier after `as` is an error.
+fn split_label(cast: ExprCast) -> Result<(Expr, Option<(Token![as], Ident)>)> {
+    match *cast.ty {
+        Type::Path(TypePath { qself: None, path }) if path.get_ident().is_some() => {
+            let label = path.get_ident().cloned().unwrap();
+            Ok((*cast.expr, Some((cast.as_token, label))))
+        }
+        ty => Err(syn::Error::new(ty.span(), "expected a node label")),
+    }
+}
+
+/// Parse the `in Set` suffix of a node, if any.
+///
+/// The label of the node may come before or after the set, e.g. `sys as label in Set` or
+/// `sys in Set as label`, so a set that is cast is split into the set and the node's `label`.
+fn parse_in_set(
+    input: ParseStream,
+    label: &mut Option<(Token![as], Ident)>,
+) -> Result<Option<(Token![in], Expr)>> {
+    if !input.peek(Token![in]) {
+        return Ok(None);
+    }
+    let in_token = input.parse()?;
+    let set = match input.parse()? {
+        Expr::Cast(cast) if label.is_none() => {
+            let (set, set_label) = split_label(cast)?;
+            *label = set_label;
+            set
+        }
+        set => set,
+    };
+    Ok(Some((in_token, set)))
+}
+
+/// The ordering of a `.before(label)` or `.after(label)` call against one of `labels`.
+fn as_label_ordering(call: &ExprMethodCall, labels: &[&Ident]) -> Option<(NodeOrdering, Ident)> {
+    let ordering = NodeOrdering::from_method(&call.method)?;
+    if call.turbofish.is_some() || call.args.len() != 1 {
+        return None;
+    }
+    let Expr::Path(ExprPath { qself: None, path, .. }) = &call.args[0] else {
+        return None;
+    };
+    let label = path.get_ident().filter(|ident| labels.contains(ident))?;
+    Some((ordering, label.clone()))
+}
+
+/// Whether the input starts with a `@depth(n)` annotation.
+///
+/// It's only an annotation when followed by the rest of the node, otherwise it's a spliced tree
+/// returned by a function named `depth`, e.g. `@depth(2) => a` or `@depth(2), a`.
+fn peek_depth_pin(input: ParseStream) -> bool {
+    let fork = input.fork();
+    fork.parse::<DepthPin>().is_ok()
+        && !(fork.is_empty()
+            || fork.peek(Token![,])
+            || fork.peek(Token![=>])
+            || fork.peek(Token![;])
+            || fork.peek(Token![#]))
+}
+
+/// Whether the identifier at the front of `input` is followed by an absolute path, as opposed to
+/// being the first segment of a path or the name of a generic function.
+fn peek_absolute_path(input: ParseStream) -> bool {
+    let fork = input.fork();
+    fork.parse::<Ident>().is_ok() && fork.parse::<Token![::]>().is_ok() && !fork.peek(Token![<])
+}
+
 impl ToTokens for Node {
     fn to_tokens(&self, tokens: &mut TokenStream2) {
-        self.0.to_tokens(tokens);
+        self.depth_pin.to_tokens(tokens);
+        if let Some((name, colon_token)) = &self.layer_name {
+            name.to_tokens(tokens);
+            colon_token.to_tokens(tokens);
+        }
+        self.async_token.to_tokens(tokens);
+        self.pure_token.to_tokens(tokens);
+        self.flush_after_token.to_tokens(tokens);
+        self.splice_token.to_tokens(tokens);
+        match &self.pipe {
+            Some(pipe) => pipe.to_tokens(tokens),
+            None => self.expr.to_tokens(tokens),
+        }
+        for (ordering, label) in &self.orderings {
+            let method = Ident::new(ordering.method(), label.span());
+            tokens.extend(quote! { .#method(#label) });
+        }
+        if let Some((as_token, label)) = &self.label {
+            as_token.to_tokens(tokens);
+            label.to_tokens(tokens);
+        }
+        if let Some((in_token, set)) = &self.in_set {
+            in_token.to_tokens(tokens);
+            set.to_tokens(tokens);
+        }
     }
 }
 
 #[cfg(debug_assertions)]
 impl std::fmt::Debug for Node {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        let path = &self.0;
+        let path = &self.expr;
         let path = quote! { #path };
-        f.debug_tuple("Node").field(&path).finish()
+        let mut f = f.debug_tuple("Node");
+        if let Some(depth) = self.pinned_depth() {
+            f.field(&format_args!("depth({depth})"));
+        }
+        if let Some(name) = self.layer_name() {
+            f.field(&name.value());
+        }
+        if self.is_async() {
+            f.field(&format_args!("async"));
+        }
+        if self.is_pure() {
+            f.field(&format_args!("pure"));
+        }
+        if self.is_flush_after() {
+            f.field(&format_args!("flush_after"));
+        }
+        if self.is_splice() {
+            f.field(&format_args!("splice"));
+        }
+        if let Some(cfg) = &self.cfg {
+            f.field(&format_args!("{}", quote! { #cfg }));
+        }
+        f.field(&path);
+        for (ordering, label) in &self.orderings {
+            f.field(&format_args!("{}({label})", ordering.method()));
+        }
+        if let Some(label) = self.label() {
+            f.field(&format_args!("as {label}"));
+        }
+        if let Some((_, set)) = &self.in_set {
+            f.field(&format_args!("in {}", quote! { #set }));
+        }
+        f.finish()
     }
 }
 
-#[cfg(debug_assertions)]
 impl std::fmt::Display for Node {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        let path = &self.0;
-        let path = quote! { #path };
-        f.write_str(&path.to_string())
+        let node = quote! { #self };
+        f.write_str(&node.to_string())
     }
 }
 
@@ -71,4 +815,30 @@ mod tests {
         let actual_call = node.as_into_descriptor_call().to_string();
         assert_eq!(actual_call, expected_call);
     }
+
+    #[test]
+    fn async_node_chains_the_startup_task_barrier() {
+        let node = Node::new_async(path!(sys));
+        let expected_call = quote! {
+            ::bevy::prelude::IntoSystemConfigs::chain(
+                (sys, ::bevy_startup_tree::await_startup_tasks)
+            )
+        }
+        .to_string();
+        let actual_call = node.as_into_descriptor_call().to_string();
+        assert_eq!(actual_call, expected_call);
+    }
+
+    #[test]
+    fn pure_node_creates_a_pure_tree_node() {
+        let node = Node::new_pure(path!(sys));
+        let expected_call = quote! {
+            ::bevy_startup_tree::StartupTreeNode::new(
+                ::bevy::prelude::IntoSystemConfigs::into_configs(sys)
+            ).pure()
+        }
+        .to_string();
+        let actual_call = node.as_tree_node_call().to_string();
+        assert_eq!(actual_call, expected_call);
+    }
 }
@@ -1,62 +1,602 @@
 use std::ops::{Add, AddAssign};
 
-use proc_macro2::{Ident, Span, TokenStream as TokenStream2};
-use quote::{quote, ToTokens, TokenStreamExt};
+use proc_macro2::{Ident, Span, TokenStream as TokenStream2, TokenTree};
+use quote::{quote, quote_spanned, ToTokens, TokenStreamExt};
 use syn::{
-    parse::{Parse, ParseStream},
+    parse::{Parse, ParseStream, Parser},
     punctuated::Punctuated,
+    spanned::Spanned,
     token::Bracket,
-    Error, Macro, MacroDelimiter, Path, PathSegment, Result, Token,
+    Attribute, Macro, MacroDelimiter, Path, PathSegment, Result, Token,
 };
 
-use crate::{Branch, Node};
+use crate::{
+    branch::{cfg_predicate, peek_fan_in, peek_group, try_parse_branch_cfg},
+    Arrow, Branch, DepthPin, Node, NodeOrdering, TreeParseError,
+};
+
+pub struct StartupTree {
+    tree: Tree,
+    /// A node in a final layer of its own after every other node, e.g. `a, b =>> done`.
+    fan_in: Option<Node>,
+    /// Tokens appended to the systems of every node, e.g. `.run_if(condition)` for
+    /// `each: .run_if(condition);`.
+    each: Option<TokenStream2>,
+}
+
+/// The maximum number of distinct `#[cfg(...)]` attributes in a tree.
+///
+/// The tree is generated once for each combination of its attributes.
+pub const MAX_CFGS: usize = 8;
+
+impl StartupTree {
+    /// Check and prepare a parsed tree for code generation.
+    pub fn new(tree: Tree) -> std::result::Result<Self, TreeParseError> {
+        Self::with_fan_in(tree, None)
+    }
+
+    /// Check and prepare a parsed tree for code generation, with an optional `fan_in` node that
+    /// runs after all other nodes of the tree.
+    pub fn with_fan_in(
+        mut tree: Tree,
+        fan_in: Option<Node>,
+    ) -> std::result::Result<Self, TreeParseError> {
+        tree.set_depth_root();
+        if let (Some(_), Some(splice_token)) = (&fan_in, tree.splice_token()) {
+            return Err(TreeParseError::SplicedParent(splice_token.span));
+        }
+        check_depth_pins(&tree, 0)?;
+        let mut cfgs = Vec::new();
+        tree_cfgs(&tree, &mut cfgs);
+        if let Some(cfg) = cfgs.get(MAX_CFGS) {
+            return Err(TreeParseError::TooManyCfgs(cfg.pound_token.span, cfgs.len()));
+        }
+        let mut this = Self { tree, fan_in, each: None };
+        for (_, levels) in this.variants() {
+            check_layer_names(levels)?;
+        }
+        this.resolve_orderings()?;
+        Ok(this)
+    }
 
-pub struct StartupTree(Tree);
+    /// The nodes of the tree grouped by depth, including the fan-in node, with all `#[cfg]`
+    /// attributes enabled.
+    fn levels(&self) -> Vec<Vec<&Node>> {
+        self.levels_with(&|_| true)
+    }
+
+    /// The nodes of the tree grouped by depth, including the fan-in node, where the children of
+    /// the arrows whose `#[cfg]` isn't `enabled` are siblings of their parent, and the branches
+    /// whose `#[cfg]` isn't `enabled` are left out.
+    fn levels_with(&self, enabled: &dyn Fn(&Attribute) -> bool) -> Vec<Vec<&Node>> {
+        let mut levels = tree_to_levels(&self.tree, enabled);
+        if let Some(fan_in) = &self.fan_in {
+            levels.push(vec![fan_in]);
+        }
+        levels
+    }
+
+    /// The levels of the tree for each combination of its `#[cfg]` arrows and branches, with the
+    /// cfg predicate of the combination.
+    ///
+    /// A tree without `#[cfg]` attributes has a single variant without a predicate. Each distinct
+    /// `#[cfg]` attribute doubles the number of variants, so a tree has at most [`MAX_CFGS`] of them.
+    fn variants(&self) -> Vec<(Option<TokenStream2>, Vec<Vec<&Node>>)> {
+        let mut cfgs = Vec::new();
+        tree_cfgs(&self.tree, &mut cfgs);
+        if cfgs.is_empty() {
+            return vec![(None, self.levels())];
+        }
+
+        (0..1usize << cfgs.len())
+            .map(|mask| {
+                let is_enabled = |i: usize| mask & (1 << i) != 0;
+                let predicates = cfgs.iter().enumerate().map(|(i, &cfg)| {
+                    let predicate = cfg_predicate(cfg);
+                    if is_enabled(i) {
+                        quote! { #predicate }
+                    } else {
+                        quote! { not(#predicate) }
+                    }
+                });
+                let enabled = |attr: &Attribute| match cfgs.iter().position(|&cfg| cfg == attr) {
+                    Some(i) => is_enabled(i),
+                    None => true,
+                };
+                (Some(quote! { all(#(#predicates),*) }), self.levels_with(&enabled))
+            })
+            .collect()
+    }
+
+    /// Resolve the `.before(label)` and `.after(label)` calls of all nodes against the labels of
+    /// the tree, then make sure that the orderings don't form a cycle.
+    fn resolve_orderings(&mut self) -> std::result::Result<(), TreeParseError> {
+        let levels = self.levels();
+        let mut labels: Vec<Ident> = Vec::new();
+        for label in levels.iter().flatten().filter_map(|node| node.label()) {
+            if labels.contains(label) {
+                return Err(TreeParseError::DuplicateNodeLabel(label.span(), label.to_string()));
+            }
+            labels.push(label.clone());
+        }
+        if labels.is_empty() {
+            return Ok(());
+        }
+
+        let labels = labels.iter().collect::<Vec<_>>();
+        let mut nodes = Vec::new();
+        tree_nodes_mut(&mut self.tree, &mut nodes);
+        nodes.extend(self.fan_in.as_mut());
+        for node in nodes {
+            node.resolve_orderings(&labels);
+        }
+
+        for (_, levels) in self.variants() {
+            check_orderings(levels)?;
+        }
+        Ok(())
+    }
+}
+
+impl StartupTree {
+    /// Parse a startup tree whose branches don't need to be separated by `,`, see
+    /// [`Tree::try_parse_forest_relaxed`].
+    pub fn parse_relaxed(input: ParseStream) -> Result<Self> {
+        Self::parse_with(input, true)
+    }
+
+    fn parse_with(input: ParseStream, relaxed: bool) -> Result<Self> {
+        let (each, tree, fan_in) = Self::parse_parts(input, relaxed)?;
+        Ok(Self { each, ..Self::with_fan_in(tree, fan_in)? })
+    }
+
+    /// Parse a startup tree whose nodes may be `setup / teardown` pairs of systems into a startup
+    /// tree of the setup systems and a teardown tree of the same shape, see
+    /// [`Node::split_teardown`].
+    pub fn parse_with_teardown(input: ParseStream) -> Result<(Self, Self)> {
+        let (each, mut tree, mut fan_in) = Self::parse_parts(input, false)?;
+
+        let mut teardown_tree = tree.clone();
+        let mut teardown_fan_in = fan_in.clone();
+        let mut nodes = Vec::new();
+        tree_nodes_mut(&mut tree, &mut nodes);
+        nodes.extend(fan_in.as_mut());
+        let mut teardown_nodes = Vec::new();
+        tree_nodes_mut(&mut teardown_tree, &mut teardown_nodes);
+        teardown_nodes.extend(teardown_fan_in.as_mut());
+        for (node, teardown_node) in nodes.into_iter().zip(teardown_nodes) {
+            *teardown_node = node.split_teardown()?;
+        }
+
+        let startup = Self { each: each.clone(), ..Self::with_fan_in(tree, fan_in)? };
+        let teardown = Self { each, ..Self::with_fan_in(teardown_tree, teardown_fan_in)? };
+        Ok((startup, teardown))
+    }
+
+    /// Parse the `each:` header, the tree, and the fan-in node of a startup tree.
+    fn parse_parts(
+        input: ParseStream,
+        relaxed: bool,
+    ) -> Result<(Option<TokenStream2>, Tree, Option<Node>)> {
+        let each = if input.peek(kw::each) && input.peek2(Token![:]) && !input.peek2(Token![::]) {
+            let _: kw::each = input.parse()?;
+            let _: Token![:] = input.parse()?;
+            Some(parse_each_suffix(input)?)
+        } else {
+            None
+        };
+        let tree = Tree::try_parse_forest_with(input, relaxed)?;
+        let fan_in = if peek_fan_in(input) {
+            let _: Token![=>] = input.parse()?;
+            let _: Token![>] = input.parse()?;
+            let fan_in = input.parse()?;
+            if input.peek(Token![,]) {
+                let _: Token![,] = input.parse()?;
+            }
+            Some(fan_in)
+        } else {
+            None
+        };
+        Ok((each, tree, fan_in))
+    }
+}
 
 impl Parse for StartupTree {
     fn parse(input: ParseStream) -> Result<Self> {
-        let mut tree: Tree = input.parse()?;
-        tree.set_depth_root();
-        Ok(Self(tree))
+        Self::parse_with(input, false)
+    }
+}
+
+mod kw {
+    syn::custom_keyword!(each);
+}
+
+/// Parse the suffix of an `each:` header up to and including its `;`.
+///
+/// The suffix must be a method call chain that starts with `.`, e.g. `.run_if(condition)`.
+fn parse_each_suffix(input: ParseStream) -> Result<TokenStream2> {
+    if !input.peek(Token![.]) {
+        return Err(input.error("expected a method call like `.run_if(condition)` after `each:`"));
+    }
+    let mut suffix = TokenStream2::new();
+    while !input.peek(Token![;]) {
+        if input.is_empty() {
+            return Err(input.error("expected `;` after the `each:` suffix"));
+        }
+        suffix.extend([input.parse::<TokenTree>()?]);
+    }
+    let _: Token![;] = input.parse()?;
+    Ok(suffix)
+}
+
+/// Make sure that no node is pinned with `@depth(n)` to a depth before `depth`, the first depth
+/// after its parent.
+///
+/// The arrows with a `#[cfg]` are all enabled, which puts the children of the arrows at the
+/// latest possible depth.
+fn check_depth_pins(tree: &Tree, depth: usize) -> std::result::Result<(), TreeParseError> {
+    fn check_branch(branch: &Branch, depth: usize) -> std::result::Result<(), TreeParseError> {
+        let depth = match &branch.node().depth_pin {
+            Some(pin) if pin.depth() < depth => {
+                return Err(TreeParseError::ShallowDepthPin(pin.depth.span(), pin.depth(), depth));
+            }
+            Some(pin) => pin.depth(),
+            None => depth,
+        };
+        match branch {
+            Branch::Leaf(_) => Ok(()),
+            Branch::Arm(_, _, child) => check_branch(child, depth + 1),
+            Branch::Tree(_, _, child) => check_depth_pins(child, depth + 1),
+        }
+    }
+
+    tree.branches.iter().try_for_each(|branch| check_branch(branch, depth))
+}
+
+/// Make sure that each layer has at most one name and that no two layers have the same name.
+fn check_layer_names(levels: Vec<Vec<&Node>>) -> std::result::Result<(), TreeParseError> {
+    let mut layer_names: Vec<String> = Vec::new();
+    for level in levels {
+        let mut level_name: Option<String> = None;
+        for name in level.into_iter().filter_map(Node::layer_name) {
+            let value = name.value();
+            match &level_name {
+                Some(level_name) if *level_name != value => {
+                    return Err(TreeParseError::ConflictingLayerNames(
+                        name.span(),
+                        level_name.clone(),
+                        value,
+                    ));
+                }
+                Some(_) => {}
+                None => {
+                    if layer_names.contains(&value) {
+                        return Err(TreeParseError::DuplicateLayerName(name.span(), value));
+                    }
+                    layer_names.push(value.clone());
+                    level_name = Some(value);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+impl StartupTree {
+    /// Whether any node of the tree has annotations that must be passed on to
+    /// `add_startup_tree`, in which case the tree is emitted as `StartupTreeNode`s instead of
+    /// `SystemConfigs`.
+    pub fn has_node_metadata(&self) -> bool {
+        self.levels().into_iter().flatten().any(Node::has_metadata)
+    }
+
+    /// The type of the nodes that the tree is emitted as, see
+    /// [`has_node_metadata`](Self::has_node_metadata).
+    pub fn node_type(&self) -> TokenStream2 {
+        if self.has_node_metadata() {
+            quote! { ::bevy_startup_tree::StartupTreeNode }
+        } else {
+            quote! { ::bevy::ecs::schedule::SystemConfigs }
+        }
+    }
+
+    /// The number of nodes in each layer of the tree for each combination of its `#[cfg]` arrows,
+    /// with the cfg predicate of the combination.
+    pub fn layer_widths(&self) -> Vec<(Option<TokenStream2>, Vec<usize>)> {
+        self.variants()
+            .into_iter()
+            .map(|(predicate, levels)| (predicate, levels.iter().map(Vec::len).collect()))
+            .collect()
+    }
+
+    /// The `@` of the first spliced tree in the tree, if any.
+    pub fn splice_token(&self) -> Option<Token![@]> {
+        self.tree.splice_token()
+    }
+
+    /// Make sure that every node of the tree, including the fan-in node, is a path to a system.
+    pub fn check_paths(&self) -> std::result::Result<(), TreeParseError> {
+        match self.levels().into_iter().flatten().find_map(Node::non_path_expr) {
+            Some(expr) => Err(TreeParseError::NonPathNode(expr.span())),
+            None => Ok(()),
+        }
     }
 }
 
 impl ToTokens for StartupTree {
     fn to_tokens(&self, tokens: &mut TokenStream2) {
-        let tree_levels = tree_to_levels(&self.0);
-        let span = Span::call_site();
-
-        let vec_elements = tree_levels.into_iter().map(|level| Macro {
-            path: Path {
-                leading_colon: Some(Token![::](span)),
-                segments: Punctuated::from_iter([
-                    PathSegment::from(Ident::new("std", span)),
-                    PathSegment::from(Ident::new("vec", span)),
-                ]),
-            },
-            bang_token: Token![!](span),
-            delimiter: MacroDelimiter::Bracket(Bracket(span)),
-            tokens: {
-                let mut elements = TokenStream2::new();
-                elements.append_separated(
-                    level.into_iter().map(Node::as_into_descriptor_call),
-                    Token![,](span),
-                );
-                elements
-            },
-        });
-        let vec_elements = Punctuated::<_, Token![,]>::from_iter(vec_elements);
+        self.to_tokens_in_layer_sets(None, tokens);
+    }
+}
+
+impl StartupTree {
+    /// The number of layers of the tree, the most of any combination of its `#[cfg]` arrows and
+    /// branches.
+    pub fn layer_count(&self) -> usize {
+        self.variants().iter().map(|(_, levels)| levels.len()).max().unwrap_or_default()
+    }
 
+    /// Like [`to_tokens`](ToTokens::to_tokens), with the systems of each layer added to the set
+    /// that `layer_set` returns for the layer's depth, if any.
+    pub fn to_tokens_in_layer_sets(
+        &self,
+        layer_set: Option<&dyn Fn(usize) -> TokenStream2>,
+        tokens: &mut TokenStream2,
+    ) {
+        let each = self.each.as_ref();
+        let has_node_metadata = self.has_node_metadata();
+        let as_call = |depth: usize, node: &Node| {
+            let layer_set = layer_set.map(|layer_set| layer_set(depth));
+            if has_node_metadata {
+                node.as_tree_node_call_with(each, layer_set.as_ref())
+            } else {
+                node.as_into_descriptor_call_with(each, layer_set.as_ref())
+            }
+        };
+
+        let mut variants = self.variants();
+        if let [(None, _)] = variants.as_slice() {
+            let (_, tree_levels) = variants.pop().unwrap();
+            levels_to_tokens_by_depth(tree_levels, &as_call).to_tokens(tokens);
+            return;
+        }
+
+        let node_type = self.node_type();
+        let variants = variants.into_iter().map(|(predicate, tree_levels)| {
+            // Every branch is left out, so there are no nodes to infer the type from
+            let levels = if tree_levels.is_empty() {
+                quote! { ::std::vec::Vec::<::std::vec::Vec<#node_type>>::new() }
+            } else {
+                levels_to_tokens_by_depth(tree_levels, &as_call)
+            };
+            quote! { #[cfg(#predicate)] let startup_tree = #levels; }
+        });
         quote! {
-            vec![ #vec_elements ]
+            {
+                #(#variants)*
+                startup_tree
+            }
         }
         .to_tokens(tokens);
     }
 }
 
-fn tree_to_levels(tree: &Tree) -> Vec<Vec<&Node>> {
+impl StartupTree {
+    /// The names of the nodes of the tree, by depth, as a `&'static [&'static [&'static str]]`
+    /// expression that can be used in a const context.
+    ///
+    /// See [`Node::name`] for the names of the nodes.
+    pub fn to_meta_tokens(&self) -> TokenStream2 {
+        let levels_to_meta = |tree_levels: Vec<Vec<&Node>>| {
+            let levels = tree_levels.into_iter().map(|level| {
+                let names = level.into_iter().map(Node::name);
+                quote! { &[#(#names),*] }
+            });
+            quote! { &[#(#levels),*] }
+        };
+
+        let variants = self.variants().into_iter().map(|(predicate, tree_levels)| {
+            let levels = levels_to_meta(tree_levels);
+            let cfg = predicate.map(|predicate| quote! { #[cfg(#predicate)] });
+            quote! { #cfg let startup_tree: &'static [&'static [&'static str]] = #levels; }
+        });
+        quote! {
+            {
+                #(#variants)*
+                startup_tree
+            }
+        }
+    }
+}
+
+/// The expression that creates the layers of a tree, given its nodes grouped by depth, e.g. by
+/// [`tree_to_levels`].
+///
+/// `as_call` is the expression of a node's element in its layer, like
+/// [`Node::as_into_descriptor_call`]. Spliced trees are left out of their layer and appended to the
+/// layers at their depth once the layers were created. This is the code generation of
+/// [`StartupTree`] without any of its checks, so that other frontends can reuse it.
+pub fn levels_to_tokens(
+    tree_levels: Vec<Vec<&Node>>,
+    as_call: &dyn Fn(&Node) -> TokenStream2,
+) -> TokenStream2 {
+    levels_to_tokens_by_depth(tree_levels, &|_, node| as_call(node))
+}
+
+/// Like [`levels_to_tokens`], with the depth of each node passed to `as_call`.
+fn levels_to_tokens_by_depth(
+    tree_levels: Vec<Vec<&Node>>,
+    as_call: &dyn Fn(usize, &Node) -> TokenStream2,
+) -> TokenStream2 {
+    let mut splices = Vec::new();
+    let tree_levels = tree_levels
+        .into_iter()
+        .enumerate()
+        .map(|(depth, level)| {
+            let (spliced, level): (Vec<&Node>, Vec<&Node>) =
+                level.into_iter().partition(|node| node.is_splice());
+            splices.extend(spliced.into_iter().map(|node| (depth, &node.expr)));
+            level
+        })
+        .collect::<Vec<_>>();
+
+    let span = Span::call_site();
+    let vec_elements = tree_levels.into_iter().enumerate().map(|(depth, level)| Macro {
+        path: Path {
+            leading_colon: Some(Token![::](span)),
+            segments: Punctuated::from_iter([
+                PathSegment::from(Ident::new("std", span)),
+                PathSegment::from(Ident::new("vec", span)),
+            ]),
+        },
+        bang_token: Token![!](span),
+        delimiter: MacroDelimiter::Bracket(Bracket(span)),
+        tokens: {
+            let mut elements = TokenStream2::new();
+            elements.append_separated(
+                level.into_iter().map(|node| as_call(depth, node)),
+                Token![,](span),
+            );
+            elements
+        },
+    });
+    let vec_elements = Punctuated::<_, Token![,]>::from_iter(vec_elements);
+
+    if splices.is_empty() {
+        return quote! {
+            ::std::vec![ #vec_elements ]
+        };
+    }
+
+    let splices = splices.into_iter().map(|(depth, expr)| {
+        quote_spanned! {expr.span()=>
+            ::bevy_startup_tree::splice_startup_tree(&mut startup_tree, #depth, #expr);
+        }
+    });
+    quote! {
+        {
+            let mut startup_tree = ::std::vec![ #vec_elements ];
+            #(#splices)*
+            startup_tree
+        }
+    }
+}
+
+/// Make sure that the orderings between nodes agree with the layers of the tree and don't form a
+/// cycle within a layer.
+fn check_orderings(levels: Vec<Vec<&Node>>) -> std::result::Result<(), TreeParseError> {
+    let depth_of = |label: &Ident| {
+        levels.iter().position(|level| level.iter().any(|node| node.label() == Some(label)))
+    };
+
+    // Edges `(from, to, ref)` between labeled nodes of the same layer, `from` runs first
+    let mut edges: Vec<(&Ident, &Ident, &Ident)> = Vec::new();
+    for (depth, level) in levels.iter().enumerate() {
+        for node in level {
+            for (ordering, label) in &node.orderings {
+                let Some(other_depth) = depth_of(label) else {
+                    return Err(TreeParseError::CfgLabel(label.span(), label.to_string()));
+                };
+                let runs_first = match ordering {
+                    NodeOrdering::Before => depth < other_depth,
+                    NodeOrdering::After => depth > other_depth,
+                };
+                if depth != other_depth && !runs_first {
+                    return Err(TreeParseError::OrderingCycle(label.span(), label.to_string()));
+                }
+                match (ordering, node.label()) {
+                    (_, Some(this)) if this == label => {
+                        return Err(TreeParseError::OrderingCycle(label.span(), label.to_string()));
+                    }
+                    (NodeOrdering::Before, Some(this)) if depth == other_depth => {
+                        edges.push((this, label, label));
+                    }
+                    (NodeOrdering::After, Some(this)) if depth == other_depth => {
+                        edges.push((label, this, label));
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    // Repeatedly remove the labels that nothing has to run before, whatever remains is a cycle
+    let mut remaining = edges;
+    loop {
+        let blocked = remaining.iter().map(|&(_, to, _)| to).collect::<Vec<_>>();
+        let len = remaining.len();
+        remaining.retain(|&(from, _, _)| blocked.contains(&from));
+        if remaining.is_empty() {
+            return Ok(());
+        }
+        if remaining.len() == len {
+            let (_, _, label) = remaining[0];
+            return Err(TreeParseError::OrderingCycle(label.span(), label.to_string()));
+        }
+    }
+}
+
+fn tree_nodes_mut<'tree>(tree: &'tree mut Tree, nodes: &mut Vec<&'tree mut Node>) {
+    fn push_branch<'tree>(nodes: &mut Vec<&'tree mut Node>, branch: &'tree mut Branch) {
+        match branch {
+            Branch::Leaf(node) => nodes.push(node),
+            Branch::Arm(node, _, b) => {
+                nodes.push(node);
+                push_branch(nodes, b);
+            }
+            Branch::Tree(node, _, t) => {
+                nodes.push(node);
+                tree_nodes_mut(t, nodes);
+            }
+        }
+    }
+
+    for branch in &mut tree.branches {
+        push_branch(nodes, branch);
+    }
+}
+
+/// The distinct `#[cfg]` attributes of the arrows and branches of a tree.
+fn tree_cfgs<'tree>(tree: &'tree Tree, cfgs: &mut Vec<&'tree Attribute>) {
+    fn push_cfg<'tree>(cfgs: &mut Vec<&'tree Attribute>, cfg: &'tree Option<Attribute>) {
+        if let Some(cfg) = cfg {
+            if !cfgs.contains(&cfg) {
+                cfgs.push(cfg);
+            }
+        }
+    }
+
+    fn push_branch<'tree>(cfgs: &mut Vec<&'tree Attribute>, branch: &'tree Branch) {
+        push_cfg(cfgs, &branch.node().cfg);
+        match branch {
+            Branch::Leaf(_) => {}
+            Branch::Arm(_, arrow, b) => {
+                push_cfg(cfgs, &arrow.cfg);
+                push_branch(cfgs, b);
+            }
+            Branch::Tree(_, arrow, t) => {
+                push_cfg(cfgs, &arrow.cfg);
+                tree_cfgs(t, cfgs);
+            }
+        }
+    }
+
+    for branch in &tree.branches {
+        push_branch(cfgs, branch);
+    }
+}
+
+/// The nodes of a tree grouped by depth.
+///
+/// The children of an arrow whose `#[cfg]` isn't `enabled` are siblings of its parent, and a
+/// top-level branch whose `#[cfg]` isn't `enabled` is left out. Pass `&|_| true` to enable all of
+/// them. A node pinned with `@depth(n)` is at depth `n`, with empty levels before it if needed.
+pub fn tree_to_levels<'tree>(
+    tree: &'tree Tree,
+    enabled: &dyn Fn(&Attribute) -> bool,
+) -> Vec<Vec<&'tree Node>> {
     let mut tree_levels: Vec<Vec<&Node>> = Vec::new();
-    tree_to_levels_impl(&mut tree_levels, tree, 0);
+    tree_to_levels_impl(&mut tree_levels, tree, 0, enabled);
     tree_levels
 }
 
@@ -64,27 +604,42 @@ fn tree_to_levels_impl<'tree>(
     tree_levels: &mut Vec<Vec<&'tree Node>>,
     subtree: &'tree Tree,
     depth: usize,
+    enabled: &dyn Fn(&Attribute) -> bool,
 ) {
-    fn push_branch<'tree>(levels: &mut Vec<Vec<&'tree Node>>, branch: &'tree Branch, depth: usize) {
+    fn push_branch<'tree>(
+        levels: &mut Vec<Vec<&'tree Node>>,
+        branch: &'tree Branch,
+        depth: usize,
+        enabled: &dyn Fn(&Attribute) -> bool,
+    ) {
+        if branch.node().cfg.as_ref().is_some_and(|cfg| !enabled(cfg)) {
+            return;
+        }
+        let depth = branch.node().pinned_depth().unwrap_or(depth);
         if depth >= levels.len() {
-            levels.push(vec![branch.node()]);
-        } else {
-            levels[depth].push(branch.node());
+            levels.resize_with(depth + 1, Vec::new);
         }
+        levels[depth].push(branch.node());
 
+        let child_depth = |arrow: &Arrow| match &arrow.cfg {
+            Some(cfg) if !enabled(cfg) => depth,
+            _ => depth + 1,
+        };
         match branch {
-            Branch::Arm(_, _, b) => push_branch(levels, b, depth + 1),
-            Branch::Tree(_, _, t) => tree_to_levels_impl(levels, t, depth + 1),
+            Branch::Arm(_, arrow, b) => push_branch(levels, b, child_depth(arrow), enabled),
+            Branch::Tree(_, arrow, t) => {
+                tree_to_levels_impl(levels, t, child_depth(arrow), enabled)
+            }
             Branch::Leaf(_) => {}
         }
     }
 
     for branch in &subtree.branches {
-        push_branch(tree_levels, branch, depth);
+        push_branch(tree_levels, branch, depth, enabled);
     }
 }
 
-#[derive(PartialEq)]
+#[derive(Clone, PartialEq)]
 pub struct Tree {
     pub depth: TreeDepth,
     pub branches: Punctuated<Branch, Token![,]>,
@@ -115,6 +670,29 @@ impl Tree {
         Self::from_branch(path.into(), trailing_comma)
     }
 
+    /// The maximum nesting of the tree, i.e. the number of depths (layers) that it generates.
+    ///
+    /// Not to be confused with the [`depth`](Self::depth) field, which is the depth of this tree
+    /// within its root.
+    pub fn depth(&self) -> usize {
+        self.branches.iter().map(Branch::depth).max().unwrap_or(0)
+    }
+
+    /// The total number of nodes in the tree.
+    pub fn node_count(&self) -> usize {
+        self.branches.iter().map(Branch::node_count).sum()
+    }
+
+    /// The `@` of the first spliced tree in the tree, if any.
+    pub fn splice_token(&self) -> Option<Token![@]> {
+        self.branches.iter().find_map(Branch::splice_token)
+    }
+
+    /// The `@depth(n)` annotation of the first pinned node of the tree, if any.
+    pub fn depth_pin(&self) -> Option<&DepthPin> {
+        self.branches.iter().find_map(Branch::depth_pin)
+    }
+
     fn _calculate_depths_impl(this: &mut Self, depth: TreeDepth) {
         this.depth = depth;
         for branch in &mut this.branches {
@@ -127,6 +705,122 @@ impl Tree {
     pub fn set_depth_root(&mut self) {
         Self::_calculate_depths_impl(self, TreeDepth::default());
     }
+
+    /// Parse a tree, reporting failures as a structured [`TreeParseError`].
+    pub fn try_parse(input: ParseStream) -> std::result::Result<Self, TreeParseError> {
+        Self::try_parse_branches(input, false, false)
+    }
+
+    /// Parse a forest of trees separated by `;` into a single tree, reporting failures as a
+    /// structured [`TreeParseError`].
+    ///
+    /// The branches of all trees are merged as if they were separated by `,`, so the trees share
+    /// the same layers. A trailing `;` is allowed.
+    pub fn try_parse_forest(input: ParseStream) -> std::result::Result<Self, TreeParseError> {
+        Self::try_parse_forest_with(input, false)
+    }
+
+    /// Parse a forest like [`try_parse_forest`](Self::try_parse_forest), but where branches may
+    /// be separated by whitespace as well as `,`, at any depth.
+    ///
+    /// A branch ends where its node's expression ends, so a `,` is still needed before a branch
+    /// that would continue the expression of the previous node, e.g. one that starts with `{`,
+    /// `(`, `[`, `.` or `|`, and after an annotation-like identifier such as `pure`. The nodes of
+    /// `chain![..]`, `par![..]` and `pipe { .. }` are still separated by `,` and `=>`.
+    pub fn try_parse_forest_relaxed(
+        input: ParseStream,
+    ) -> std::result::Result<Self, TreeParseError> {
+        Self::try_parse_forest_with(input, true)
+    }
+
+    fn try_parse_forest_with(
+        input: ParseStream,
+        relaxed: bool,
+    ) -> std::result::Result<Self, TreeParseError> {
+        let mut tree = Self::try_parse_branches(input, true, relaxed)?;
+        while !input.is_empty() && !peek_fan_in(input) {
+            let _: Token![;] = input.parse()?;
+            if input.is_empty() {
+                break;
+            }
+            let group = Self::try_parse_branches(input, true, relaxed)?;
+            if !tree.branches.empty_or_trailing() {
+                tree.branches.push_punct(Default::default());
+            }
+            tree.branches.extend(group.branches.into_pairs());
+        }
+        Ok(tree)
+    }
+
+    /// Parse the branches of a tree up to the end of the input, or up to the next `;` or fan-in
+    /// `=>>` of a forest. Without a `,`, the branches of a `relaxed` tree are separated anyway.
+    pub(crate) fn try_parse_branches(
+        input: ParseStream,
+        in_forest: bool,
+        relaxed: bool,
+    ) -> std::result::Result<Self, TreeParseError> {
+        let is_end = |input: ParseStream| {
+            input.is_empty() || (in_forest && (input.peek(Token![;]) || peek_fan_in(input)))
+        };
+
+        // A tree of only commas, e.g. `{ , }`, is as empty as `{}`
+        let fork = input.fork();
+        while fork.parse::<Option<Token![,]>>()?.is_some() {}
+        if is_end(&fork) {
+            return Err(TreeParseError::EmptyTree(input.span()));
+        }
+
+        let mut branches = Punctuated::new();
+        while !is_end(input) {
+            let cfg = if in_forest { try_parse_branch_cfg(input)? } else { None };
+            let with_cfg = |mut branch: Branch| {
+                branch.node_mut().cfg = cfg.clone();
+                branch
+            };
+            if peek_group(input) {
+                let group = Branch::try_parse_group(input, relaxed)?;
+                let trailing_comma = group.branches.trailing_punct();
+                for branch in group.branches {
+                    if !branches.empty_or_trailing() {
+                        branches.push_punct(Default::default());
+                    }
+                    branches.push_value(with_cfg(branch));
+                }
+                // Keep the trailing comma of a group that ends the tree, so that e.g. the
+                // `Display` output of a tree with a trailing comma parses to the same tree.
+                if trailing_comma && is_end(input) {
+                    branches.push_punct(Default::default());
+                }
+            } else {
+                branches.push_value(with_cfg(Branch::try_parse_with(input, relaxed)?));
+            }
+            if is_end(input) {
+                break;
+            }
+            if input.peek(Token![,]) {
+                branches.push_punct(input.parse()?);
+            } else if relaxed {
+                branches.push_punct(Default::default());
+            } else {
+                return Err(TreeParseError::ExpectedComma(input.span()));
+            }
+        }
+
+        Ok(Self { depth: TreeDepth::default(), branches })
+    }
+
+    /// Parse a tree from `tokens`, reporting failures as a structured [`TreeParseError`].
+    pub fn parse_tokens(tokens: TokenStream2) -> std::result::Result<Self, TreeParseError> {
+        let mut error = None;
+        let parser = |input: ParseStream| {
+            Self::try_parse(input).map_err(|err| {
+                let syn_err = syn::Error::new(err.span(), &err);
+                error = Some(err);
+                syn_err
+            })
+        };
+        parser.parse2(tokens).map_err(|syn_err| error.unwrap_or(TreeParseError::Syn(syn_err)))
+    }
 }
 
 impl<B: Into<Branch>> FromIterator<B> for Tree {
@@ -156,10 +850,7 @@ impl From<Path> for Tree {
 
 impl Parse for Tree {
     fn parse(input: ParseStream) -> Result<Self> {
-        if input.is_empty() {
-            return Err(Error::new(input.span(), "tree may not be empty"));
-        }
-        Ok(Self { depth: TreeDepth::default(), branches: Punctuated::parse_terminated(input)? })
+        Self::try_parse(input).map_err(Into::into)
     }
 }
 
@@ -173,7 +864,8 @@ impl std::fmt::Debug for Tree {
     }
 }
 
-#[cfg(debug_assertions)]
+/// Renders the tree as valid input of the macros that parses back to an equal [`Tree`], with
+/// every branch on its own line.
 impl std::fmt::Display for Tree {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         use std::fmt::Write;
@@ -181,9 +873,12 @@ impl std::fmt::Display for Tree {
             f.write_str("{}")
         } else {
             f.write_str("{\n")?;
-            for branch in &self.branches {
+            for pair in self.branches.pairs() {
                 std::fmt::Display::fmt(&(self.depth + 1), f)?;
-                std::fmt::Display::fmt(branch, f)?;
+                std::fmt::Display::fmt(pair.value(), f)?;
+                if pair.punct().is_some() {
+                    f.write_char(',')?;
+                }
                 f.write_char('\n')?;
             }
             std::fmt::Display::fmt(&self.depth, f)?;
@@ -219,7 +914,6 @@ impl std::fmt::Debug for TreeDepth {
     }
 }
 
-#[cfg(debug_assertions)]
 impl std::fmt::Display for TreeDepth {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         for _ in 0..self.0 {
@@ -237,7 +931,7 @@ mod tests {
     use rand::random;
     use syn::parse2;
 
-    use crate::{test_utils::assert_err, Tree, TreeDepth};
+    use crate::{test_utils::assert_err, Tree, TreeDepth, TreeParseError};
 
     #[test]
     fn error_on_empty_tree() {
@@ -245,6 +939,12 @@ mod tests {
         assert_err(&result, "tree may not be empty");
     }
 
+    #[test]
+    fn structured_error_on_empty_tree() {
+        let result = Tree::parse_tokens(TokenStream2::new());
+        assert!(matches!(result, Err(TreeParseError::EmptyTree(_))));
+    }
+
     fn safe_random_tree_depth() -> (u32, TreeDepth) {
         let value = random::<u32>();
         // Subtract 1 to guarantee that adding 1 won't overflow
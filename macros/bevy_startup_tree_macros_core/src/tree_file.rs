@@ -0,0 +1,112 @@
+use std::path::PathBuf;
+
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{quote, ToTokens};
+use ron::Value;
+use syn::{
+    parse::{Parse, ParseStream},
+    LitStr, Path, Result,
+};
+
+use crate::{Branch, Node, StartupTree, Tree, TreeParseError};
+
+/// A startup tree that is read from a RON file at compile time.
+///
+/// The path of the file is relative to the root of the crate that invokes the macro. See
+/// [`tree_from_ron`] for the format of the file.
+pub struct StartupTreeFile {
+    pub path: LitStr,
+    pub full_path: PathBuf,
+    pub tree: StartupTree,
+}
+
+impl Parse for StartupTreeFile {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let path: LitStr = input.parse()?;
+        let manifest_dir = std::env::var_os("CARGO_MANIFEST_DIR").unwrap_or_default();
+        let full_path = PathBuf::from(manifest_dir).join(path.value());
+        let source = std::fs::read_to_string(&full_path).map_err(|err| {
+            syn::Error::new(path.span(), format!("failed to read {}: {err}", full_path.display()))
+        })?;
+        let tree = StartupTree::new(tree_from_ron(&source, path.span())?)?;
+        Ok(Self { path, full_path, tree })
+    }
+}
+
+impl ToTokens for StartupTreeFile {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let Self { full_path, tree, .. } = self;
+        // Include the file so that the crate is rebuilt when it changes
+        let full_path = full_path.to_string_lossy();
+        quote! {
+            {
+                const _: &str = ::std::include_str!(#full_path);
+                #tree
+            }
+        }
+        .to_tokens(tokens);
+    }
+}
+
+/// Parse a tree from its RON description.
+///
+/// A tree is a list of branches. A branch is either the name of a system, or a map from the name
+/// of a system to its children, which are a tree themselves. The names must be paths to systems
+/// that are in scope where the tree is used. Errors are reported at `span`.
+///
+/// ```ron
+/// [
+///     "load_config",
+///     { "spawn_world": ["spawn_player", { "spawn_hud": ["spawn_minimap"] }] },
+/// ]
+/// ```
+///
+/// The siblings in a map are sorted by name, which doesn't matter because there is no ordering
+/// between the systems of a layer.
+pub fn tree_from_ron(source: &str, span: Span) -> std::result::Result<Tree, TreeParseError> {
+    let value: Value =
+        ron::from_str(source).map_err(|err| TreeParseError::Ron(span, err.to_string()))?;
+    value_to_tree(&value, span)
+}
+
+fn value_to_tree(value: &Value, span: Span) -> std::result::Result<Tree, TreeParseError> {
+    let branches = match value {
+        Value::Seq(values) => values
+            .iter()
+            .map(|value| value_to_branches(value, span))
+            .collect::<std::result::Result<Vec<_>, _>>()?
+            .into_iter()
+            .flatten()
+            .collect(),
+        value => value_to_branches(value, span)?,
+    };
+    if branches.is_empty() {
+        return Err(TreeParseError::EmptyTree(span));
+    }
+    Ok(Tree::from_branches(branches, false))
+}
+
+fn value_to_branches(
+    value: &Value,
+    span: Span,
+) -> std::result::Result<Vec<Branch>, TreeParseError> {
+    match value {
+        Value::String(name) => Ok(vec![Branch::leaf(name_to_node(name, span)?)]),
+        Value::Map(map) => map
+            .iter()
+            .map(|(name, children)| {
+                let Value::String(name) = name else {
+                    return Err(TreeParseError::Ron(span, "expected a system name".into()));
+                };
+                Ok(Branch::tree(name_to_node(name, span)?, value_to_tree(children, span)?))
+            })
+            .collect(),
+        _ => Err(TreeParseError::Ron(span, "expected a system name, a map, or a list".into())),
+    }
+}
+
+fn name_to_node(name: &str, span: Span) -> std::result::Result<Node, TreeParseError> {
+    syn::parse_str::<Path>(name)
+        .map(Node::from)
+        .map_err(|_| TreeParseError::InvalidSystemName(span, name.into()))
+}
@@ -0,0 +1,51 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, ToTokens};
+use syn::{
+    braced,
+    parse::{Parse, ParseStream},
+    Attribute, Ident, Result, Token, Visibility,
+};
+
+use crate::StartupTree;
+
+/// A function definition that returns a startup tree.
+///
+/// ```text
+/// #[attrs] vis name => { tree }
+/// ```
+pub struct StartupTreeFn {
+    pub attrs: Vec<Attribute>,
+    pub vis: Visibility,
+    pub ident: Ident,
+    pub fat_arrow_token: Token![=>],
+    pub tree: StartupTree,
+}
+
+impl Parse for StartupTreeFn {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let attrs = input.call(Attribute::parse_outer)?;
+        let vis = input.parse()?;
+        let ident = input.parse()?;
+        let fat_arrow_token = input.parse()?;
+        let brace_contents;
+        braced!(brace_contents in input);
+        let tree = brace_contents.parse()?;
+        Ok(Self { attrs, vis, ident, fat_arrow_token, tree })
+    }
+}
+
+impl ToTokens for StartupTreeFn {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let Self { attrs, vis, ident, tree, .. } = self;
+        let node_type = tree.node_type();
+        quote! {
+            #(#attrs)*
+            #vis fn #ident() -> ::std::vec::Vec<
+                ::std::vec::Vec<#node_type>
+            > {
+                #tree
+            }
+        }
+        .to_tokens(tokens);
+    }
+}
@@ -0,0 +1,103 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, ToTokens};
+use syn::{
+    bracketed,
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    token::Bracket,
+    Expr, Result, Token,
+};
+
+use crate::{levels_to_tokens, Node, TreeParseError};
+
+/// A startup tree written as its layers instead of as dependencies between nodes.
+///
+/// ```text
+/// [system, ...], ...
+/// ```
+pub struct StartupLayers {
+    pub layers: Punctuated<StartupLayer, Token![,]>,
+}
+
+impl Parse for StartupLayers {
+    fn parse(input: ParseStream) -> Result<Self> {
+        if input.is_empty() {
+            return Err(TreeParseError::EmptyTree(input.span()).into());
+        }
+        let layers = Punctuated::parse_terminated(input)?;
+        Ok(Self { layers })
+    }
+}
+
+impl StartupLayers {
+    /// The nodes of each layer.
+    fn nodes(&self) -> Vec<Vec<Node>> {
+        self.layers
+            .iter()
+            .map(|layer| layer.systems.iter().cloned().map(Node::new).collect())
+            .collect()
+    }
+
+    /// The names of the systems of each layer, as a `&'static [&'static [&'static str]]`, like
+    /// [`StartupTree::to_meta_tokens`](crate::StartupTree::to_meta_tokens).
+    pub fn to_meta_tokens(&self) -> TokenStream2 {
+        let levels = self.nodes().into_iter().map(|layer| {
+            let names = layer.iter().map(Node::name);
+            quote! { &[#(#names),*] }
+        });
+        quote! {
+            {
+                let startup_tree: &'static [&'static [&'static str]] = &[#(#levels),*];
+                startup_tree
+            }
+        }
+    }
+}
+
+impl ToTokens for StartupLayers {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let layers = self.nodes();
+        let levels = layers.iter().map(|layer| layer.iter().collect()).collect();
+        levels_to_tokens(levels, &Node::as_into_descriptor_call).to_tokens(tokens);
+    }
+}
+
+/// The structure of [`StartupLayers`] as the names of its systems by layer.
+///
+/// ```text
+/// [system, ...], ...
+/// ```
+pub struct StartupLayersMeta {
+    pub layers: StartupLayers,
+}
+
+impl Parse for StartupLayersMeta {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let layers = input.parse()?;
+        Ok(Self { layers })
+    }
+}
+
+impl ToTokens for StartupLayersMeta {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        self.layers.to_meta_tokens().to_tokens(tokens);
+    }
+}
+
+/// The systems of one layer of [`StartupLayers`], e.g. `[a, b]`.
+pub struct StartupLayer {
+    pub bracket_token: Bracket,
+    pub systems: Punctuated<Expr, Token![,]>,
+}
+
+impl Parse for StartupLayer {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let content;
+        let bracket_token = bracketed!(content in input);
+        if content.is_empty() {
+            return Err(syn::Error::new(bracket_token.span.join(), "layer may not be empty"));
+        }
+        let systems = Punctuated::parse_terminated(&content)?;
+        Ok(Self { bracket_token, systems })
+    }
+}
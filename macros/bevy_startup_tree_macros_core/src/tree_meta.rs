@@ -0,0 +1,30 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::ToTokens;
+use syn::{
+    parse::{Parse, ParseStream},
+    Result,
+};
+
+use crate::StartupTree;
+
+/// The structure of a startup tree as the names of its nodes by depth.
+///
+/// ```text
+/// tree
+/// ```
+pub struct StartupTreeMeta {
+    pub tree: StartupTree,
+}
+
+impl Parse for StartupTreeMeta {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let tree = input.parse()?;
+        Ok(Self { tree })
+    }
+}
+
+impl ToTokens for StartupTreeMeta {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        self.tree.to_meta_tokens().to_tokens(tokens);
+    }
+}
@@ -0,0 +1,29 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::ToTokens;
+use syn::{
+    parse::{Parse, ParseStream},
+    Result,
+};
+
+use crate::StartupTree;
+
+/// A startup tree whose branches may be separated by whitespace instead of commas.
+///
+/// ```text
+/// tree
+/// ```
+pub struct RelaxedStartupTree {
+    pub tree: StartupTree,
+}
+
+impl Parse for RelaxedStartupTree {
+    fn parse(input: ParseStream) -> Result<Self> {
+        Ok(Self { tree: StartupTree::parse_relaxed(input)? })
+    }
+}
+
+impl ToTokens for RelaxedStartupTree {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        self.tree.to_tokens(tokens);
+    }
+}
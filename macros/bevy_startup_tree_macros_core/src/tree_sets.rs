@@ -0,0 +1,89 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote, ToTokens};
+use syn::{
+    braced,
+    parse::{Parse, ParseStream},
+    Attribute, Ident, Result, Token, Visibility,
+};
+
+use crate::{StartupTree, TreeParseError};
+
+/// An enum with a system set for each layer of a startup tree, and an associated function that
+/// returns the tree with the systems of each layer in its set.
+///
+/// ```text
+/// #[attrs] vis name => { tree }
+/// ```
+pub struct StartupTreeSets {
+    pub attrs: Vec<Attribute>,
+    pub vis: Visibility,
+    pub ident: Ident,
+    pub fat_arrow_token: Token![=>],
+    pub tree: StartupTree,
+}
+
+impl StartupTreeSets {
+    /// The names of the variants of the enum, `L0` for the set of the first layer and so on.
+    pub fn variants(&self) -> Vec<Ident> {
+        (0..self.tree.layer_count()).map(|depth| format_ident!("L{depth}")).collect()
+    }
+}
+
+impl Parse for StartupTreeSets {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let attrs = input.call(Attribute::parse_outer)?;
+        let vis = input.parse()?;
+        let ident = input.parse()?;
+        let fat_arrow_token = input.parse()?;
+        let brace_contents;
+        braced!(brace_contents in input);
+        let tree: StartupTree = brace_contents.parse()?;
+        if let Some(splice_token) = tree.splice_token() {
+            return Err(TreeParseError::SplicedLayerSets(splice_token.span).into());
+        }
+        Ok(Self { attrs, vis, ident, fat_arrow_token, tree })
+    }
+}
+
+impl ToTokens for StartupTreeSets {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let Self { attrs, vis, ident, tree, .. } = self;
+        let variants = self.variants();
+        let layer_count = variants.len();
+        let node_type = tree.node_type();
+        let mut tree_tokens = TokenStream2::new();
+        tree.to_tokens_in_layer_sets(
+            Some(&|depth| {
+                let variant = &variants[depth];
+                quote! { #ident::#variant }
+            }),
+            &mut tree_tokens,
+        );
+        quote! {
+            #(#attrs)*
+            #[derive(
+                ::bevy::ecs::schedule::SystemSet,
+                ::std::clone::Clone,
+                ::std::marker::Copy,
+                ::std::fmt::Debug,
+                ::std::hash::Hash,
+                ::std::cmp::PartialEq,
+                ::std::cmp::Eq,
+            )]
+            #vis enum #ident {
+                #(#variants),*
+            }
+
+            impl #ident {
+                /// The sets of the layers of the tree, indexed by depth.
+                #vis const LAYERS: [Self; #layer_count] = [#(Self::#variants),*];
+
+                /// The startup tree, with the systems of each layer in the layer's set.
+                #vis fn tree() -> ::std::vec::Vec<::std::vec::Vec<#node_type>> {
+                    #tree_tokens
+                }
+            }
+        }
+        .to_tokens(tokens);
+    }
+}
@@ -0,0 +1,71 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, quote_spanned, ToTokens};
+use syn::{
+    braced, bracketed,
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    token::Bracket,
+    LitInt, Result, Token,
+};
+
+use crate::StartupTree;
+
+/// An assertion of the number of nodes in each layer of a startup tree.
+///
+/// ```text
+/// [widths], { tree }
+/// ```
+pub struct StartupTreeShape {
+    pub bracket_token: Bracket,
+    pub widths: Punctuated<LitInt, Token![,]>,
+    pub comma_token: Token![,],
+    pub tree: StartupTree,
+}
+
+impl StartupTreeShape {
+    /// The expected number of nodes in each layer.
+    fn expected_widths(&self) -> Result<Vec<usize>> {
+        self.widths.iter().map(LitInt::base10_parse).collect()
+    }
+}
+
+impl Parse for StartupTreeShape {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let widths_contents;
+        let bracket_token = bracketed!(widths_contents in input);
+        let widths = widths_contents.parse_terminated(LitInt::parse, Token![,])?;
+        let comma_token = input.parse()?;
+        let tree_contents;
+        braced!(tree_contents in input);
+        let tree: StartupTree = tree_contents.parse()?;
+        let _: Option<Token![,]> = input.parse()?;
+        if let Some(splice_token) = tree.splice_token() {
+            return Err(syn::Error::new(
+                splice_token.span,
+                "the shape of a tree with a spliced tree is only known at runtime",
+            ));
+        }
+        let this = Self { bracket_token, widths, comma_token, tree };
+        this.expected_widths()?;
+        Ok(this)
+    }
+}
+
+impl ToTokens for StartupTreeShape {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let expected = self.expected_widths().unwrap_or_default();
+        let span = self.bracket_token.span.join();
+        let errors =
+            self.tree.layer_widths().into_iter().filter(|(_, widths)| *widths != expected).map(
+                |(predicate, widths)| {
+                    let message = format!("expected layer widths {expected:?}, found {widths:?}");
+                    let cfg = predicate.map(|predicate| quote! { #[cfg(#predicate)] });
+                    quote_spanned! {span=> #cfg ::std::compile_error!(#message); }
+                },
+            );
+        quote! {
+            const _: () = { #(#errors)* };
+        }
+        .to_tokens(tokens);
+    }
+}
@@ -0,0 +1,31 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::ToTokens;
+use syn::{
+    parse::{Parse, ParseStream},
+    Result,
+};
+
+use crate::StartupTree;
+
+/// A startup tree whose nodes may only be paths to systems, e.g. no closures or method calls.
+///
+/// ```text
+/// tree
+/// ```
+pub struct StrictStartupTree {
+    pub tree: StartupTree,
+}
+
+impl Parse for StrictStartupTree {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let tree: StartupTree = input.parse()?;
+        tree.check_paths()?;
+        Ok(Self { tree })
+    }
+}
+
+impl ToTokens for StrictStartupTree {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        self.tree.to_tokens(tokens);
+    }
+}
@@ -0,0 +1,33 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, ToTokens};
+use syn::{
+    parse::{Parse, ParseStream},
+    Result,
+};
+
+use crate::StartupTree;
+
+/// A startup tree whose nodes are paired with teardown systems, and the teardown tree of the same
+/// shape.
+///
+/// ```text
+/// tree
+/// ```
+pub struct StartupTeardownTree {
+    pub startup: StartupTree,
+    pub teardown: StartupTree,
+}
+
+impl Parse for StartupTeardownTree {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let (startup, teardown) = StartupTree::parse_with_teardown(input)?;
+        Ok(Self { startup, teardown })
+    }
+}
+
+impl ToTokens for StartupTeardownTree {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let Self { startup, teardown } = self;
+        tokens.extend(quote! { (#startup, #teardown) });
+    }
+}
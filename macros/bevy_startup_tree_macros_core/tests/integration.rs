@@ -1,5 +1,14 @@
-use bevy_startup_tree_macros_core::{Branch, Node, StartupTree, Tree, TreeDepth};
-use quote::quote;
+#[cfg(feature = "file")]
+use bevy_startup_tree_macros_core::tree_from_ron;
+use bevy_startup_tree_macros_core::{
+    levels_to_tokens, tree_to_levels, Branch, Node, RelaxedStartupTree, StartupLayers,
+    StartupLayersMeta, StartupTeardownTree, StartupTree, StartupTreeFn, StartupTreeMeta,
+    StartupTreeSets, StartupTreeShape, StrictStartupTree, Tree, TreeDepth, TreeParseError,
+    MAX_CFGS,
+};
+#[cfg(feature = "file")]
+use proc_macro2::Span;
+use quote::{quote, ToTokens};
 use syn::parse2;
 
 mod utils;
@@ -24,9 +33,8 @@ fn parse_tree_with_long_branch() -> syn::Result<()> {
     Ok(())
 }
 
-#[test]
-fn parse_complex_tree() -> syn::Result<()> {
-    let expected = Tree::from_iter([
+fn complex_tree() -> Tree {
+    Tree::from_iter([
         Branch::from(path!(s1a)),
         Branch::tree(
             Node::from(path!(s1b)),
@@ -35,7 +43,12 @@ fn parse_complex_tree() -> syn::Result<()> {
                 Branch::tree(Node::from(path!(s2b)), Tree::from_iter([path!(s3b), path!(s3c)])),
             ]),
         ),
-    ]);
+    ])
+}
+
+#[test]
+fn parse_complex_tree() -> syn::Result<()> {
+    let expected = complex_tree();
 
     let actual: Tree = parse2(quote! {
         s1a,
@@ -53,6 +66,20 @@ fn parse_complex_tree() -> syn::Result<()> {
     Ok(())
 }
 
+#[test]
+fn complex_tree_stats() {
+    let tree = complex_tree();
+    assert_eq!(tree.depth(), 3);
+    assert_eq!(tree.node_count(), 7);
+}
+
+#[test]
+fn single_node_tree_stats() {
+    let tree = Tree::from(path!(sys));
+    assert_eq!(tree.depth(), 1);
+    assert_eq!(tree.node_count(), 1);
+}
+
 #[test]
 fn parse_tree_branches_and_commas() -> syn::Result<()> {
     let cases = [
@@ -65,7 +92,7 @@ fn parse_tree_branches_and_commas() -> syn::Result<()> {
             )),
         ),
         (quote! { sys2, }, Ok(Tree::from_branch(Branch::from(path!(sys2)), true))),
-        (quote! { sys3 => }, Err("unexpected end of input, expected an expression")),
+        (quote! { sys3 => }, Err("expected a child after `sys3 =>`")),
         (
             quote! { sys4 => child },
             Ok(Tree::from_branch(
@@ -164,6 +191,354 @@ fn parse_tree_branches_and_commas() -> syn::Result<()> {
     Ok(())
 }
 
+#[test]
+fn parse_absolute_path_nodes() -> syn::Result<()> {
+    let tree: Tree = parse2(quote! { ::my_crate::spawn => pure ::my_crate::step })?;
+    let expected = Tree::from(Branch::arm(
+        Node::from(path!(::my_crate::spawn)),
+        Branch::leaf(Node::new_pure(parse2(quote! { ::my_crate::step })?)),
+    ));
+    assert_eq!(tree, expected);
+    Ok(())
+}
+
+#[test]
+fn parse_async_nodes() -> syn::Result<()> {
+    let tree: Tree = parse2(quote! {
+        async sys1 => sys2,
+        sys4 => async sys5
+    })?;
+    let expected = Tree::from_iter([
+        Branch::arm(Node::new_async(parse2(quote! { sys1 })?), Branch::from(path!(sys2))),
+        Branch::arm(
+            Node::from(path!(sys4)),
+            Branch::leaf(Node::new_async(parse2(quote! { sys5 })?)),
+        ),
+    ]);
+    assert_eq!(tree, expected);
+    Ok(())
+}
+
+#[test]
+fn parse_pure_nodes() -> syn::Result<()> {
+    let tree: Tree = parse2(quote! {
+        pure sys1 => pure async sys2,
+        pure => sys3,
+        sys4 => pure,
+        pure::<u8>
+    })?;
+    let mut pure_async = Node::new_async(parse2(quote! { sys2 })?);
+    pure_async.pure_token = Some(Default::default());
+    let expected = Tree::from_iter([
+        Branch::arm(Node::new_pure(parse2(quote! { sys1 })?), Branch::leaf(pure_async)),
+        Branch::arm(Node::from(path!(pure)), Branch::from(path!(sys3))),
+        Branch::arm(Node::from(path!(sys4)), Branch::from(path!(pure))),
+        Branch::from(path!(pure::<u8>)),
+    ]);
+    assert_eq!(tree, expected);
+    Ok(())
+}
+
+#[test]
+fn parse_flush_after_nodes() -> syn::Result<()> {
+    let tree: Tree = parse2(quote! {
+        flush_after sys1 => pure sys2,
+        flush_after
+    })?;
+    let expected = Tree::from_iter([
+        Branch::arm(
+            Node::new_flush_after(parse2(quote! { sys1 })?),
+            Branch::leaf(Node::new_pure(parse2(quote! { sys2 })?)),
+        ),
+        Branch::from(path!(flush_after)),
+    ]);
+    assert_eq!(tree, expected);
+    Ok(())
+}
+
+#[test]
+fn parse_layer_names() -> syn::Result<()> {
+    let tree: Tree = parse2(quote! {
+        "load": sys1,
+        sys2 => "build": pure sys3
+    })?;
+    let expected = Tree::from_iter([
+        Branch::leaf(Node::from(path!(sys1)).with_layer_name(parse2(quote! { "load" })?)),
+        Branch::arm(
+            Node::from(path!(sys2)),
+            Branch::leaf(
+                Node::new_pure(parse2(quote! { sys3 })?)
+                    .with_layer_name(parse2(quote! { "build" })?),
+            ),
+        ),
+    ]);
+    assert_eq!(tree, expected);
+    Ok(())
+}
+
+#[test]
+fn parse_startup_tree_forests() -> syn::Result<()> {
+    let cases = [
+        (quote! { a => b; c => d }, quote! { a => b, c => d }),
+        (quote! { a => b, c; d => e; }, quote! { a => b, c, d => e }),
+        (quote! { a => { b, c }; d => e => f }, quote! { a => { b, c }, d => e => f }),
+    ];
+
+    for (forest_tokens, tree_tokens) in cases {
+        let actual: StartupTree = parse2(forest_tokens)?;
+        let expected: StartupTree = parse2(tree_tokens)?;
+        assert_eq!(quote! { #actual }.to_string(), quote! { #expected }.to_string());
+    }
+
+    let cases = [
+        (quote! { a; ; b }, "tree may not be empty"),
+        (quote! { ; a }, "tree may not be empty"),
+        (quote! { a => { b; c } }, "expected `,`"),
+    ];
+
+    for (tokens, expected) in cases {
+        let actual = parse2::<StartupTree>(tokens).map(|_| ()).map_err(|err| err.to_string());
+        assert_eq!(actual, Err(String::from(expected)));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn parse_tree_of_only_commas() {
+    let cases = [
+        quote! {},
+        quote! { , },
+        quote! { ,, },
+        quote! { , ; , },
+        quote! { a; , },
+        quote! { a => { , } },
+    ];
+
+    for tokens in cases {
+        let expected = Err(String::from("tree may not be empty"));
+        let actual = parse2::<StartupTree>(tokens.clone()).map(|_| ());
+        assert_eq!(actual.map_err(|err| err.to_string()), expected, "{tokens}");
+        let actual = parse2::<StrictStartupTree>(tokens.clone()).map(|_| ());
+        assert_eq!(actual.map_err(|err| err.to_string()), expected, "{tokens}");
+        let actual = parse2::<RelaxedStartupTree>(tokens.clone()).map(|_| ());
+        assert_eq!(actual.map_err(|err| err.to_string()), expected, "{tokens}");
+    }
+
+    assert!(matches!(Tree::parse_tokens(quote! { , }), Err(TreeParseError::EmptyTree(_))));
+}
+
+#[test]
+fn startup_layers_match_equivalent_tree() -> syn::Result<()> {
+    let layers: StartupLayers = parse2(quote! { [s1a, s1b], [s2a], [s3a, s3b] })?;
+    let tree: StartupTree = parse2(quote! {
+        s1a => s2a => { s3a, s3b },
+        s1b,
+    })?;
+
+    assert_eq!(layers.to_token_stream().to_string(), tree.to_token_stream().to_string());
+    Ok(())
+}
+
+#[test]
+fn startup_layers_meta_match_equivalent_tree_meta() -> syn::Result<()> {
+    let layers_meta: StartupLayersMeta = parse2(quote! { [s1a, s1b], [spawn::<Enemy>] })?;
+    let tree_meta: StartupTreeMeta = parse2(quote! { s1a => spawn::<Enemy>, s1b })?;
+
+    assert_eq!(layers_meta.to_token_stream().to_string(), tree_meta.to_token_stream().to_string());
+    Ok(())
+}
+
+#[test]
+fn parse_startup_layers_errors() {
+    let cases = [
+        (quote! {}, "tree may not be empty"),
+        (quote! { [a], [] }, "layer may not be empty"),
+        (quote! { a, [b] }, "expected square brackets"),
+    ];
+
+    for (tokens, expected) in cases {
+        let actual = parse2::<StartupLayers>(tokens.clone()).map(|_| ());
+        assert_eq!(actual.map_err(|err| err.to_string()), Err(String::from(expected)), "{tokens}");
+    }
+}
+
+#[test]
+fn parse_startup_tree_layer_name_errors() {
+    let cases = [
+        (quote! { "a": sys1, "a": sys2 => sys3 }, Ok(())),
+        (
+            quote! { "a": sys1, "b": sys2 },
+            Err("layer is already named \"a\", it can't also be named \"b\""),
+        ),
+        (
+            quote! { "a": sys1 => "a": sys2 },
+            Err("layer name \"a\" is already used by another layer"),
+        ),
+    ];
+
+    for (tokens, expected) in cases {
+        let actual = parse2::<StartupTree>(tokens).map(|_| ()).map_err(|err| err.to_string());
+        assert_eq!(actual, expected.map_err(String::from));
+    }
+}
+
+#[test]
+fn parse_chains() -> syn::Result<()> {
+    let cases = [
+        (quote! { chain![a] }, quote! { a }),
+        (quote! { chain![a, b, c, d, e] }, quote! { a => b => c => d => e }),
+        (quote! { chain![a, b,] => c }, quote! { a => b => c }),
+        (quote! { chain![a, b] => { c, d } }, quote! { a => b => { c, d } }),
+        (quote! { x => chain![a, b] => c, y }, quote! { x => a => b => c, y }),
+        (quote! { x => { chain![async a, b] } }, quote! { x => { async a => b } }),
+        (quote! { chain(a, b) }, quote! { chain(a, b) }),
+    ];
+
+    for (chain_tokens, arrow_tokens) in cases {
+        let actual: Tree = parse2(chain_tokens)?;
+        let expected: Tree = parse2(arrow_tokens)?;
+        assert_eq!(actual, expected);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn parse_pars() -> syn::Result<()> {
+    let cases = [
+        (quote! { par![a] }, quote! { { a } }),
+        (quote! { par![a, b, c] => d }, quote! { { a, b, c } => d }),
+        (quote! { par![a, b,] => d }, quote! { { a, b, } => d }),
+        (quote! { x => par![a, pure b] => { c, d } }, quote! { x => { a, pure b } => { c, d } }),
+        (quote! { x, par![a, b] => c, y }, quote! { x, { a, b } => c, y }),
+        (quote! { par(a, b) }, quote! { par(a, b) }),
+    ];
+
+    for (par_tokens, brace_tokens) in cases {
+        let actual: Tree = parse2(par_tokens)?;
+        let expected: Tree = parse2(brace_tokens)?;
+        assert_eq!(actual, expected);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn par_nodes_share_a_layer() {
+    let tree_meta: StartupTreeMeta = parse2(quote! {
+        par![a, b, c] => d
+    })
+    .expect("failed to arrange for test");
+
+    let expected = quote! {
+        {
+            let startup_tree: &'static [&'static [&'static str]] =
+                &[&["a", "b", "c", "_"], &["d"]];
+            startup_tree
+        }
+    }
+    .to_string();
+
+    let actual = quote! { #tree_meta }.to_string();
+
+    assert_eq!(actual, expected);
+}
+
+#[cfg(feature = "file")]
+#[test]
+fn parse_tree_from_ron() -> syn::Result<()> {
+    let cases = [
+        (r#"["sys"]"#, quote! { sys }),
+        (r#""sys""#, quote! { sys }),
+        (r#"["a", "b"]"#, quote! { a, b }),
+        (r#"[{ "a": ["b", "c"] }, "d"]"#, quote! { a => { b, c }, d }),
+        (r#"{ "a": { "b": "c" }, "d": "e" }"#, quote! { a => { b => { c } }, d => { e } }),
+        (r#"[{ "path::to::a": [{ "b": ["c"] }] }]"#, quote! { path::to::a => { b => { c } } }),
+    ];
+
+    for (source, tokens) in cases {
+        let actual = tree_from_ron(source, Span::call_site()).map_err(syn::Error::from)?;
+        let expected: Tree = parse2(tokens)?;
+        assert_eq!(actual, expected);
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "file")]
+#[test]
+fn parse_tree_from_ron_errors() {
+    let cases = [
+        (r#"["a""#, "Ron"),
+        (r#"[1]"#, "Ron"),
+        (r#"[{ 1: ["a"] }]"#, "Ron"),
+        (r#"[]"#, "EmptyTree"),
+        (r#"[{ "a": [] }]"#, "EmptyTree"),
+        (r#"["not a system"]"#, "InvalidSystemName"),
+        (r#"[{ "a": ["b-c"] }]"#, "InvalidSystemName"),
+    ];
+
+    for (source, expected_variant) in cases {
+        let actual_variant = match tree_from_ron(source, Span::call_site()) {
+            Ok(tree) => panic!("expected a {expected_variant} error, got: {tree:?}"),
+            Err(TreeParseError::Ron(_, _)) => "Ron",
+            Err(TreeParseError::EmptyTree(_)) => "EmptyTree",
+            Err(TreeParseError::InvalidSystemName(_, _)) => "InvalidSystemName",
+            Err(err) => panic!("expected a {expected_variant} error, got: {err}"),
+        };
+        assert_eq!(actual_variant, expected_variant);
+    }
+}
+
+#[test]
+fn parse_tree_structured_errors() {
+    let cases = [
+        (quote! { sys1 => {} }, "EmptyTree"),
+        (quote! { sys2a sys2b }, "ExpectedComma"),
+        (quote! { sys3 => { child sys3b } }, "ExpectedComma"),
+        (quote! { sys4 => }, "DanglingArrow"),
+        (quote! { sys5 => { child => } }, "DanglingArrow"),
+        (quote! { sys6, + }, "Syn"),
+        (quote! { chain![] => sys7 }, "EmptyChain"),
+        (quote! { par![] => sys8 }, "EmptyTree"),
+        (quote! { par![a] => }, "DanglingArrow"),
+        (quote! { @sub => sys9 }, "SplicedParent"),
+        (quote! { sys10 => #[cfg(x)] child }, "InvalidBranchAttribute"),
+        (quote! { { @depth(2) sys11, b } => c }, "PinnedGroupParent"),
+    ];
+
+    for (tokens, expected_variant) in cases {
+        let actual_variant = match Tree::parse_tokens(tokens) {
+            Ok(tree) => panic!("expected a {expected_variant} error, got: {tree:?}"),
+            Err(TreeParseError::EmptyTree(_)) => "EmptyTree",
+            Err(TreeParseError::ExpectedComma(_)) => "ExpectedComma",
+            Err(TreeParseError::DanglingArrow(_, _)) => "DanglingArrow",
+            Err(TreeParseError::EmptyChain(_)) => "EmptyChain",
+            Err(TreeParseError::InvalidArrowAttribute(_)) => "InvalidArrowAttribute",
+            Err(TreeParseError::InvalidBranchAttribute(_)) => "InvalidBranchAttribute",
+            Err(TreeParseError::TooManyCfgs(_, _)) => "TooManyCfgs",
+            Err(TreeParseError::ConflictingLayerNames(_, _, _)) => "ConflictingLayerNames",
+            Err(TreeParseError::DuplicateLayerName(_, _)) => "DuplicateLayerName",
+            Err(TreeParseError::DuplicateNodeLabel(_, _)) => "DuplicateNodeLabel",
+            Err(TreeParseError::OrderingCycle(_, _)) => "OrderingCycle",
+            Err(TreeParseError::CfgLabel(_, _)) => "CfgLabel",
+            Err(TreeParseError::SplicedParent(_)) => "SplicedParent",
+            Err(TreeParseError::ShallowDepthPin(_, _, _)) => "ShallowDepthPin",
+            Err(TreeParseError::PinnedGroupParent(_)) => "PinnedGroupParent",
+            Err(TreeParseError::SplicedTeardown(_)) => "SplicedTeardown",
+            Err(TreeParseError::SplicedLayerSets(_)) => "SplicedLayerSets",
+            Err(TreeParseError::NonPathNode(_)) => "NonPathNode",
+            #[cfg(feature = "file")]
+            Err(TreeParseError::Ron(_, _)) => "Ron",
+            #[cfg(feature = "file")]
+            Err(TreeParseError::InvalidSystemName(_, _)) => "InvalidSystemName",
+            Err(TreeParseError::Syn(_)) => "Syn",
+        };
+        assert_eq!(actual_variant, expected_variant);
+    }
+}
+
 #[test]
 fn tokenize_tree() {
     let tree: StartupTree = parse2(quote! {
@@ -179,7 +554,7 @@ fn tokenize_tree() {
     .expect("failed to arrange for test");
 
     let expected = quote! {
-        vec![
+        ::std::vec![
             ::std::vec![
                 ::bevy::prelude::IntoSystemConfigs::into_configs(s1a),
                 ::bevy::prelude::IntoSystemConfigs::into_configs(s1b)
@@ -204,6 +579,1145 @@ fn tokenize_tree() {
     assert_eq!(actual, expected);
 }
 
+#[test]
+fn tokenize_tree_with_ragged_siblings() {
+    let tree: StartupTree = parse2(quote! {
+        a => { b => c },
+        d,
+    })
+    .expect("failed to arrange for test");
+
+    let expected = quote! {
+        ::std::vec![
+            ::std::vec![
+                ::bevy::prelude::IntoSystemConfigs::into_configs(a),
+                ::bevy::prelude::IntoSystemConfigs::into_configs(d)
+            ],
+            ::std::vec![::bevy::prelude::IntoSystemConfigs::into_configs(b)],
+            ::std::vec![::bevy::prelude::IntoSystemConfigs::into_configs(c)]
+        ]
+    }
+    .to_string();
+
+    let actual = quote! { #tree }.to_string();
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn tokenize_tree_with_annotated_nodes() {
+    let tree: StartupTree = parse2(quote! {
+        pure s1a => "second": s2a,
+        flush_after s1b,
+    })
+    .expect("failed to arrange for test");
+
+    let expected = quote! {
+        ::std::vec![
+            ::std::vec![
+                ::bevy_startup_tree::StartupTreeNode::new(
+                    ::bevy::prelude::IntoSystemConfigs::into_configs(s1a)
+                ).pure(),
+                ::bevy_startup_tree::StartupTreeNode::new(
+                    ::bevy::prelude::IntoSystemConfigs::into_configs(s1b)
+                ).flush_after()
+            ],
+            ::std::vec![
+                ::bevy_startup_tree::StartupTreeNode::new(
+                    ::bevy::prelude::IntoSystemConfigs::into_configs(s2a)
+                ).with_layer_name("second")
+            ]
+        ]
+    }
+    .to_string();
+
+    let actual = quote! { #tree }.to_string();
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn tokenize_tree_with_fan_in() {
+    let tree: StartupTree = parse2(quote! {
+        s1a => s2a,
+        s1b;
+        s1c =>> done,
+    })
+    .expect("failed to arrange for test");
+
+    let expected = quote! {
+        ::std::vec![
+            ::std::vec![
+                ::bevy::prelude::IntoSystemConfigs::into_configs(s1a),
+                ::bevy::prelude::IntoSystemConfigs::into_configs(s1b),
+                ::bevy::prelude::IntoSystemConfigs::into_configs(s1c)
+            ],
+            ::std::vec![::bevy::prelude::IntoSystemConfigs::into_configs(s2a)],
+            ::std::vec![::bevy::prelude::IntoSystemConfigs::into_configs(done)]
+        ]
+    }
+    .to_string();
+
+    let actual = quote! { #tree }.to_string();
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn parse_startup_tree_fan_in_errors() {
+    let cases = [
+        (quote! { a => { b =>> c } }, "expected `,`"),
+        (quote! { a =>> b =>> c }, "unexpected token"),
+        (quote! { "x": a =>> "x": b }, "layer name \"x\" is already used by another layer"),
+    ];
+
+    for (tokens, expected) in cases {
+        let actual = parse2::<StartupTree>(tokens).map(|_| ()).map_err(|err| err.to_string());
+        assert_eq!(actual, Err(String::from(expected)));
+    }
+}
+
+#[test]
+fn tokenize_tree_with_each_suffix() {
+    let tree: StartupTree = parse2(quote! {
+        each: .run_if(cond);
+        s1a => pure s2a,
+        async s1b,
+    })
+    .expect("failed to arrange for test");
+
+    let expected = quote! {
+        ::std::vec![
+            ::std::vec![
+                ::bevy_startup_tree::StartupTreeNode::new(
+                    ::bevy::prelude::IntoSystemConfigs::into_configs(s1a).run_if(cond)
+                ),
+                ::bevy_startup_tree::StartupTreeNode::new(
+                    ::bevy::prelude::IntoSystemConfigs::chain(
+                        (s1b, ::bevy_startup_tree::await_startup_tasks)
+                    ).run_if(cond)
+                )
+            ],
+            ::std::vec![
+                ::bevy_startup_tree::StartupTreeNode::new(
+                    ::bevy::prelude::IntoSystemConfigs::into_configs(s2a).run_if(cond)
+                ).pure()
+            ]
+        ]
+    }
+    .to_string();
+
+    let actual = quote! { #tree }.to_string();
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn parse_startup_tree_each_errors() {
+    let cases = [
+        (
+            quote! { each: run_if(cond); a },
+            "expected a method call like `.run_if(condition)` after `each:`",
+        ),
+        (
+            quote! { each: .run_if(cond) },
+            "unexpected end of input, expected `;` after the `each:` suffix",
+        ),
+        (quote! { each: .run_if(cond); }, "tree may not be empty"),
+    ];
+
+    for (tokens, expected) in cases {
+        let actual = parse2::<StartupTree>(tokens).map(|_| ()).map_err(|err| err.to_string());
+        assert_eq!(actual, Err(String::from(expected)), "{expected}");
+    }
+}
+
+#[test]
+fn parse_startup_tree_each_as_system_path() {
+    let tree: StartupTree = parse2(quote! { each::sys => each }).expect("failed to parse");
+    let expected = quote! {
+        ::std::vec![
+            ::std::vec![::bevy::prelude::IntoSystemConfigs::into_configs(each::sys)],
+            ::std::vec![::bevy::prelude::IntoSystemConfigs::into_configs(each)]
+        ]
+    }
+    .to_string();
+    assert_eq!(quote! { #tree }.to_string(), expected);
+}
+
+#[test]
+fn tokenize_tree_with_cfg_arrows() {
+    let tree: StartupTree = parse2(quote! {
+        s1a #[cfg(feature = "x")] => s2a => s3a,
+    })
+    .expect("failed to arrange for test");
+
+    let expected = quote! {
+        {
+            #[cfg(all(not(feature = "x")))]
+            let startup_tree = ::std::vec![
+                ::std::vec![
+                    ::bevy::prelude::IntoSystemConfigs::into_configs(s1a),
+                    ::bevy::prelude::IntoSystemConfigs::into_configs(s2a)
+                ],
+                ::std::vec![::bevy::prelude::IntoSystemConfigs::into_configs(s3a)]
+            ];
+            #[cfg(all(feature = "x"))]
+            let startup_tree = ::std::vec![
+                ::std::vec![::bevy::prelude::IntoSystemConfigs::into_configs(s1a)],
+                ::std::vec![::bevy::prelude::IntoSystemConfigs::into_configs(s2a)],
+                ::std::vec![::bevy::prelude::IntoSystemConfigs::into_configs(s3a)]
+            ];
+            startup_tree
+        }
+    }
+    .to_string();
+
+    let actual = quote! { #tree }.to_string();
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn parse_startup_tree_cfg_arrow_errors() {
+    let cases = [
+        (
+            quote! { a #[test] => b },
+            "only a `#[cfg(...)]` attribute followed by `=>` is allowed here",
+        ),
+        (
+            quote! { a #[cfg(x)] b },
+            "only a `#[cfg(...)]` attribute followed by `=>` is allowed here",
+        ),
+        (quote! { a #[cfg(x)] => }, "expected a child after `a =>`"),
+        (
+            quote! { "x": a #[cfg(y)] => "z": b },
+            "layer is already named \"x\", it can't also be named \"z\"",
+        ),
+    ];
+
+    for (tokens, expected) in cases {
+        let actual = parse2::<StartupTree>(tokens).map(|_| ()).map_err(|err| err.to_string());
+        assert_eq!(actual, Err(String::from(expected)), "{expected}");
+    }
+}
+
+#[test]
+fn parse_startup_tree_too_many_cfgs() {
+    let arrows = |n: usize| {
+        let features = (0..n).map(|i| format!("f{i}"));
+        quote! { a #(#[cfg(feature = #features)] => b)* }
+    };
+
+    assert!(parse2::<StartupTree>(arrows(MAX_CFGS)).is_ok());
+
+    let actual = parse2::<StartupTree>(arrows(64)).map(|_| ()).map_err(|err| err.to_string());
+    let expected = "a tree can have at most 8 distinct `#[cfg(...)]` attributes, found 64";
+    assert_eq!(actual, Err(String::from(expected)));
+}
+
+#[test]
+fn tokenize_tree_with_cfg_branches() {
+    let tree: StartupTree = parse2(quote! {
+        #[cfg(feature = "web")] s1a => s2a,
+        s1b,
+    })
+    .expect("failed to arrange for test");
+
+    let expected = quote! {
+        {
+            #[cfg(all(not(feature = "web")))]
+            let startup_tree = ::std::vec![
+                ::std::vec![::bevy::prelude::IntoSystemConfigs::into_configs(s1b)]
+            ];
+            #[cfg(all(feature = "web"))]
+            let startup_tree = ::std::vec![
+                ::std::vec![
+                    ::bevy::prelude::IntoSystemConfigs::into_configs(s1a),
+                    ::bevy::prelude::IntoSystemConfigs::into_configs(s1b)
+                ],
+                ::std::vec![::bevy::prelude::IntoSystemConfigs::into_configs(s2a)]
+            ];
+            startup_tree
+        }
+    }
+    .to_string();
+
+    let actual = quote! { #tree }.to_string();
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn tokenize_tree_with_every_branch_cfg_disabled() {
+    let tree: StartupTree = parse2(quote! {
+        #[cfg(feature = "web")] { s1a, s1b }
+    })
+    .expect("failed to arrange for test");
+
+    let expected = quote! {
+        {
+            #[cfg(all(not(feature = "web")))]
+            let startup_tree = ::std::vec::Vec::<
+                ::std::vec::Vec<::bevy::ecs::schedule::SystemConfigs>>::new();
+            #[cfg(all(feature = "web"))]
+            let startup_tree = ::std::vec![
+                ::std::vec![
+                    ::bevy::prelude::IntoSystemConfigs::into_configs(s1a),
+                    ::bevy::prelude::IntoSystemConfigs::into_configs(s1b)
+                ]
+            ];
+            startup_tree
+        }
+    }
+    .to_string();
+
+    let actual = quote! { #tree }.to_string();
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn parse_startup_tree_cfg_branch_errors() {
+    let message = "only a single `#[cfg(...)]` attribute on a top-level branch is allowed";
+    let cases = [
+        quote! { #[test] a },
+        quote! { #[cfg(x)] #[cfg(y)] a },
+        quote! { a => #[cfg(x)] b },
+        quote! { a => { b, #[cfg(x)] c } },
+        quote! { { #[cfg(x)] a } },
+    ];
+
+    for tokens in cases {
+        let actual = parse2::<StartupTree>(tokens).map(|_| ()).map_err(|err| err.to_string());
+        assert_eq!(actual, Err(String::from(message)));
+    }
+}
+
+#[test]
+fn tokenize_tree_with_node_sets() {
+    let tree: StartupTree = parse2(quote! {
+        s1a in PlayerSet => async s2a in Sets::Spawn,
+    })
+    .expect("failed to arrange for test");
+
+    let expected = quote! {
+        ::std::vec![
+            ::std::vec![::bevy::prelude::IntoSystemConfigs::in_set(
+                ::bevy::prelude::IntoSystemConfigs::into_configs(s1a),
+                PlayerSet
+            )],
+            ::std::vec![::bevy::prelude::IntoSystemConfigs::in_set(
+                ::bevy::prelude::IntoSystemConfigs::chain(
+                    (s2a, ::bevy_startup_tree::await_startup_tasks)
+                ),
+                Sets::Spawn
+            )]
+        ]
+    }
+    .to_string();
+
+    let actual = quote! { #tree }.to_string();
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn tokenize_tree_with_pinned_depths() {
+    let tree: StartupTree = parse2(quote! {
+        s1a => @depth(3) s4a => s5a,
+        @depth(1) s2a,
+    })
+    .expect("failed to arrange for test");
+
+    let expected = quote! {
+        ::std::vec![
+            ::std::vec![::bevy::prelude::IntoSystemConfigs::into_configs(s1a)],
+            ::std::vec![::bevy::prelude::IntoSystemConfigs::into_configs(s2a)],
+            ::std::vec![],
+            ::std::vec![::bevy::prelude::IntoSystemConfigs::into_configs(s4a)],
+            ::std::vec![::bevy::prelude::IntoSystemConfigs::into_configs(s5a)]
+        ]
+    }
+    .to_string();
+
+    let actual = quote! { #tree }.to_string();
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn parse_startup_tree_depth_pin_errors() {
+    let cases = [
+        (quote! { a => @depth(1) b }, Ok(())),
+        (quote! { @depth(2), a }, Ok(())),
+        (
+            quote! { a => @depth(0) b },
+            Err("node can't be pinned to depth 0, it must run at depth 1 or later to run after its parent"),
+        ),
+        (
+            quote! { @depth(3) a => { b, @depth(3) c } },
+            Err("node can't be pinned to depth 3, it must run at depth 4 or later to run after its parent"),
+        ),
+        (
+            quote! { { @depth(2) a, b } => c },
+            Err("a group with children can't contain nodes pinned with `@depth(..)`"),
+        ),
+    ];
+
+    for (tokens, expected) in cases {
+        let actual = parse2::<StartupTree>(tokens).map(|_| ()).map_err(|err| err.to_string());
+        assert_eq!(actual, expected.map_err(String::from));
+    }
+}
+
+#[test]
+fn parse_node_set_with_label() -> syn::Result<()> {
+    for tokens in [quote! { sys as x in PlayerSet }, quote! { sys in PlayerSet as x }] {
+        let node: Node = parse2(tokens)?;
+        assert_eq!(node.name(), "sys");
+        assert_eq!(node.label().map(ToString::to_string).as_deref(), Some("x"));
+        let (_, set) = node.in_set.expect("node should have a set");
+        assert_eq!(quote! { #set }.to_string(), "PlayerSet");
+    }
+    Ok(())
+}
+
+#[test]
+fn tokenize_tree_with_node_labels() {
+    let tree: StartupTree = parse2(quote! {
+        s1a as setup,
+        s1b.after(setup).run_if(cond).before(s1a) => s2a.after(setup),
+    })
+    .expect("failed to arrange for test");
+
+    let expected = quote! {
+        ::std::vec![
+            ::std::vec![
+                ::bevy_startup_tree::StartupTreeNode::new(
+                    ::bevy::prelude::IntoSystemConfigs::into_configs(s1a)
+                ).with_label("setup"),
+                ::bevy_startup_tree::StartupTreeNode::new(
+                    ::bevy::prelude::IntoSystemConfigs::into_configs(s1b.run_if(cond).before(s1a))
+                ).after_label("setup")
+            ],
+            ::std::vec![
+                ::bevy_startup_tree::StartupTreeNode::new(
+                    ::bevy::prelude::IntoSystemConfigs::into_configs(s2a)
+                ).after_label("setup")
+            ]
+        ]
+    }
+    .to_string();
+
+    let actual = quote! { #tree }.to_string();
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn parse_startup_tree_node_label_errors() {
+    let cases = [
+        (quote! { a as x, b as x }, "node label `x` is already used by another node"),
+        (quote! { a as x.y }, "casts cannot be followed by a field access"),
+        (quote! { a as x::y }, "expected a node label"),
+        (quote! { a as x => b.before(x) }, "ordering against `x` creates a cycle"),
+        (quote! { a.after(x), b => c as x }, "ordering against `x` creates a cycle"),
+        (quote! { a.after(x) as x }, "ordering against `x` creates a cycle"),
+        (quote! { a.after(y) as x, b.after(x) as y }, "ordering against `y` creates a cycle"),
+        (
+            quote! { #[cfg(feature = "x")] a as la, b.after(la) },
+            "node labeled `la` is left out by a `#[cfg]` that this node isn't",
+        ),
+    ];
+
+    for (tokens, expected) in cases {
+        let actual = parse2::<StartupTree>(tokens).map(|_| ()).map_err(|err| err.to_string());
+        assert_eq!(actual, Err(String::from(expected)), "{expected}");
+    }
+}
+
+#[test]
+fn parse_startup_tree_orderings_within_cfg_branch() {
+    let tree = parse2::<StartupTree>(quote! { #[cfg(feature = "x")] { a as la, b.after(la) }, c });
+    assert!(tree.is_ok());
+}
+
+#[test]
+fn split_startup_teardown_tree() -> syn::Result<()> {
+    let tree: StartupTeardownTree = parse2(quote! {
+        "load": a / x => {
+            pure b.after(l) / y,
+            (|| {}) / (|| {}) as l in Set,
+            c,
+        }
+        =>> d / z
+    })?;
+
+    let startup: StartupTree = parse2(quote! {
+        "load": a => {
+            pure b.after(l),
+            (|| {}) as l in Set,
+            c,
+        }
+        =>> d
+    })?;
+    let teardown: StartupTree = parse2(quote! {
+        "load": x => {
+            y,
+            (|| {}) as l,
+            _,
+        }
+        =>> z
+    })?;
+
+    assert_eq!(tree.startup.to_token_stream().to_string(), startup.to_token_stream().to_string());
+    assert_eq!(tree.teardown.to_token_stream().to_string(), teardown.to_token_stream().to_string());
+    Ok(())
+}
+
+#[test]
+fn startup_teardown_tree_rejects_splices() {
+    let actual = parse2::<StartupTeardownTree>(quote! { a => @sub }).map(|_| ());
+    let actual = actual.map_err(|err| err.to_string());
+    assert_eq!(actual, Err(String::from("a startup teardown tree can't contain spliced trees")));
+}
+
+#[test]
+fn tokenize_tree_with_pipes() {
+    let tree: StartupTree = parse2(quote! {
+        pipe { produce => transform => consume } => s2a,
+        pipe { only },
+    })
+    .expect("failed to arrange for test");
+
+    let expected = quote! {
+        ::std::vec![
+            ::std::vec![
+                ::bevy::prelude::IntoSystemConfigs::into_configs(
+                    ::bevy::prelude::IntoSystem::pipe(
+                        ::bevy::prelude::IntoSystem::pipe(produce, transform),
+                        consume
+                    )
+                ),
+                ::bevy::prelude::IntoSystemConfigs::into_configs(only)
+            ],
+            ::std::vec![::bevy::prelude::IntoSystemConfigs::into_configs(s2a)]
+        ]
+    }
+    .to_string();
+
+    let actual = quote! { #tree }.to_string();
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn tokenize_tree_with_optional_pipe_steps() {
+    let tree: StartupTree = parse2(quote! {
+        pipe { read => parse =?> validate => spawn },
+        pipe { a = ? > b },
+    })
+    .expect("failed to arrange for test");
+
+    let expected = quote! {
+        ::std::vec![::std::vec![
+            ::bevy::prelude::IntoSystemConfigs::into_configs(
+                ::bevy::prelude::IntoSystem::pipe(
+                    ::bevy::prelude::IntoSystem::pipe(
+                        ::bevy::prelude::IntoSystem::pipe(read, parse),
+                        ::bevy_startup_tree::optional_step(validate)
+                    ),
+                    spawn
+                )
+            ),
+            ::bevy::prelude::IntoSystemConfigs::into_configs(
+                ::bevy::prelude::IntoSystem::pipe(a, ::bevy_startup_tree::optional_step(b))
+            )
+        ]]
+    }
+    .to_string();
+
+    let actual = quote! { #tree }.to_string();
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn tokenize_tree_with_generic_pipe_systems() {
+    let tree: StartupTree = parse2(quote! {
+        pipe { produce::<Vec<u8>> => consume::<2> },
+    })
+    .expect("failed to arrange for test");
+
+    let expected = quote! {
+        ::std::vec![
+            ::std::vec![::bevy::prelude::IntoSystemConfigs::into_configs(
+                ::bevy::prelude::IntoSystem::pipe(produce::<Vec<u8> >, consume::<2>)
+            )]
+        ]
+    }
+    .to_string();
+
+    let actual = quote! { #tree }.to_string();
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn tokenize_tree_with_splices() {
+    let tree: StartupTree = parse2(quote! {
+        s1a => @sub,
+        s1b => s2b => @make_sub(),
+    })
+    .expect("failed to arrange for test");
+
+    let expected = quote! {
+        {
+            let mut startup_tree = ::std::vec![
+                ::std::vec![
+                    ::bevy_startup_tree::StartupTreeNode::new(
+                        ::bevy::prelude::IntoSystemConfigs::into_configs(s1a)
+                    ),
+                    ::bevy_startup_tree::StartupTreeNode::new(
+                        ::bevy::prelude::IntoSystemConfigs::into_configs(s1b)
+                    )
+                ],
+                ::std::vec![::bevy_startup_tree::StartupTreeNode::new(
+                    ::bevy::prelude::IntoSystemConfigs::into_configs(s2b)
+                )],
+                ::std::vec![]
+            ];
+            ::bevy_startup_tree::splice_startup_tree(&mut startup_tree, 1usize, sub);
+            ::bevy_startup_tree::splice_startup_tree(&mut startup_tree, 2usize, make_sub());
+            startup_tree
+        }
+    }
+    .to_string();
+
+    let actual = quote! { #tree }.to_string();
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn parse_startup_tree_splice_errors() {
+    let message = "nothing can run after a spliced tree, its depth is only known at runtime";
+    let cases = [
+        quote! { @sub => a },
+        quote! { a => @sub => b },
+        quote! { { a, @sub } => b },
+        quote! { par![a, @sub] => b },
+        quote! { chain![@sub, a] },
+        quote! { a => @sub =>> done },
+    ];
+
+    for tokens in cases {
+        let actual = parse2::<StartupTree>(tokens).map(|_| ()).map_err(|err| err.to_string());
+        assert_eq!(actual, Err(String::from(message)));
+    }
+}
+
+#[test]
+fn parse_startup_tree_pipe_errors() {
+    let cases = [
+        (quote! { pipe {} }, "pipe may not be empty"),
+        (quote! { pipe { a, b } }, "expected `=>`"),
+        (quote! { pipe { a => } }, "unexpected end of input, expected an expression"),
+        (quote! { pipe { a =?> } }, "unexpected end of input, expected an expression"),
+        (quote! { pipe { =?> a } }, "expected an expression"),
+        (quote! { pipe { a => b } => }, "expected a child after `pipe { a => b } =>`"),
+    ];
+
+    for (tokens, expected) in cases {
+        let actual = parse2::<StartupTree>(tokens).map(|_| ()).map_err(|err| err.to_string());
+        assert_eq!(actual, Err(String::from(expected)), "{expected}");
+    }
+}
+
+#[test]
+fn tokenize_tree_with_groups() {
+    let tree: StartupTree = parse2(quote! {
+        { s1a => s2a, s1b } => s3a,
+        s1c => { s2b } => s3b,
+    })
+    .expect("failed to arrange for test");
+
+    let expected = quote! {
+        ::std::vec![
+            ::std::vec![
+                ::bevy_startup_tree::StartupTreeNode::new(
+                    ::bevy::prelude::IntoSystemConfigs::into_configs(s1a)
+                ),
+                ::bevy_startup_tree::StartupTreeNode::new(
+                    ::bevy::prelude::IntoSystemConfigs::into_configs(s1b)
+                ),
+                ::bevy_startup_tree::StartupTreeNode::placeholder().pure(),
+                ::bevy_startup_tree::StartupTreeNode::new(
+                    ::bevy::prelude::IntoSystemConfigs::into_configs(s1c)
+                )
+            ],
+            ::std::vec![
+                ::bevy_startup_tree::StartupTreeNode::new(
+                    ::bevy::prelude::IntoSystemConfigs::into_configs(s2a)
+                ),
+                ::bevy_startup_tree::StartupTreeNode::placeholder().pure(),
+                ::bevy_startup_tree::StartupTreeNode::new(
+                    ::bevy::prelude::IntoSystemConfigs::into_configs(s2b)
+                ),
+                ::bevy_startup_tree::StartupTreeNode::placeholder().pure()
+            ],
+            ::std::vec![
+                ::bevy_startup_tree::StartupTreeNode::new(
+                    ::bevy::prelude::IntoSystemConfigs::into_configs(s3a)
+                ),
+                ::bevy_startup_tree::StartupTreeNode::new(
+                    ::bevy::prelude::IntoSystemConfigs::into_configs(s3b)
+                )
+            ]
+        ]
+    }
+    .to_string();
+
+    let actual = quote! { #tree }.to_string();
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn tokenize_tree_with_placeholders() {
+    let tree: StartupTree = parse2(quote! {
+        s1a => _ => s3a,
+        _ => "gap": _,
+    })
+    .expect("failed to arrange for test");
+
+    let expected = quote! {
+        ::std::vec![
+            ::std::vec![
+                ::bevy_startup_tree::StartupTreeNode::new(
+                    ::bevy::prelude::IntoSystemConfigs::into_configs(s1a)
+                ),
+                ::bevy_startup_tree::StartupTreeNode::placeholder()
+            ],
+            ::std::vec![
+                ::bevy_startup_tree::StartupTreeNode::placeholder(),
+                ::bevy_startup_tree::StartupTreeNode::placeholder().with_layer_name("gap")
+            ],
+            ::std::vec![
+                ::bevy_startup_tree::StartupTreeNode::new(
+                    ::bevy::prelude::IntoSystemConfigs::into_configs(s3a)
+                )
+            ]
+        ]
+    }
+    .to_string();
+
+    let actual = quote! { #tree }.to_string();
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn tokenize_tree_with_generic_systems() {
+    let tree: StartupTree = parse2(quote! {
+        spawn::<Enemy> => {
+            spawn::<Ally>,
+            path::to::spawn::<Boss, 3> => spawn::<Vec<Item>>,
+        },
+    })
+    .expect("failed to arrange for test");
+
+    let expected = quote! {
+        ::std::vec![
+            ::std::vec![::bevy::prelude::IntoSystemConfigs::into_configs(spawn::<Enemy>)],
+            ::std::vec![
+                ::bevy::prelude::IntoSystemConfigs::into_configs(spawn::<Ally>),
+                ::bevy::prelude::IntoSystemConfigs::into_configs(path::to::spawn::<Boss, 3>)
+            ],
+            // syn splits the `>>` of nested generics into separate puncts
+            ::std::vec![::bevy::prelude::IntoSystemConfigs::into_configs(spawn::<Vec<Item> >)]
+        ]
+    }
+    .to_string();
+
+    let actual = quote! { #tree }.to_string();
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn tokenize_tree_with_absolute_paths() {
+    let tree: StartupTree = parse2(quote! {
+        ::my_crate::spawn => ::my_crate::systems::setup,
+    })
+    .expect("failed to arrange for test");
+
+    let expected = quote! {
+        ::std::vec![
+            ::std::vec![::bevy::prelude::IntoSystemConfigs::into_configs(::my_crate::spawn)],
+            ::std::vec![
+                ::bevy::prelude::IntoSystemConfigs::into_configs(::my_crate::systems::setup)
+            ]
+        ]
+    }
+    .to_string();
+
+    let actual = quote! { #tree }.to_string();
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn tokenize_tree_fn() {
+    let tree_fn: StartupTreeFn = parse2(quote! {
+        pub(crate) my_tree => {
+            s1a => s2a,
+            s1b,
+        }
+    })
+    .expect("failed to arrange for test");
+
+    let expected = quote! {
+        pub(crate) fn my_tree() -> ::std::vec::Vec<
+            ::std::vec::Vec<::bevy::ecs::schedule::SystemConfigs>
+        > {
+            ::std::vec![
+                ::std::vec![
+                    ::bevy::prelude::IntoSystemConfigs::into_configs(s1a),
+                    ::bevy::prelude::IntoSystemConfigs::into_configs(s1b)
+                ],
+                ::std::vec![::bevy::prelude::IntoSystemConfigs::into_configs(s2a)]
+            ]
+        }
+    }
+    .to_string();
+
+    let actual = quote! { #tree_fn }.to_string();
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn tokenize_tree_sets() {
+    let tree_sets: StartupTreeSets = parse2(quote! {
+        /// Docs
+        pub Layers => {
+            s1a => s2a in Set,
+            s1b,
+        }
+    })
+    .expect("failed to arrange for test");
+
+    let expected = quote! {
+        /// Docs
+        #[derive(
+            ::bevy::ecs::schedule::SystemSet,
+            ::std::clone::Clone,
+            ::std::marker::Copy,
+            ::std::fmt::Debug,
+            ::std::hash::Hash,
+            ::std::cmp::PartialEq,
+            ::std::cmp::Eq,
+        )]
+        pub enum Layers {
+            L0, L1
+        }
+
+        impl Layers {
+            /// The sets of the layers of the tree, indexed by depth.
+            pub const LAYERS: [Self; 2usize] = [Self::L0, Self::L1];
+
+            /// The startup tree, with the systems of each layer in the layer's set.
+            pub fn tree() -> ::std::vec::Vec<::std::vec::Vec<::bevy::ecs::schedule::SystemConfigs>> {
+                ::std::vec![
+                    ::std::vec![
+                        ::bevy::prelude::IntoSystemConfigs::in_set(
+                            ::bevy::prelude::IntoSystemConfigs::into_configs(s1a),
+                            Layers::L0
+                        ),
+                        ::bevy::prelude::IntoSystemConfigs::in_set(
+                            ::bevy::prelude::IntoSystemConfigs::into_configs(s1b),
+                            Layers::L0
+                        )
+                    ],
+                    ::std::vec![::bevy::prelude::IntoSystemConfigs::in_set(
+                        ::bevy::prelude::IntoSystemConfigs::in_set(
+                            ::bevy::prelude::IntoSystemConfigs::into_configs(s2a),
+                            Set
+                        ),
+                        Layers::L1
+                    )]
+                ]
+            }
+        }
+    }
+    .to_string();
+
+    let actual = quote! { #tree_sets }.to_string();
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn tree_sets_count_layers_of_deepest_cfg_variant() -> syn::Result<()> {
+    let tree_sets: StartupTreeSets = parse2(quote! {
+        Layers => {
+            a #[cfg(x)] => b => c,
+            #[cfg(y)] d => e => f => g,
+        }
+    })?;
+    assert_eq!(tree_sets.variants(), ["L0", "L1", "L2", "L3"]);
+
+    let err = parse2::<StartupTreeSets>(quote! { Layers => { a => @sub } }).map(|_| ());
+    assert_eq!(
+        err.map_err(|err| err.to_string()),
+        Err(String::from("a tree with generated layer sets can't contain spliced trees"))
+    );
+    Ok(())
+}
+
+#[test]
+fn tokenize_tree_meta() {
+    let tree_meta: StartupTreeMeta = parse2(quote! {
+        "load": s1a => pure spawn::<Enemy> as enemies,
+        s1b.run_if(cond) => _ => pipe { produce => consume },
+    })
+    .expect("failed to arrange for test");
+
+    let expected = quote! {
+        {
+            let startup_tree: &'static [&'static [&'static str]] = &[
+                &["s1a", "s1b . run_if (cond)"],
+                &["spawn::<Enemy>", "_"],
+                &["pipe { produce => consume }"]
+            ];
+            startup_tree
+        }
+    }
+    .to_string();
+
+    let actual = quote! { #tree_meta }.to_string();
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn tokenize_tree_meta_with_cfg_arrows() {
+    let tree_meta: StartupTreeMeta = parse2(quote! {
+        s1a #[cfg(test)] => s2a
+    })
+    .expect("failed to arrange for test");
+
+    let expected = quote! {
+        {
+            #[cfg(all(not(test)))]
+            let startup_tree: &'static [&'static [&'static str]] = &[&["s1a", "s2a"]];
+            #[cfg(all(test))]
+            let startup_tree: &'static [&'static [&'static str]] = &[&["s1a"], &["s2a"]];
+            startup_tree
+        }
+    }
+    .to_string();
+
+    let actual = quote! { #tree_meta }.to_string();
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn tokenize_tree_shape() {
+    let tree_shape: StartupTreeShape = parse2(quote! {
+        [3, 4, 1], { a, b => c => d, e => { f, g, h } }
+    })
+    .expect("failed to arrange for test");
+
+    let expected = quote! { const _: () = {}; }.to_string();
+
+    let actual = quote! { #tree_shape }.to_string();
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn tokenize_tree_shape_mismatch() {
+    let tree_shape: StartupTreeShape = parse2(quote! {
+        [1, 2], { a => b => c }
+    })
+    .expect("failed to arrange for test");
+
+    let expected = quote! {
+        const _: () = {
+            ::std::compile_error!("expected layer widths [1, 2], found [1, 1, 1]");
+        };
+    }
+    .to_string();
+
+    let actual = quote! { #tree_shape }.to_string();
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn tokenize_tree_shape_with_cfg_arrows() {
+    let tree_shape: StartupTreeShape = parse2(quote! {
+        [1, 1], { a #[cfg(test)] => b }
+    })
+    .expect("failed to arrange for test");
+
+    let expected = quote! {
+        const _: () = {
+            #[cfg(all(not(test)))]
+            ::std::compile_error!("expected layer widths [1, 1], found [2]");
+        };
+    }
+    .to_string();
+
+    let actual = quote! { #tree_shape }.to_string();
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn parse_tree_shape_errors() {
+    let cases = [
+        (
+            quote! { [1], { a => @b } },
+            "the shape of a tree with a spliced tree is only known at runtime",
+        ),
+        (quote! { [1, x], { a } }, "expected integer literal"),
+        (quote! { [1] { a } }, "expected `,`"),
+    ];
+    for (tokens, expected) in cases {
+        let err = parse2::<StartupTreeShape>(tokens).err().expect("expected an error");
+        assert_eq!(err.to_string(), expected);
+    }
+}
+
+#[test]
+fn tree_levels_without_startup_tree() {
+    let tree: Tree = parse2(quote! {
+        s1a => { s2a, s2b => s3a },
+        s1b #[cfg(x)] => s2c,
+    })
+    .expect("failed to arrange for test");
+
+    let names = |enabled: &dyn Fn(&syn::Attribute) -> bool| -> Vec<Vec<String>> {
+        let levels = tree_to_levels(&tree, enabled);
+        levels.iter().map(|level| level.iter().map(|node| node.name()).collect()).collect()
+    };
+    assert_eq!(names(&|_| true), [vec!["s1a", "s1b"], vec!["s2a", "s2b", "s2c"], vec!["s3a"]]);
+    assert_eq!(names(&|_| false), [vec!["s1a", "s1b", "s2c"], vec!["s2a", "s2b"], vec!["s3a"]]);
+
+    let expected = quote! {
+        ::std::vec![
+            ::std::vec![s1a, s1b],
+            ::std::vec![s2a, s2b, s2c],
+            ::std::vec![s3a]
+        ]
+    }
+    .to_string();
+    let actual = levels_to_tokens(tree_to_levels(&tree, &|_| true), &|node| {
+        let expr = &node.expr;
+        quote! { #expr }
+    })
+    .to_string();
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn parse_strict_startup_tree() {
+    let accepted = [
+        quote! { a => b::c => d::<E> },
+        quote! { "load": pure a as x => { b.after(x), async c } => _ },
+        quote! { a => pipe { b => c } },
+        quote! { a, b =>> c },
+    ];
+    for tokens in accepted {
+        let actual = parse2::<StrictStartupTree>(tokens.clone()).map(|_| ());
+        assert!(actual.is_ok(), "{tokens}");
+    }
+
+    let rejected = [
+        quote! { a => || {} },
+        quote! { a.run_if(cond) },
+        quote! { a => pipe { b => |In(x): In<u32>| {} } },
+        quote! { a =>> b.after(c) },
+    ];
+    for tokens in rejected {
+        let actual = parse2::<StrictStartupTree>(tokens).map(|_| ()).map_err(|err| err.to_string());
+        assert_eq!(
+            actual,
+            Err(String::from("only paths to systems are allowed as nodes of a strict tree"))
+        );
+    }
+}
+
+#[test]
+fn parse_relaxed_startup_tree() -> syn::Result<()> {
+    let cases = [
+        (quote! { a b }, quote! { a, b }),
+        (quote! { a => b c => { d e => f } }, quote! { a => b, c => { d, e => f } }),
+        (quote! { a, b c, }, quote! { a, b, c, }),
+        (
+            quote! { x::a as y b.after(y) => chain![c, d] },
+            quote! { x::a as y, b.after(y) => chain![c, d] },
+        ),
+        (quote! { a b; c d =>> e }, quote! { a, b; c, d =>> e }),
+    ];
+    for (relaxed, expected) in cases {
+        let actual = parse2::<RelaxedStartupTree>(relaxed.clone())?.tree.to_token_stream();
+        let expected = parse2::<StartupTree>(expected)?.to_token_stream();
+        assert_eq!(actual.to_string(), expected.to_string(), "{relaxed}");
+    }
+
+    let actual = parse2::<StartupTree>(quote! { a b }).map(|_| ()).map_err(|err| err.to_string());
+    assert_eq!(actual, Err(String::from("expected `,`")));
+    Ok(())
+}
+
+#[test]
+fn display_round_trips() -> syn::Result<()> {
+    let cases = [
+        quote! { a },
+        quote! { a => b => c, d, },
+        quote! { a => { b, c => { d, e, } }, f },
+        quote! { "load": async a as x in Set => { pure b.after(x), flush_after c::<D> } },
+        quote! { a.run_if(cond) => |mut commands: Commands| commands.spawn(()) },
+        quote! { chain![a, b] => par![c, d] },
+        quote! { { a, b => c } => d, e },
+        quote! { { a, b, } },
+        quote! { a #[cfg(feature = "x")] => { b, c } },
+        quote! { pipe { a => b } as p => @sub },
+        quote! { pipe { a =?> b => c =?> d } },
+        quote! { pure pure::a => flush_after },
+        quote! { a => @depth(3) "x": b, @depth(1) c },
+    ];
+    for tokens in cases {
+        let tree: Tree = parse2(tokens)?;
+        let rendered = tree.to_string();
+        let reparsed: Tree = syn::parse_str(&rendered)?;
+        assert_eq!(reparsed, tree, "{rendered}");
+    }
+    Ok(())
+}
+
+#[test]
+fn clone_subtree() -> syn::Result<()> {
+    let tree: Tree = parse2(quote! {
+        "load": async a as x in Set => { pure b.after(x), pipe { c => d } => _ },
+        chain![e, f] => @g,
+    })?;
+
+    let mut cloned = tree.clone();
+    assert_eq!(cloned, tree);
+
+    let subtree = cloned.branches[0].sub_tree_mut().unwrap().clone();
+    cloned.branches.push(Branch::tree(Node::from(path!(h)), subtree));
+    assert_eq!(cloned.node_count(), tree.node_count() + 4);
+    Ok(())
+}
+
 #[test]
 fn calculate_tree_depth() {
     #[derive(Debug, PartialEq)]
@@ -0,0 +1,1323 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt::{self, Write},
+    rc::Rc,
+};
+
+use bevy_app::{App, MainScheduleOrder, Startup};
+use bevy_ecs::{
+    schedule::{
+        apply_deferred, common_conditions::run_once, Condition, InternedScheduleLabel,
+        InternedSystemSet, IntoSystemConfigs, IntoSystemSet, IntoSystemSetConfigs, Schedule,
+        ScheduleLabel, Schedules, SystemConfigs, SystemSet, SystemSetConfigs,
+    },
+    system::ResMut,
+    world::{Mut, World},
+};
+use bevy_state::{condition::in_state, state::States};
+use bevy_utils::tracing::{debug, warn};
+use rand::distributions::{Alphanumeric, DistString};
+
+use crate::{
+    count_systems, panics::catch_node_panics, rng::get_rng, ScopedStartupTreeLayer, StartupTasks,
+    StartupTimings, StartupTreeFlush, StartupTreeHandle, StartupTreeLayer, StartupTreeManifest,
+    StartupTreeNode, StartupTreeNodeLabel, StartupTreeSystems,
+};
+
+const NAMESPACE_LEN: usize = 6;
+
+type LayerConfigFn = Box<dyn FnOnce(StartupTreeLayer) -> SystemSetConfigs>;
+type LayerSetFn = Box<dyn Fn(usize) -> InternedSystemSet>;
+type FlushFn = Box<dyn Fn() -> SystemConfigs>;
+
+/// A builder for inserting a startup tree into an [`App`] with additional configuration.
+///
+/// [`AddStartupTree::add_startup_tree`](crate::AddStartupTree::add_startup_tree) is equivalent to
+/// creating a builder with [`StartupTreeBuilder::new`] and immediately calling
+/// [`insert`](StartupTreeBuilder::insert). A configured builder can be inserted with
+/// [`add_startup_tree_with`](crate::AddStartupTree::add_startup_tree_with) to keep chaining calls
+/// on the app.
+///
+/// [`App`]: https://docs.rs/bevy/~0.14/bevy/app/struct.App.html
+pub struct StartupTreeBuilder {
+    layers: Vec<Vec<StartupTreeNode>>,
+    layer_configs: Vec<(usize, LayerConfigFn)>,
+    schedule: InternedScheduleLabel,
+    run_once: bool,
+    ambiguous_with: Vec<InternedSystemSet>,
+    ambiguous_layers: bool,
+    after: Vec<InternedSystemSet>,
+    timed: bool,
+    manifest: bool,
+    flush_at_end: bool,
+    no_terminal_flush: bool,
+    flush_every: usize,
+    flush_system: Option<FlushFn>,
+    report_panics: bool,
+    in_systems_set: bool,
+    layer_sets: Option<LayerSetFn>,
+    scope: Option<&'static str>,
+}
+
+impl StartupTreeBuilder {
+    /// Create a builder for the given tree.
+    ///
+    /// The input has the same shape as the input of
+    /// [`add_startup_tree`](crate::AddStartupTree::add_startup_tree).
+    pub fn new<I2, I>(startup_tree: I2) -> Self
+    where
+        I2: IntoIterator<Item = I>,
+        I: IntoIterator<Item: Into<StartupTreeNode>>,
+    {
+        Self {
+            layers: startup_tree
+                .into_iter()
+                .map(|level| level.into_iter().map(Into::into).collect())
+                .collect(),
+            layer_configs: Vec::new(),
+            schedule: Startup.intern(),
+            run_once: false,
+            ambiguous_with: Vec::new(),
+            ambiguous_layers: false,
+            after: Vec::new(),
+            timed: false,
+            manifest: false,
+            flush_at_end: false,
+            no_terminal_flush: false,
+            flush_every: 1,
+            flush_system: None,
+            report_panics: false,
+            in_systems_set: false,
+            layer_sets: None,
+            scope: None,
+        }
+    }
+
+    /// Insert the tree into `schedule` instead of the [`Startup` schedule][`Startup`].
+    ///
+    /// [`Startup`]: https://docs.rs/bevy/~0.14/bevy/app/struct.Startup.html
+    pub fn in_schedule(mut self, schedule: impl ScheduleLabel) -> Self {
+        self.schedule = schedule.intern();
+        self
+    }
+
+    /// Run the layers of the tree in reverse, the deepest layer first and the roots last.
+    ///
+    /// This reuses the structure of a setup tree for teardown, e.g. to despawn children before
+    /// their parents. The layers are still flushed in between. Depths given to other methods of
+    /// the builder, like [`layer_run_if`](Self::layer_run_if), and to the [`StartupTreeHandle`]
+    /// count in the order that the layers run.
+    pub fn reversed(mut self) -> Self {
+        self.layers.reverse();
+        self
+    }
+
+    /// Only run the tree the first time its schedule runs.
+    ///
+    /// This is useful when the tree is inserted into a schedule that runs repeatedly, like
+    /// `FixedUpdate`, but the tree should only be run once, e.g. for deterministic setup. Every
+    /// layer is skipped on all subsequent runs of the schedule.
+    pub fn run_once(mut self) -> Self {
+        self.run_once = true;
+        self
+    }
+
+    /// Only run the systems of the layer at `depth` if `condition` returns `true`.
+    ///
+    /// The condition is applied to the layer's system set, so it is evaluated once for the whole
+    /// layer. Layers deeper than a skipped layer still run after it, the ordering constraint on a
+    /// skipped layer is satisfied once its (zero) systems have run.
+    pub fn layer_run_if<M>(self, depth: usize, condition: impl Condition<M> + 'static) -> Self {
+        self.configure_layer(depth, move |layer_set| layer_set.run_if(condition))
+    }
+
+    /// Configure the system set of the layer at `depth` with the configs that `configure` returns
+    /// for it, e.g. `|layer| layer.after(other_system)`.
+    ///
+    /// The configs are added to the schedule once the tree is inserted, in addition to the
+    /// configuration of the builder, so the layer still runs after the previous one.
+    pub fn configure_layer(
+        mut self,
+        depth: usize,
+        configure: impl FnOnce(StartupTreeLayer) -> SystemSetConfigs + 'static,
+    ) -> Self {
+        self.layer_configs.push((depth, Box::new(configure)));
+        self
+    }
+
+    /// Configure the system set of every layer with the configs that `configure` returns for the
+    /// layer's depth and set.
+    ///
+    /// This is like calling [`configure_layer`](Self::configure_layer) for each layer, e.g. to
+    /// apply a run condition, ordering, or set to some layers based on their depth.
+    ///
+    /// ```rust no_run
+    /// # use bevy::prelude::*;
+    /// # use bevy_startup_tree::{startup_tree, StartupTreeBuilder};
+    /// # fn sys_1() {}
+    /// # fn sys_2() {}
+    /// # fn sys_3() {}
+    /// # fn headless() -> bool { true }
+    /// StartupTreeBuilder::new(startup_tree! { sys_1 => sys_2 => sys_3 })
+    ///     .configure_layers(|depth, layer| match depth {
+    ///         0 => layer.into_configs(),
+    ///         _ => layer.run_if(not(headless)),
+    ///     })
+    ///     .insert(&mut App::new());
+    /// ```
+    pub fn configure_layers(
+        self,
+        configure: impl Fn(usize, StartupTreeLayer) -> SystemSetConfigs + 'static,
+    ) -> Self {
+        let configure = Rc::new(configure);
+        (0..self.layers.len()).fold(self, |builder, depth| {
+            let configure = Rc::clone(&configure);
+            builder.configure_layer(depth, move |layer_set| configure(depth, layer_set))
+        })
+    }
+
+    /// Only run the systems of the tree while the app is in `state`.
+    ///
+    /// This applies the [`in_state`] condition to every layer with
+    /// [`layer_run_if`](Self::layer_run_if). The condition is checked each time a layer's
+    /// schedule runs, so a tree in `Startup` only runs if the app starts in `state`, it doesn't run
+    /// later when the state is entered; add the tree to [`OnEnter`] for that. In the `OnEnter`
+    /// schedule of `state` the condition is always true, and in its `OnExit` schedule it's always
+    /// false, since the state was already changed to the next one when the exit schedule runs.
+    ///
+    /// [`in_state`]: https://docs.rs/bevy/~0.14/bevy/state/condition/fn.in_state.html
+    /// [`OnEnter`]: https://docs.rs/bevy/~0.14/bevy/state/state/struct.OnEnter.html
+    pub fn in_state<S: States>(self, state: S) -> Self {
+        (0..self.layers.len())
+            .fold(self, |builder, depth| builder.layer_run_if(depth, in_state(state.clone())))
+    }
+
+    /// Mark every layer and flush of the tree as ambiguous with `set`.
+    ///
+    /// This silences ambiguity reports between the systems of the tree and those of `set`, e.g.
+    /// when both write to a resource but the order doesn't matter. Ambiguities with other systems
+    /// are still reported.
+    pub fn ambiguous_with<M>(mut self, set: impl IntoSystemSet<M>) -> Self {
+        self.ambiguous_with.push(set.into_system_set().intern());
+        self
+    }
+
+    /// Mark the systems of each layer as ambiguous with each other.
+    ///
+    /// There is no guarantee about the order of the systems at the same depth, so conflicts
+    /// between them are usually expected. This silences the ambiguity reports for those conflicts
+    /// while still reporting conflicts with systems outside of the layer.
+    ///
+    /// The trade-off is that a real ambiguity within a layer, e.g. two systems that overwrite the
+    /// same resource and whose order does matter, goes unreported. Note that this doesn't make
+    /// conflicting systems run in parallel, the executor never runs systems with conflicting
+    /// access at the same time.
+    pub fn ambiguous_layers(mut self) -> Self {
+        self.ambiguous_layers = true;
+        self
+    }
+
+    /// Only start running the tree once the systems in `set` have run.
+    ///
+    /// The systems of [`PreStartup`] always run before the tree, but the tree's first layer may run
+    /// at the same time as other systems in its own schedule, e.g. those that another plugin adds
+    /// to `Startup` directly. Ordering the tree after the set of those systems removes the race.
+    ///
+    /// [`PreStartup`]: https://docs.rs/bevy/~0.14/bevy/app/struct.PreStartup.html
+    pub fn after<M>(mut self, set: impl IntoSystemSet<M>) -> Self {
+        self.after.push(set.into_system_set().intern());
+        self
+    }
+
+    /// Put the systems of each layer into the set returned by `sets` for the layer's depth.
+    ///
+    /// This makes the layers of the tree available under names of the app's own choosing, e.g.
+    /// the variants of a `#[derive(SystemSet)]` enum, so that other systems can be ordered
+    /// relative to them without going through the [`StartupTreeHandle`]. The layers are still
+    /// ordered by the tree's own [`StartupTreeLayer`] sets, which are nested in the given sets.
+    pub fn layer_sets<S: SystemSet>(mut self, sets: impl Fn(usize) -> S + 'static) -> Self {
+        self.layer_sets = Some(Box::new(move |depth| sets(depth).intern()));
+        self
+    }
+
+    /// Tag the layers of the tree with a `scope`, e.g. the name of the world the tree belongs to.
+    ///
+    /// Each layer is nested in a [`ScopedStartupTreeLayer`] set with the scope, and the scope is
+    /// part of the labels of the tree's sets, so that identical trees in different worlds or
+    /// sub-apps aren't confused in tooling and graph dumps. The layers are still ordered by the
+    /// tree's own [`StartupTreeLayer`] sets.
+    pub fn scope(mut self, scope: &'static str) -> Self {
+        self.scope = Some(scope);
+        self
+    }
+
+    /// Put the layers and flushes of the tree into the [`StartupTreeSystems`] set.
+    ///
+    /// Every tree inserted this way shares the set, so other systems can be ordered relative to
+    /// all of them at once and schedule graph tools group the tree's systems. The layers are also
+    /// still in their own [`StartupTreeLayer`] sets.
+    pub fn in_systems_set(mut self) -> Self {
+        self.in_systems_set = true;
+        self
+    }
+
+    /// Record how long each layer of the tree takes to run in the [`StartupTimings`] resource.
+    ///
+    /// A system is added before each layer that records when the layer starts and one after it
+    /// that records the elapsed time.
+    pub fn timed(mut self) -> Self {
+        self.timed = true;
+        self
+    }
+
+    /// Record the systems of the tree and their layers in the [`StartupTreeManifest`] resource.
+    ///
+    /// The systems are recorded when the tree is inserted. Building the tree with
+    /// [`build_schedule`](Self::build_schedule) doesn't record it.
+    pub fn manifest(mut self) -> Self {
+        self.manifest = true;
+        self
+    }
+
+    /// Log the label of the layer of a node when one of its systems panics.
+    ///
+    /// The systems of each node are wrapped in an exclusive system that runs them in a schedule of
+    /// their own, catches a panic, logs the layer, and continues to unwind. This is a debugging
+    /// aid with a cost: the nodes of the tree don't run in parallel with each other or with other
+    /// systems anymore, the commands of each node are applied as soon as it finishes, and
+    /// [`StartupTreeManifest`] lists the wrappers instead of the systems of the nodes.
+    ///
+    /// The wrapper is put in the sets that the systems of the node are in, e.g. with `in Set`, and
+    /// ordered against the sets and systems that they are ordered against. Other things about the
+    /// systems only apply within the wrapper: their run conditions are checked when it runs, and
+    /// systems outside of the tree that are ordered against a system function of the node, like
+    /// `.after(spawn_player)`, or declared ambiguous with it are not ordered against the wrapper.
+    pub fn report_panics(mut self) -> Self {
+        self.report_panics = true;
+        self
+    }
+
+    /// Add a flush after everything else in the tree, including the flushes of `flush_after`
+    /// nodes in the last layer.
+    ///
+    /// The flush is the end of the tree's [`StartupTreeHandle`], so systems added with
+    /// [`add_finalizer`](StartupTreeHandle::add_finalizer) see all commands of the tree applied,
+    /// even if the last layer is `pure` or only has `flush_after` nodes. Systems in later
+    /// schedules like `PostStartup` don't need this, the remaining commands of a schedule are
+    /// always applied when it finishes.
+    pub fn flush_at_end(mut self) -> Self {
+        self.flush_at_end = true;
+        self
+    }
+
+    /// Don't add the flush that applies the commands of the last layer of the tree.
+    ///
+    /// This is for apps that place their own sync points after the tree. Without the flush,
+    /// systems that run after the tree in the same schedule, including finalizers added with
+    /// [`add_finalizer`](StartupTreeHandle::add_finalizer), may not see the commands of the last
+    /// layer applied. For a tree in `Startup` they are still applied before `PostStartup`, unless
+    /// the schedule was configured not to apply the remaining commands when it finishes. The
+    /// flushes of `flush_after` nodes and the one added by [`flush_at_end`](Self::flush_at_end)
+    /// are kept.
+    pub fn no_terminal_flush(mut self) -> Self {
+        self.no_terminal_flush = true;
+        self
+    }
+
+    /// Only flush the commands of the tree after every `n`th layer, instead of after every layer.
+    ///
+    /// This reduces the number of sync points of trees whose layers mostly read, since each flush
+    /// is a point where no other systems of the schedule can run. The commands of a layer without
+    /// a flush are applied by the next flush, so a layer only sees the commands of the layers
+    /// before the last flush. For example, with `n = 2` the third layer sees the commands of the
+    /// first two, but the second layer doesn't see those of the first, e.g. an entity spawned by
+    /// the first layer doesn't exist yet for a query in the second.
+    ///
+    /// The last layer is still flushed, unless [`no_terminal_flush`](Self::no_terminal_flush) is
+    /// used, and the flushes of `flush_after` nodes are kept. Note that Bevy also inserts sync
+    /// points between systems with commands and the systems that are ordered after them, unless
+    /// the `auto_insert_apply_deferred` build setting of the schedule is disabled.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is zero.
+    pub fn flush_every(mut self, n: usize) -> Self {
+        assert!(n > 0, "startup tree can't be flushed every 0 layers");
+        self.flush_every = n;
+        self
+    }
+
+    /// Use `flush_system` for the flushes of the tree instead of [`apply_deferred`].
+    ///
+    /// This is for apps with their own deferred infrastructure, e.g. a custom command queue. A
+    /// copy of `flush_system` is added in place of every flush of the tree, so it runs between the
+    /// layers and after `flush_after` nodes, and follows the same rules as the flushes it
+    /// replaces, e.g. [`flush_every`](Self::flush_every) and layers with only `pure` nodes. Note
+    /// that [`Commands`] are only applied by `apply_deferred`, so with another flush system they
+    /// are only applied by the sync points that Bevy inserts or when the schedule finishes.
+    ///
+    /// [`apply_deferred`]: https://docs.rs/bevy/~0.14/bevy/ecs/schedule/fn.apply_deferred.html
+    /// [`Commands`]: https://docs.rs/bevy/~0.14/bevy/ecs/system/struct.Commands.html
+    pub fn flush_with<M>(
+        mut self,
+        flush_system: impl IntoSystemConfigs<M> + Clone + 'static,
+    ) -> Self {
+        self.flush_system = Some(Box::new(move || flush_system.clone().into_configs()));
+        self
+    }
+
+    /// Insert the tree into the `app`.
+    ///
+    /// The tree is inserted into the [`Startup` schedule][`Startup`] unless another schedule was
+    /// given with [`in_schedule`](Self::in_schedule).
+    ///
+    /// Returns a [`StartupTreeHandle`] that can be used to extend the tree after it was inserted.
+    ///
+    /// A warning is logged if the schedule is one of the app's startup schedules and it already
+    /// ran, since those only run once and the tree would never run.
+    ///
+    /// [`Startup`]: https://docs.rs/bevy/~0.14/bevy/app/struct.Startup.html
+    pub fn insert(self, app: &mut App) -> StartupTreeHandle {
+        app.init_resource::<StartupTasks>();
+        if self.timed {
+            app.init_resource::<StartupTimings>();
+        }
+        if self.manifest {
+            app.init_resource::<StartupTreeManifest>();
+        }
+        #[cfg(feature = "test-util")]
+        app.init_resource::<crate::StartupTreeInsertionLog>();
+
+        // `edit_schedule` takes an `FnMut`, so the builder can't be moved into the closure
+        let label = self.schedule;
+        if startup_schedule_ran(app.world(), label) {
+            warn!(
+                "adding startup tree to {label:?} after it already ran, the tree won't run unless \
+                 the schedule is run again"
+            );
+        }
+        let manifest = self.manifest;
+        let mut builder = Some(self);
+        let mut handle = None;
+        app.edit_schedule(label, |schedule| {
+            handle = builder.take().map(|builder| builder.insert_into_schedule(schedule));
+        });
+        let handle = handle.unwrap();
+
+        if manifest {
+            app.world_mut().resource_scope(|world, mut manifest: Mut<StartupTreeManifest>| {
+                let schedules = world.resource::<Schedules>();
+                manifest.record(schedules.get(label).unwrap(), handle.layers());
+            });
+        }
+        #[cfg(feature = "test-util")]
+        app.world_mut().resource_scope(|world, mut log: Mut<crate::StartupTreeInsertionLog>| {
+            let schedules = world.resource::<Schedules>();
+            log.record(schedules.get(label).unwrap(), handle.layers());
+        });
+
+        handle
+    }
+
+    /// Build the tree into a new [`Schedule`] that isn't part of any [`App`].
+    ///
+    /// The schedule has the label given with [`in_schedule`](Self::in_schedule), or `Startup`. It
+    /// can be stored and run on a world manually, e.g. with [`Schedule::run`]. A tree built with
+    /// [`timed`](Self::timed) requires the [`StartupTimings`] resource to be in the world.
+    ///
+    /// [`App`]: https://docs.rs/bevy/~0.14/bevy/app/struct.App.html
+    /// [`Schedule`]: https://docs.rs/bevy/~0.14/bevy/ecs/schedule/struct.Schedule.html
+    /// [`Schedule::run`]: https://docs.rs/bevy/~0.14/bevy/ecs/schedule/struct.Schedule.html#method.run
+    pub fn build_schedule(self) -> Schedule {
+        let mut schedule = Schedule::new(self.schedule);
+        self.insert_into_schedule(&mut schedule);
+        schedule
+    }
+
+    /// Insert the tree into an existing [`Schedule`] that isn't part of any [`App`].
+    ///
+    /// This is like [`build_schedule`](Self::build_schedule), but the schedule can be configured
+    /// before the tree is inserted, and more than one tree can be inserted into it. The schedule
+    /// given with [`in_schedule`](Self::in_schedule) is ignored, the handle refers to the label of
+    /// `schedule`.
+    ///
+    /// [`App`]: https://docs.rs/bevy/~0.14/bevy/app/struct.App.html
+    /// [`Schedule`]: https://docs.rs/bevy/~0.14/bevy/ecs/schedule/struct.Schedule.html
+    pub fn insert_into_schedule(self, schedule: &mut Schedule) -> StartupTreeHandle {
+        debug!(
+            "adding startup tree with {} systems in {} layers to {:?}",
+            count_systems(&self.layers),
+            self.layers.len(),
+            schedule.label(),
+        );
+
+        let labels = TreeLabels::new(self.scope);
+        let make_label = |suffix: fmt::Arguments| labels.make(suffix);
+
+        let mut layer_sets = Vec::with_capacity(self.layers.len());
+
+        let add_flush =
+            |schedule: &mut Schedule, flush_set: StartupTreeFlush, config: SystemSetConfigs| {
+                let config = if self.run_once { config.run_if(run_once()) } else { config };
+                schedule.configure_sets(config);
+                if self.in_systems_set {
+                    schedule.configure_sets(flush_set.in_set(StartupTreeSystems));
+                }
+                for &set in &self.ambiguous_with {
+                    schedule.configure_sets(flush_set.ambiguous_with(set));
+                }
+                let flush_system = match &self.flush_system {
+                    Some(flush_system) => flush_system(),
+                    None => apply_deferred.into_configs(),
+                };
+                schedule.add_systems(flush_system.in_set(flush_set));
+            };
+
+        let mut layer_names: Vec<&'static str> = Vec::new();
+        for level in &self.layers {
+            let mut names = level.iter().filter_map(StartupTreeNode::layer_name);
+            let Some(name) = names.next() else {
+                continue;
+            };
+            if let Some(conflicting) = names.find(|&other| other != name) {
+                panic!(
+                    "layer is already named \"{name}\", it can't also be named \"{conflicting}\""
+                );
+            }
+            let duplicate = layer_names.contains(&name);
+            assert!(!duplicate, "layer name \"{name}\" is already used by another layer");
+            layer_names.push(name);
+        }
+
+        let mut node_labels: HashMap<&'static str, StartupTreeNodeLabel> = HashMap::new();
+        for label in self.layers.iter().flatten().filter_map(StartupTreeNode::label) {
+            let label_set = StartupTreeNodeLabel::new(make_label(format_args!("_node_{label}")));
+            let duplicate = node_labels.insert(label, label_set).is_some();
+            assert!(!duplicate, "node label `{label}` is already used by another node");
+        }
+        let node_label = |label: &'static str| -> StartupTreeNodeLabel {
+            match node_labels.get(label) {
+                Some(&label_set) => label_set,
+                None => panic!("startup tree has no node labeled `{label}`"),
+            }
+        };
+
+        let depth = self.layers.len();
+        let mut last_set: Option<InternedSystemSet> = None;
+        let mut node_flush_sets: Vec<StartupTreeFlush> = Vec::new();
+
+        for (i, level) in self.layers.into_iter().enumerate() {
+            let layer_label = match level.iter().find_map(StartupTreeNode::layer_name) {
+                Some(name) => make_label(format_args!("_{name}")),
+                None => make_label(format_args!("_layer_{i}")),
+            };
+            let layer_set = StartupTreeLayer::new(layer_label, i);
+
+            let layer_config = if let Some(last_set) = last_set {
+                layer_set.after(last_set)
+            } else {
+                self.after.iter().fold(layer_set.into_configs(), |config, &set| config.after(set))
+            };
+            let layer_config =
+                if self.run_once { layer_config.run_if(run_once()) } else { layer_config };
+            schedule.configure_sets(layer_config);
+            for &set in &self.ambiguous_with {
+                schedule.configure_sets(layer_set.ambiguous_with(set));
+            }
+            if self.ambiguous_layers {
+                schedule.configure_sets(layer_set.ambiguous_with(layer_set));
+            }
+            if let Some(sets) = &self.layer_sets {
+                schedule.configure_sets(layer_set.in_set(sets(i)));
+            }
+            if let Some(scope) = self.scope {
+                schedule.configure_sets(
+                    layer_set.in_set(ScopedStartupTreeLayer::new(scope, layer_set)),
+                );
+            }
+            if self.in_systems_set {
+                schedule.configure_sets(layer_set.in_set(StartupTreeSystems));
+            }
+
+            if self.timed {
+                let start = (move |mut timings: ResMut<StartupTimings>| timings.start(layer_set))
+                    .before(layer_set);
+                let start =
+                    if let Some(last_set) = last_set { start.after(last_set) } else { start };
+                let finish = (move |mut timings: ResMut<StartupTimings>| timings.finish(layer_set))
+                    .after(layer_set);
+                let timers = (start, finish).into_configs();
+                let timers = if self.run_once { timers.run_if(run_once()) } else { timers };
+                schedule.add_systems(timers);
+            }
+
+            for flush_set in node_flush_sets.drain(..) {
+                schedule.configure_sets(flush_set.before(layer_set));
+            }
+
+            let is_terminal = i + 1 == depth;
+            let is_flushed =
+                if is_terminal { !self.no_terminal_flush } else { (i + 1) % self.flush_every == 0 };
+            let needs_flush =
+                is_flushed && level.iter().any(|node| !node.is_pure() && !node.is_flush_after());
+
+            for (j, node) in level.into_iter().enumerate() {
+                let flush_after = node.is_flush_after();
+                let label = node.label().map(node_label);
+                let (after, before) = node.label_orderings();
+                let after = after.iter().map(|&label| node_label(label)).collect::<Vec<_>>();
+                let before = before.iter().map(|&label| node_label(label)).collect::<Vec<_>>();
+                let Some(systems) = node.into_systems() else {
+                    continue;
+                };
+                let systems = if self.report_panics {
+                    catch_node_panics(systems, layer_set, schedule.get_executor_kind())
+                } else {
+                    systems
+                };
+                let systems = systems.in_set(layer_set);
+                let systems = match label {
+                    Some(label_set) => systems.in_set(label_set),
+                    None => systems,
+                };
+                let systems = after.into_iter().fold(systems, |systems, set| systems.after(set));
+                let systems = before.into_iter().fold(systems, |systems, set| systems.before(set));
+                if flush_after {
+                    let flush_set =
+                        StartupTreeFlush::new(make_label(format_args!("_flush_{i}_{j}")), i);
+                    schedule.add_systems(systems.before(flush_set));
+                    add_flush(schedule, flush_set, flush_set.into_configs());
+                    node_flush_sets.push(flush_set);
+                } else {
+                    schedule.add_systems(systems);
+                }
+            }
+
+            layer_sets.push(layer_set);
+            last_set = Some(layer_set.intern());
+
+            if needs_flush {
+                let flush_set = StartupTreeFlush::new(make_label(format_args!("_flush_{i}")), i);
+                add_flush(schedule, flush_set, flush_set.after(layer_set));
+                // The next layer runs after the flush of this one, so it's also after these
+                for node_flush_set in node_flush_sets.drain(..) {
+                    schedule.configure_sets(node_flush_set.before(flush_set));
+                }
+                last_set = Some(flush_set.intern());
+            }
+        }
+
+        if self.flush_at_end {
+            let flush_set =
+                StartupTreeFlush::new(make_label(format_args!("_flush_end")), layer_sets.len());
+            let config = match last_set {
+                Some(last_set) => flush_set.after(last_set),
+                None => flush_set.into_configs(),
+            };
+            add_flush(schedule, flush_set, config);
+            for node_flush_set in node_flush_sets.drain(..) {
+                schedule.configure_sets(node_flush_set.before(flush_set));
+            }
+            last_set = Some(flush_set.intern());
+        }
+
+        for (depth, configure) in self.layer_configs {
+            if let Some(&layer_set) = layer_sets.get(depth) {
+                schedule.configure_sets(configure(layer_set));
+            }
+        }
+
+        // The flushes of `flush_after` nodes in the last layer can't be ordered before the last
+        // set if it's the layer itself, so they are also an end of the tree
+        let ends = last_set.into_iter().chain(node_flush_sets.into_iter().map(|set| set.intern()));
+        StartupTreeHandle::new(schedule.label(), layer_sets, ends.collect())
+    }
+}
+
+/// The labels of the sets of a tree, which all start with the tree's random namespace.
+///
+/// The labels are leaked to get the `&'static str`s that the sets need. Every label is formatted
+/// into the same buffer after the namespace, so that each one only allocates its leaked copy.
+struct TreeLabels {
+    buffer: RefCell<String>,
+    namespace_len: usize,
+}
+
+impl TreeLabels {
+    fn new(scope: Option<&str>) -> Self {
+        let mut buffer = String::with_capacity(64);
+        buffer.push_str("__startup_tree_");
+        if let Some(scope) = scope {
+            buffer.push_str(scope);
+            buffer.push('_');
+        }
+        Alphanumeric.append_string(&mut get_rng(), &mut buffer, NAMESPACE_LEN);
+        let namespace_len = buffer.len();
+        Self { buffer: RefCell::new(buffer), namespace_len }
+    }
+
+    /// Leak a label made of the namespace followed by `suffix`.
+    fn make(&self, suffix: fmt::Arguments) -> &'static str {
+        let mut buffer = self.buffer.borrow_mut();
+        buffer.truncate(self.namespace_len);
+        buffer.write_fmt(suffix).unwrap();
+        Box::leak(Box::from(buffer.as_str()))
+    }
+}
+
+/// Whether `label` is one of the startup schedules of the app, like `Startup`, and it already ran.
+///
+/// A schedule is initialized when it first runs, and it stays initialized when systems are added to
+/// it afterwards.
+fn startup_schedule_ran(world: &World, label: InternedScheduleLabel) -> bool {
+    let is_startup = match world.get_resource::<MainScheduleOrder>() {
+        Some(order) => order.startup_labels.contains(&label),
+        None => label == Startup.intern(),
+    };
+    is_startup
+        && world
+            .get_resource::<Schedules>()
+            .and_then(|schedules| schedules.get(label))
+            .is_some_and(|schedule| schedule.systems().is_ok())
+}
+
+/// Build a tree into a new [`Schedule`] that isn't part of any [`App`].
+///
+/// This is equivalent to creating a [`StartupTreeBuilder`] with [`StartupTreeBuilder::new`] and
+/// immediately calling [`build_schedule`](StartupTreeBuilder::build_schedule). The schedule can be
+/// stored and run on a world manually.
+///
+/// ```rust
+/// # use bevy::prelude::*;
+/// # use bevy_startup_tree::{build_startup_schedule, startup_tree};
+/// # fn sys_1() {}
+/// # fn sys_2() {}
+/// let mut schedule = build_startup_schedule(startup_tree! { sys_1 => sys_2 });
+/// let mut world = World::new();
+/// schedule.run(&mut world);
+/// ```
+///
+/// [`App`]: https://docs.rs/bevy/~0.14/bevy/app/struct.App.html
+/// [`Schedule`]: https://docs.rs/bevy/~0.14/bevy/ecs/schedule/struct.Schedule.html
+pub fn build_startup_schedule<I2, I>(startup_tree: I2) -> Schedule
+where
+    I2: IntoIterator<Item = I>,
+    I: IntoIterator<Item: Into<StartupTreeNode>>,
+{
+    StartupTreeBuilder::new(startup_tree).build_schedule()
+}
+
+/// Insert a tree into an existing [`Schedule`] that isn't part of any [`App`].
+///
+/// This is equivalent to creating a [`StartupTreeBuilder`] with [`StartupTreeBuilder::new`] and
+/// immediately calling [`insert_into_schedule`](StartupTreeBuilder::insert_into_schedule). Unlike
+/// [`build_startup_schedule`], the schedule can be configured before the tree is inserted.
+///
+/// ```rust
+/// # use bevy::{ecs::schedule::ExecutorKind, prelude::*};
+/// # use bevy_startup_tree::{insert_startup_tree, startup_tree};
+/// # fn sys_1() {}
+/// # fn sys_2() {}
+/// let mut schedule = Schedule::default();
+/// schedule.set_executor_kind(ExecutorKind::SingleThreaded);
+/// insert_startup_tree(&mut schedule, startup_tree! { sys_1 => sys_2 });
+/// schedule.run(&mut World::new());
+/// ```
+///
+/// [`App`]: https://docs.rs/bevy/~0.14/bevy/app/struct.App.html
+/// [`Schedule`]: https://docs.rs/bevy/~0.14/bevy/ecs/schedule/struct.Schedule.html
+pub fn insert_startup_tree<I2, I>(schedule: &mut Schedule, startup_tree: I2) -> StartupTreeHandle
+where
+    I2: IntoIterator<Item = I>,
+    I: IntoIterator<Item: Into<StartupTreeNode>>,
+{
+    StartupTreeBuilder::new(startup_tree).insert_into_schedule(schedule)
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        ecs::schedule::{LogLevel, ScheduleBuildSettings, ScheduleLabel},
+        prelude::*,
+    };
+
+    use super::{startup_schedule_ran, TreeLabels};
+    use crate::{
+        build_startup_schedule,
+        fixtures::{flush_runs_before, ran_app, sys_0, sys_1, sys_2, Ran},
+        insert_startup_tree,
+        rng::{reseed_rng, reset_rng},
+        startup_tree, ScopedStartupTreeLayer, StartupTimings, StartupTreeBuilder, StartupTreeNode,
+        StartupTreeSystems,
+    };
+
+    #[derive(Resource)]
+    struct SkipLayer;
+
+    fn run_tree(skip: bool) -> Vec<u32> {
+        reseed_rng();
+
+        let mut app = ran_app();
+        if skip {
+            app.insert_resource(SkipLayer);
+        }
+
+        StartupTreeBuilder::new(startup_tree! { sys_0 => sys_1 => sys_2 })
+            .layer_run_if(1, not(resource_exists::<SkipLayer>))
+            .insert(&mut app);
+
+        app.update();
+
+        app.world_mut().remove_resource::<Ran>().unwrap().0
+    }
+
+    #[test]
+    fn runs_conditional_layer_when_condition_is_true() {
+        assert_eq!(run_tree(false), [0, 1, 2]);
+    }
+
+    #[test]
+    fn runs_later_layers_when_middle_layer_is_skipped() {
+        assert_eq!(run_tree(true), [0, 2]);
+    }
+
+    #[test]
+    fn configure_layers_applies_config_by_depth() {
+        reseed_rng();
+
+        let mut app = ran_app();
+        app.insert_resource(SkipLayer);
+
+        StartupTreeBuilder::new(startup_tree! { sys_0 => sys_1 => sys_2 => sys_1 => sys_0 })
+            .configure_layers(|depth, layer| match depth % 2 {
+                1 => layer.run_if(not(resource_exists::<SkipLayer>)),
+                _ => layer.into_configs(),
+            })
+            .insert(&mut app);
+
+        app.update();
+
+        assert_eq!(app.world().resource::<Ran>().0, [0, 2, 0]);
+    }
+
+    #[derive(SystemSet, Clone, Debug, PartialEq, Eq, Hash)]
+    struct OtherSet;
+
+    fn other(mut ran: ResMut<Ran>) {
+        ran.0.push(100);
+    }
+
+    fn startup_ambiguities(ambiguous_with_other: bool) -> Result<(), String> {
+        reseed_rng();
+
+        let mut app = App::new();
+        app.init_resource::<Ran>();
+        app.edit_schedule(Startup, |schedule| {
+            schedule.set_build_settings(ScheduleBuildSettings {
+                ambiguity_detection: LogLevel::Error,
+                ..default()
+            });
+        });
+        app.add_systems(Startup, other.in_set(OtherSet));
+
+        let builder = StartupTreeBuilder::new(startup_tree! { sys_0 => sys_1 });
+        let builder = if ambiguous_with_other { builder.ambiguous_with(OtherSet) } else { builder };
+        builder.insert(&mut app);
+
+        let mut schedule = app.world_mut().resource_mut::<Schedules>().remove(Startup).unwrap();
+        schedule.initialize(app.world_mut()).map_err(|err| err.to_string())
+    }
+
+    #[test]
+    fn reports_ambiguities_with_other_set() {
+        assert!(startup_ambiguities(false).is_err());
+    }
+
+    #[test]
+    fn ambiguous_with_silences_ambiguities_with_other_set() {
+        assert_eq!(startup_ambiguities(true), Ok(()));
+    }
+
+    #[derive(Component)]
+    struct Spawned;
+
+    fn spawn(mut commands: Commands) {
+        commands.spawn(Spawned);
+    }
+
+    fn count_spawned(q_spawned: Query<(), With<Spawned>>, mut ran: ResMut<Ran>) {
+        ran.0.push(q_spawned.iter().count() as u32);
+    }
+
+    #[test]
+    fn flush_at_end_applies_last_layer_before_finalizers() {
+        reseed_rng();
+
+        let mut app = ran_app();
+
+        let handle =
+            StartupTreeBuilder::new(startup_tree! { sys_0 => { flush_after spawn, spawn } })
+                .flush_at_end()
+                .insert(&mut app);
+        handle.add_finalizer(&mut app, count_spawned);
+        app.add_systems(PostStartup, count_spawned);
+
+        app.update();
+
+        assert_eq!(app.world().resource::<Ran>().0, [0, 2, 2]);
+    }
+
+    #[test]
+    #[should_panic(expected = "startup tree has no node labeled `missing`")]
+    fn panics_on_unknown_node_label() {
+        reseed_rng();
+
+        let node = StartupTreeNode::new(sys_0.into_configs()).after_label("missing");
+        StartupTreeBuilder::new(vec![vec![node]]).insert(&mut App::new());
+    }
+
+    #[test]
+    #[should_panic(expected = "layer name \"load\" is already used by another layer")]
+    fn panics_on_duplicate_layer_name() {
+        reseed_rng();
+
+        let node = |system: fn(ResMut<Ran>)| {
+            StartupTreeNode::new(system.into_configs()).with_layer_name("load")
+        };
+        StartupTreeBuilder::new(vec![vec![node(sys_0)], vec![node(sys_1)]]).insert(&mut App::new());
+    }
+
+    #[test]
+    #[should_panic(expected = "layer is already named \"load\", it can't also be named \"spawn\"")]
+    fn panics_on_conflicting_layer_names() {
+        reseed_rng();
+
+        let tree = vec![vec![
+            StartupTreeNode::new(sys_0.into_configs()).with_layer_name("load"),
+            StartupTreeNode::new(sys_1.into_configs()).with_layer_name("spawn"),
+        ]];
+        StartupTreeBuilder::new(tree).insert(&mut App::new());
+    }
+
+    fn layer_ambiguities(ambiguous_layers: bool) -> Result<(), String> {
+        reseed_rng();
+
+        let mut app = App::new();
+        app.init_resource::<Ran>();
+        app.edit_schedule(Startup, |schedule| {
+            schedule.set_build_settings(ScheduleBuildSettings {
+                ambiguity_detection: LogLevel::Error,
+                ..default()
+            });
+        });
+
+        let builder = StartupTreeBuilder::new(startup_tree! { sys_0 => { sys_1, sys_2 } });
+        let builder = if ambiguous_layers { builder.ambiguous_layers() } else { builder };
+        builder.insert(&mut app);
+
+        let mut schedule = app.world_mut().resource_mut::<Schedules>().remove(Startup).unwrap();
+        schedule.initialize(app.world_mut()).map_err(|err| err.to_string())
+    }
+
+    #[test]
+    fn reports_ambiguities_within_layer() {
+        assert!(layer_ambiguities(false).is_err());
+    }
+
+    #[test]
+    fn ambiguous_layers_silences_ambiguities_within_layer() {
+        assert_eq!(layer_ambiguities(true), Ok(()));
+    }
+
+    #[test]
+    fn run_once_tree_only_runs_on_first_schedule_run() {
+        reseed_rng();
+
+        let mut app = ran_app();
+
+        StartupTreeBuilder::new(startup_tree! { sys_0 => sys_1 => sys_2 })
+            .in_schedule(FixedUpdate)
+            .run_once()
+            .insert(&mut app);
+
+        app.world_mut().run_schedule(FixedUpdate);
+        app.world_mut().run_schedule(FixedUpdate);
+
+        assert_eq!(app.world().resource::<Ran>().0, [0, 1, 2]);
+    }
+
+    #[derive(Component)]
+    struct Marker;
+
+    fn spawn_marker(mut commands: Commands) {
+        commands.spawn(Marker);
+    }
+
+    fn idle() {}
+
+    fn count_markers(q_markers: Query<&Marker>, mut ran: ResMut<Ran>) {
+        ran.0.push(q_markers.iter().count() as u32);
+    }
+
+    #[test]
+    fn flush_after_node_is_flushed_before_next_layer() {
+        reseed_rng();
+
+        let mut app = App::new();
+        app.init_resource::<Ran>();
+        app.edit_schedule(Startup, |schedule| {
+            schedule.set_build_settings(ScheduleBuildSettings {
+                auto_insert_apply_deferred: false,
+                ..default()
+            });
+        });
+
+        StartupTreeBuilder::new(startup_tree! {
+            flush_after spawn_marker,
+            pure idle => count_markers,
+        })
+        .insert(&mut app);
+
+        app.update();
+
+        assert_eq!(app.world().resource::<Ran>().0, [1]);
+    }
+
+    #[test]
+    fn flush_after_node_in_last_layer_is_flushed_before_end() {
+        for no_terminal_flush in [false, true] {
+            reseed_rng();
+
+            let mut app = App::new();
+            app.init_resource::<Ran>();
+            app.edit_schedule(Startup, |schedule| {
+                schedule.set_build_settings(ScheduleBuildSettings {
+                    auto_insert_apply_deferred: false,
+                    ..default()
+                });
+            });
+
+            let builder = StartupTreeBuilder::new(
+                startup_tree! { idle => { idle, flush_after spawn_marker } },
+            );
+            let builder = if no_terminal_flush { builder.no_terminal_flush() } else { builder };
+            let handle = builder.insert(&mut app);
+            let count_markers =
+                handle.end().iter().fold(count_markers.into_configs(), |sys, &end| sys.after(end));
+            app.add_systems(Startup, count_markers);
+
+            assert!(flush_runs_before(&app, "_flush_1_1", "count_markers"), "{no_terminal_flush}");
+            app.update();
+            assert_eq!(app.world().resource::<Ran>().0, [1], "{no_terminal_flush}");
+        }
+    }
+
+    #[test]
+    fn timed_tree_records_each_layer() {
+        reseed_rng();
+
+        let mut app = ran_app();
+
+        StartupTreeBuilder::new(startup_tree! { sys_0 => sys_1 => sys_2 }).timed().insert(&mut app);
+
+        app.update();
+
+        let timings = app.world().resource::<StartupTimings>();
+        let timed_depths = timings.iter().map(|(layer, _)| layer.index()).collect::<Vec<_>>();
+        assert_eq!(timed_depths, [0, 1, 2]);
+    }
+
+    #[test]
+    fn skipped_layer_does_not_stall_later_layers() {
+        reseed_rng();
+
+        let mut app = ran_app();
+        app.edit_schedule(Startup, |schedule| {
+            schedule.set_build_settings(ScheduleBuildSettings {
+                auto_insert_apply_deferred: false,
+                ..default()
+            });
+        });
+
+        StartupTreeBuilder::new(startup_tree! { spawn_marker => sys_1 => count_markers })
+            .layer_run_if(1, || false)
+            .insert(&mut app);
+
+        app.update();
+
+        assert_eq!(app.world().resource::<Ran>().0, [1]);
+    }
+
+    #[test]
+    fn builds_standalone_schedule() {
+        reseed_rng();
+
+        let mut world = World::new();
+        world.init_resource::<Ran>();
+
+        let mut schedule = build_startup_schedule(startup_tree! { sys_0 => sys_1 => sys_2 });
+        schedule.run(&mut world);
+        schedule.run(&mut world);
+
+        assert_eq!(world.resource::<Ran>().0, [0, 1, 2, 0, 1, 2]);
+    }
+
+    #[test]
+    fn inserts_into_existing_schedule() {
+        reseed_rng();
+
+        let mut world = World::new();
+        world.init_resource::<Ran>();
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(sys_0);
+        let handle = insert_startup_tree(&mut schedule, startup_tree! { sys_1 => sys_2 });
+        schedule.configure_sets(handle.layer(0).unwrap().after(sys_0));
+        schedule.run(&mut world);
+
+        assert_eq!(world.resource::<Ran>().0, [0, 1, 2]);
+    }
+
+    #[test]
+    fn scope_tags_layers() {
+        reset_rng();
+
+        let schedule = StartupTreeBuilder::new(startup_tree! { sys_0 => sys_1 })
+            .scope("client")
+            .build_schedule();
+
+        let mut scoped = schedule
+            .graph()
+            .system_sets()
+            .filter_map(|(_, set, _)| {
+                set.as_dyn_eq().as_any().downcast_ref::<ScopedStartupTreeLayer>().copied()
+            })
+            .map(|set| (set.scope(), set.label(), set.index()))
+            .collect::<Vec<_>>();
+        scoped.sort();
+        assert_eq!(
+            scoped,
+            [
+                ("client", "__startup_tree_client_zujxzB_layer_0", 0),
+                ("client", "__startup_tree_client_zujxzB_layer_1", 1),
+            ]
+        );
+        assert_eq!(
+            flush_labels(&schedule),
+            ["__startup_tree_client_zujxzB_flush_0", "__startup_tree_client_zujxzB_flush_1"]
+        );
+    }
+
+    #[derive(Resource)]
+    struct PreStartupRan;
+
+    fn pre_startup(mut commands: Commands) {
+        commands.insert_resource(PreStartupRan);
+    }
+
+    fn check_pre_startup(pre_startup: Option<Res<PreStartupRan>>, mut ran: ResMut<Ran>) {
+        ran.0.push(if pre_startup.is_some() { 10 } else { 0 });
+    }
+
+    #[test]
+    fn tree_runs_after_pre_startup_and_given_set() {
+        reseed_rng();
+
+        let mut app = ran_app();
+        app.add_systems(PreStartup, pre_startup);
+        app.add_systems(Startup, other.in_set(OtherSet));
+
+        StartupTreeBuilder::new(startup_tree! { check_pre_startup => sys_1 })
+            .after(OtherSet)
+            .insert(&mut app);
+
+        app.update();
+
+        assert_eq!(app.world().resource::<Ran>().0, [100, 10, 1]);
+    }
+
+    #[derive(SystemSet, Clone, Debug, PartialEq, Eq, Hash)]
+    enum MyStartup {
+        Load,
+        Build,
+        Finish,
+    }
+
+    #[test]
+    fn layer_sets_can_order_other_systems() {
+        reseed_rng();
+
+        let mut app = ran_app();
+        app.add_systems(Startup, other.after(MyStartup::Load).before(MyStartup::Build));
+
+        StartupTreeBuilder::new(startup_tree! { sys_0 => sys_1 => sys_2 })
+            .layer_sets(|depth| match depth {
+                0 => MyStartup::Load,
+                1 => MyStartup::Build,
+                _ => MyStartup::Finish,
+            })
+            .insert(&mut app);
+
+        app.update();
+
+        assert_eq!(app.world().resource::<Ran>().0, [0, 100, 1, 2]);
+    }
+
+    #[test]
+    fn systems_set_contains_every_tree_system() {
+        reseed_rng();
+
+        let mut app = ran_app();
+        app.configure_sets(Startup, StartupTreeSystems.run_if(|| false));
+        app.add_systems(Startup, other);
+
+        StartupTreeBuilder::new(startup_tree! { sys_0 => { sys_1, sys_2 } })
+            .in_systems_set()
+            .insert(&mut app);
+        StartupTreeBuilder::new(startup_tree! { flush_after sys_0 => sys_1 })
+            .in_systems_set()
+            .insert(&mut app);
+
+        app.update();
+
+        assert_eq!(app.world().resource::<Ran>().0, [100]);
+    }
+
+    #[test]
+    fn detects_startup_schedule_that_already_ran() {
+        reseed_rng();
+
+        let mut app = App::new();
+        app.init_resource::<Ran>();
+        StartupTreeBuilder::new(startup_tree! { sys_0 }).insert(&mut app);
+        assert!(!startup_schedule_ran(app.world(), Startup.intern()));
+
+        app.update();
+
+        assert!(startup_schedule_ran(app.world(), Startup.intern()));
+        assert!(!startup_schedule_ran(app.world(), Update.intern()));
+
+        // Logs a warning, the tree never runs
+        StartupTreeBuilder::new(startup_tree! { sys_1 }).insert(&mut app);
+        app.update();
+
+        assert_eq!(app.world().resource::<Ran>().0, [0]);
+    }
+
+    fn flush_labels(schedule: &Schedule) -> Vec<String> {
+        let graph = schedule.graph();
+        let mut labels = graph
+            .hierarchy()
+            .graph()
+            .nodes()
+            .filter_map(|id| graph.get_set_at(id))
+            .map(|set| format!("{set:#?}"))
+            .filter(|label| label.contains("_flush_"))
+            .collect::<Vec<_>>();
+        labels.sort();
+        labels
+    }
+
+    #[test]
+    fn labels_share_namespace() {
+        reset_rng();
+
+        let labels = TreeLabels::new(Some("client"));
+        let layer = labels.make(format_args!("_layer_{}", 0));
+        let flush = labels.make(format_args!("_flush_{}_{}", 10, 2));
+
+        assert_eq!(layer, "__startup_tree_client_zujxzB_layer_0");
+        assert_eq!(flush, "__startup_tree_client_zujxzB_flush_10_2");
+    }
+
+    #[test]
+    fn adds_terminal_flush_by_default() {
+        reset_rng();
+
+        let schedule = StartupTreeBuilder::new(startup_tree! { sys_0 => sys_1 }).build_schedule();
+
+        assert_eq!(
+            flush_labels(&schedule),
+            ["__startup_tree_zujxzB_flush_0", "__startup_tree_zujxzB_flush_1"]
+        );
+    }
+
+    #[test]
+    fn no_terminal_flush_omits_last_layer_flush() {
+        reset_rng();
+
+        let schedule = StartupTreeBuilder::new(startup_tree! { sys_0 => sys_1 })
+            .no_terminal_flush()
+            .build_schedule();
+
+        assert_eq!(flush_labels(&schedule), ["__startup_tree_zujxzB_flush_0"]);
+    }
+
+    #[test]
+    fn flush_every_only_flushes_every_nth_layer() {
+        reset_rng();
+
+        let schedule = StartupTreeBuilder::new(startup_tree! { sys_0 => sys_1 => sys_2 => other })
+            .flush_every(2)
+            .build_schedule();
+
+        assert_eq!(
+            flush_labels(&schedule),
+            ["__startup_tree_zujxzB_flush_1", "__startup_tree_zujxzB_flush_3"]
+        );
+    }
+
+    #[test]
+    fn flush_every_applies_skipped_commands_at_next_flush() {
+        #[derive(Resource)]
+        struct Marker;
+
+        fn insert_marker(mut commands: Commands) {
+            commands.insert_resource(Marker);
+        }
+
+        fn count_marker(marker: Option<Res<Marker>>, mut ran: ResMut<Ran>) {
+            ran.0.push(u32::from(marker.is_some()));
+        }
+
+        reseed_rng();
+
+        let mut app = App::new();
+        app.init_resource::<Ran>();
+        app.edit_schedule(Startup, |schedule| {
+            schedule.set_build_settings(ScheduleBuildSettings {
+                auto_insert_apply_deferred: false,
+                ..default()
+            });
+        });
+        StartupTreeBuilder::new(startup_tree! {
+            insert_marker => count_marker => count_marker => count_marker
+        })
+        .flush_every(2)
+        .insert(&mut app);
+
+        app.update();
+
+        assert_eq!(app.world().resource::<Ran>().0, [0, 1, 1]);
+    }
+
+    #[test]
+    fn flush_with_runs_flush_system_between_layers() {
+        fn flush(mut ran: ResMut<Ran>) {
+            ran.0.push(9);
+        }
+
+        reseed_rng();
+
+        let mut app = App::new();
+        app.init_resource::<Ran>();
+        StartupTreeBuilder::new(startup_tree! { sys_0 => sys_1 => sys_2 })
+            .flush_with(flush)
+            .insert(&mut app);
+
+        app.update();
+
+        assert_eq!(app.world().resource::<Ran>().0, [0, 9, 1, 9, 2, 9]);
+    }
+}
@@ -0,0 +1,26 @@
+use std::fmt;
+
+/// An error returned when working with a startup tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TreeError {
+    /// The tree does not have a layer at `depth`, it only has `len` layers.
+    LayerOutOfRange { depth: usize, len: usize },
+    /// The tree has no layers.
+    EmptyTree,
+    /// The layer at `depth` has no nodes.
+    EmptyLayer { depth: usize },
+}
+
+impl fmt::Display for TreeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LayerOutOfRange { depth, len } => {
+                write!(f, "tree has no layer at depth {depth}, it only has {len} layers")
+            }
+            Self::EmptyTree => f.write_str("tree has no layers"),
+            Self::EmptyLayer { depth } => write!(f, "tree layer at depth {depth} has no nodes"),
+        }
+    }
+}
+
+impl std::error::Error for TreeError {}
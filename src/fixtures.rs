@@ -0,0 +1,65 @@
+//! Fixtures shared by the unit tests of the crate.
+
+use bevy::prelude::*;
+
+use crate::StartupTreeFlush;
+
+/// The values pushed by the systems that ran, in the order they ran.
+#[derive(Resource, Default)]
+pub(crate) struct Ran(pub(crate) Vec<u32>);
+
+macro_rules! push_systems {
+    ($($name:ident => $value:literal);+ $(;)?) => {
+        $( pub(crate) fn $name(mut ran: ResMut<Ran>) { ran.0.push($value); } )+
+    };
+}
+
+push_systems! {
+    sys_0 => 0;
+    sys_1 => 1;
+    sys_2 => 2;
+    sys_3 => 3;
+}
+
+/// An app with the task pools that the multi-threaded executor needs and an empty [`Ran`].
+pub(crate) fn ran_app() -> App {
+    let mut app = App::new();
+    app.add_plugins(TaskPoolPlugin::default());
+    app.init_resource::<Ran>();
+    app
+}
+
+/// Whether the `Startup` schedule of `app` orders the flush whose label ends with `flush_suffix`
+/// before the system whose name ends with `system`, directly or through other sets and their
+/// members.
+///
+/// This checks the graph instead of running the schedule, since systems that aren't ordered
+/// relative to each other may still happen to run in the right order.
+pub(crate) fn flush_runs_before(app: &App, flush_suffix: &str, system: &str) -> bool {
+    let graph = app.world().resource::<Schedules>().get(Startup).unwrap().graph();
+    let (flush, _, _) = graph
+        .system_sets()
+        .find(|(_, set, _)| {
+            let flush = set.as_dyn_eq().as_any().downcast_ref::<StartupTreeFlush>();
+            flush.is_some_and(|flush| flush.label().ends_with(flush_suffix))
+        })
+        .unwrap();
+    let (system, _, _) = graph.systems().find(|(_, sys, _)| sys.name().ends_with(system)).unwrap();
+
+    let dependencies = graph.dependency().graph();
+    let hierarchy = graph.hierarchy().graph();
+    let mut stack = vec![flush];
+    let mut visited = vec![flush];
+    while let Some(node) = stack.pop() {
+        for next in dependencies.neighbors(node).chain(hierarchy.neighbors(node)) {
+            if next == system {
+                return true;
+            }
+            if !visited.contains(&next) {
+                visited.push(next);
+                stack.push(next);
+            }
+        }
+    }
+    false
+}
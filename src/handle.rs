@@ -0,0 +1,269 @@
+use bevy_app::App;
+use bevy_ecs::schedule::{
+    Chain, Condition, InternedScheduleLabel, InternedSystemSet, IntoSystemConfigs,
+    IntoSystemSetConfigs, NodeConfigs,
+};
+
+use crate::{StartupTreeLayer, TreeError};
+
+/// A handle to a startup tree that was inserted into an [`App`].
+///
+/// Returned by [`StartupTreeBuilder::insert`](crate::StartupTreeBuilder::insert). The handle can
+/// be used to extend the tree after it was inserted, e.g. when a plugin augments the startup tree
+/// of another plugin.
+///
+/// [`App`]: https://docs.rs/bevy/~0.14/bevy/app/struct.App.html
+#[derive(Debug, Clone)]
+pub struct StartupTreeHandle {
+    schedule: InternedScheduleLabel,
+    layers: Vec<StartupTreeLayer>,
+    end: Vec<InternedSystemSet>,
+}
+
+impl StartupTreeHandle {
+    pub(crate) fn new(
+        schedule: InternedScheduleLabel,
+        layers: Vec<StartupTreeLayer>,
+        end: Vec<InternedSystemSet>,
+    ) -> Self {
+        Self { schedule, layers, end }
+    }
+
+    pub(crate) fn schedule(&self) -> InternedScheduleLabel {
+        self.schedule
+    }
+
+    /// The last sets of the tree, i.e. the flush of its last layer if it has one, and the flushes
+    /// of `flush_after` nodes in the last layer that aren't ordered before it.
+    pub(crate) fn end(&self) -> &[InternedSystemSet] {
+        &self.end
+    }
+
+    /// Append the layers of a tree that was inserted after the end of this one.
+    pub(crate) fn extend(&mut self, other: StartupTreeHandle) {
+        self.layers.extend(other.layers);
+        if !other.end.is_empty() {
+            self.end = other.end;
+        }
+    }
+
+    /// The set of the layer at `depth` of the tree, or `None` if the tree doesn't have that many
+    /// layers.
+    pub fn layer(&self, depth: usize) -> Option<StartupTreeLayer> {
+        self.layers.get(depth).copied()
+    }
+
+    /// The sets of all layers of the tree, in depth order.
+    pub fn layers(&self) -> &[StartupTreeLayer] {
+        &self.layers
+    }
+
+    /// The number of layers of the tree.
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// Whether the tree has no layers.
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// The set of the last layer of the tree, or `None` if the tree is empty.
+    pub fn last_layer(&self) -> Option<StartupTreeLayer> {
+        self.layers.last().copied()
+    }
+
+    /// Add `systems` that run after the whole tree, in the same schedule as the tree.
+    ///
+    /// For a tree in [`Startup`] the systems run before anything in [`PostStartup`]. They are
+    /// ordered after the last layer, the flush that follows it, and the flushes of the
+    /// `flush_after` nodes in it, so commands queued by the tree have been applied. Multiple
+    /// finalizers run in no particular order relative to each other.
+    ///
+    /// [`Startup`]: https://docs.rs/bevy/~0.14/bevy/app/struct.Startup.html
+    /// [`PostStartup`]: https://docs.rs/bevy/~0.14/bevy/app/struct.PostStartup.html
+    pub fn add_finalizer<M>(&self, app: &mut App, systems: impl IntoSystemConfigs<M>) {
+        let systems = systems.into_configs();
+        let systems = match self.last_layer() {
+            Some(layer_set) => systems.after(layer_set),
+            None => systems,
+        };
+        let systems = self.end.iter().fold(systems, |systems, &end| systems.after(end));
+        app.add_systems(self.schedule, systems);
+    }
+
+    /// Only run the layers of the tree if `condition` returns `true`.
+    ///
+    /// This gates a tree after it was inserted, e.g. when a later plugin decides whether the tree
+    /// is needed. The condition is evaluated once for all layers, when the first of them is about
+    /// to run, and the layers keep their order. The flushes between the layers still run.
+    pub fn set_run_condition<M>(&self, app: &mut App, condition: impl Condition<M>) {
+        let layers = NodeConfigs::Configs {
+            configs: self.layers.iter().map(|layer| layer.into_configs()).collect(),
+            collective_conditions: Vec::new(),
+            chained: Chain::No,
+        };
+        app.configure_sets(self.schedule, layers.run_if(condition));
+    }
+
+    /// Add `systems` to the layer at `depth` of the tree.
+    ///
+    /// The systems run after those of the previous layer and before those of the next layer, in
+    /// no particular order relative to the other systems of the layer.
+    ///
+    /// Returns an error if the tree doesn't have a layer at `depth`.
+    pub fn add_to_layer<M>(
+        &self,
+        app: &mut App,
+        depth: usize,
+        systems: impl IntoSystemConfigs<M>,
+    ) -> Result<(), TreeError> {
+        let layer_set = self
+            .layer(depth)
+            .ok_or(TreeError::LayerOutOfRange { depth, len: self.layers.len() })?;
+        app.add_systems(self.schedule, systems.in_set(layer_set));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{ecs::schedule::ScheduleBuildSettings, prelude::*};
+
+    use crate::{
+        fixtures::{flush_runs_before, ran_app, sys_0, sys_1, sys_2, sys_3, Ran},
+        rng::reseed_rng,
+        startup_tree, StartupTreeBuilder, TreeError,
+    };
+
+    #[test]
+    fn adds_system_to_existing_layer() {
+        reseed_rng();
+
+        let mut app = ran_app();
+
+        let handle =
+            StartupTreeBuilder::new(startup_tree! { sys_0 => sys_1 => sys_2 }).insert(&mut app);
+        handle.add_to_layer(&mut app, 1, sys_3).unwrap();
+
+        app.update();
+
+        let ran = &app.world().resource::<Ran>().0;
+        assert_eq!(ran[0], 0);
+        assert_eq!(ran[3], 2);
+        assert!(ran[1..3].contains(&1));
+        assert!(ran[1..3].contains(&3));
+    }
+
+    #[test]
+    fn finalizers_run_after_last_layer_within_startup() {
+        reseed_rng();
+
+        let mut app = ran_app();
+
+        let handle =
+            StartupTreeBuilder::new(startup_tree! { sys_0 => sys_1 => sys_2 }).insert(&mut app);
+        handle.add_finalizer(&mut app, sys_3);
+        handle.add_finalizer(&mut app, sys_3);
+        app.add_systems(PostStartup, |mut ran: ResMut<Ran>| ran.0.push(4));
+
+        app.update();
+
+        let ran = &app.world().resource::<Ran>().0;
+        assert_eq!(ran, &[0, 1, 2, 3, 3, 4]);
+    }
+
+    #[derive(Component)]
+    struct Spawned;
+
+    fn spawn(mut commands: Commands) {
+        commands.spawn(Spawned);
+    }
+
+    fn count_spawned(q_spawned: Query<(), With<Spawned>>, mut ran: ResMut<Ran>) {
+        ran.0.push(q_spawned.iter().count() as u32);
+    }
+
+    #[test]
+    fn finalizers_see_commands_of_flush_after_node_in_last_layer() {
+        reseed_rng();
+
+        let mut app = ran_app();
+        app.edit_schedule(Startup, |schedule| {
+            schedule.set_build_settings(ScheduleBuildSettings {
+                auto_insert_apply_deferred: false,
+                ..default()
+            });
+        });
+
+        let handle =
+            StartupTreeBuilder::new(startup_tree! { sys_0 => flush_after spawn }).insert(&mut app);
+        handle.add_finalizer(&mut app, count_spawned);
+
+        assert!(flush_runs_before(&app, "_flush_1_0", "count_spawned"));
+        app.update();
+        assert_eq!(app.world().resource::<Ran>().0, [0, 1]);
+    }
+
+    #[derive(Resource)]
+    struct Enabled;
+
+    fn run_gated_tree(enabled: bool) -> Vec<u32> {
+        reseed_rng();
+
+        let mut app = ran_app();
+        if enabled {
+            app.insert_resource(Enabled);
+        }
+
+        let handle =
+            StartupTreeBuilder::new(startup_tree! { sys_0 => sys_1 => sys_2 }).insert(&mut app);
+        app.add_systems(Startup, sys_3);
+        handle.set_run_condition(&mut app, resource_exists::<Enabled>);
+
+        app.update();
+
+        app.world_mut().remove_resource::<Ran>().unwrap().0
+    }
+
+    #[test]
+    fn run_condition_skips_every_layer() {
+        assert_eq!(run_gated_tree(false), [3]);
+    }
+
+    #[test]
+    fn run_condition_keeps_layer_order() {
+        let ran = run_gated_tree(true);
+        let tree_systems = ran.into_iter().filter(|&sys| sys != 3).collect::<Vec<_>>();
+        assert_eq!(tree_systems, [0, 1, 2]);
+    }
+
+    #[test]
+    fn layers_are_queryable_by_depth() {
+        reseed_rng();
+
+        let mut app = App::new();
+
+        let handle =
+            StartupTreeBuilder::new(startup_tree! { sys_0 => sys_1 => sys_2 }).insert(&mut app);
+
+        assert_eq!(handle.len(), 3);
+        assert!(!handle.is_empty());
+        assert_eq!(handle.layer(0), handle.layers().first().copied());
+        assert_eq!(handle.layer(2), handle.last_layer());
+        assert_ne!(handle.layer(1), handle.layer(2));
+        assert_eq!(handle.layer(3), None);
+    }
+
+    #[test]
+    fn errors_when_adding_system_to_missing_layer() {
+        reseed_rng();
+
+        let mut app = App::new();
+
+        let handle = StartupTreeBuilder::new(startup_tree! { sys_0 => sys_1 }).insert(&mut app);
+        let result = handle.add_to_layer(&mut app, 2, sys_3);
+
+        assert_eq!(result, Err(TreeError::LayerOutOfRange { depth: 2, len: 2 }));
+    }
+}
@@ -0,0 +1,95 @@
+use bevy_ecs::schedule::{IntoSystemConfigs, SystemConfigs};
+
+use crate::StartupTreeNode;
+
+/// Create a tree from an array of layers, each of which is the systems at one depth.
+///
+/// Arrays can be passed to [`add_startup_tree`](crate::AddStartupTree::add_startup_tree) without
+/// the [`startup_tree`](crate::startup_tree) macro, but all of the inner arrays must have the same
+/// length because they are the same type. This function wraps each layer in an array of length 1
+/// instead, so a layer with multiple systems is written as a tuple of systems.
+///
+/// ```rust
+/// # use bevy::prelude::*;
+/// # use bevy_startup_tree::{startup_tree_layers, AddStartupTree};
+/// # fn sys_1_a() {}
+/// # fn sys_1_b() {}
+/// # fn sys_2() {}
+/// # fn sys_3_a() {}
+/// # fn sys_3_b() {}
+/// App::new().add_startup_tree(startup_tree_layers([
+///     (sys_1_a, sys_1_b).into_configs(),
+///     sys_2.into_configs(),
+/// ]));
+///
+/// // Fine without the adapter, because each layer has the same number of nodes
+/// App::new().add_startup_tree([
+///     [sys_1_a.into_configs(), sys_1_b.into_configs()],
+///     [sys_3_a.into_configs(), sys_3_b.into_configs()],
+/// ]);
+/// ```
+pub fn startup_tree_layers<N, const LEN: usize>(layers: [N; LEN]) -> [[N; 1]; LEN]
+where
+    N: Into<StartupTreeNode>,
+{
+    layers.map(|layer| [layer])
+}
+
+/// Merge the layers of `subtree` into `startup_tree`, starting at `depth`.
+///
+/// The first layer of `subtree` is appended to the layer at `depth`, the second one to the next
+/// layer and so on, and layers are added as needed. The [`startup_tree`](crate::startup_tree)
+/// macro uses this for `@subtree` nodes, but it can also be used to assemble a tree by hand.
+///
+/// ```rust
+/// # use bevy::prelude::*;
+/// # use bevy_startup_tree::{splice_startup_tree, startup_tree, StartupTreeNode};
+/// # fn root() {}
+/// # fn load() {}
+/// # fn spawn() {}
+/// let mut tree: Vec<Vec<StartupTreeNode>> = vec![vec![root.into_configs().into()]];
+/// splice_startup_tree(&mut tree, 1, startup_tree! { load => spawn });
+/// assert_eq!(tree.len(), 3);
+/// ```
+pub fn splice_startup_tree<I2, I>(
+    startup_tree: &mut Vec<Vec<StartupTreeNode>>,
+    depth: usize,
+    subtree: I2,
+) where
+    I2: IntoIterator<Item = I>,
+    I: IntoIterator<Item: Into<StartupTreeNode>>,
+{
+    for (i, layer) in subtree.into_iter().enumerate() {
+        if startup_tree.len() <= depth + i {
+            startup_tree.resize_with(depth + i + 1, Vec::new);
+        }
+        startup_tree[depth + i].extend(layer.into_iter().map(Into::into));
+    }
+}
+
+/// Append a layer with only `finalizer` to `startup_tree`, so that it runs after every other
+/// system of the tree.
+///
+/// This is the runtime equivalent of a fan-in node, e.g. `a, b =>> finalizer`, for a tree that was
+/// already created, like one that is returned by a [`startup_tree_fn`](crate::startup_tree_fn).
+///
+/// ```rust
+/// # use bevy::prelude::*;
+/// # use bevy_startup_tree::{startup_tree, startup_tree_with_finalizer, AddStartupTree};
+/// # fn load() {}
+/// # fn spawn() {}
+/// # fn finish() {}
+/// let tree = startup_tree_with_finalizer(startup_tree! { load => spawn }, finish);
+/// assert_eq!(tree.len(), 3);
+/// App::new().add_startup_tree(tree);
+/// ```
+pub fn startup_tree_with_finalizer<N, M>(
+    mut startup_tree: Vec<Vec<N>>,
+    finalizer: impl IntoSystemConfigs<M>,
+) -> Vec<Vec<N>>
+where
+    SystemConfigs: Into<N>,
+{
+    startup_tree.push(vec![finalizer.into_configs().into()]);
+    startup_tree
+}
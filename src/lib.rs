@@ -61,6 +61,531 @@
 //! - Depth 1 tree set
 //! - Depth 1 tree flush set
 //!
+//! # Node Expressions
+//!
+//! A node can be any expression that converts into systems, like `make_system(3)` or
+//! `sys.run_if(condition)`. The expression is evaluated once, where the `startup_tree` macro is
+//! used, to create the systems. It is not evaluated again each time the systems run. Side effects
+//! of the expression, e.g. `make_system(counter.next())`, happen when the tree is created, even if
+//! the systems never run:
+//!
+//! ```rust no_run
+//! # use bevy_startup_tree::startup_tree;
+//! fn spawn_wave(index: u32) -> impl FnMut() {
+//!     move || println!("spawning wave {index}")
+//! }
+//!
+//! let mut next_wave = 0..;
+//! # std::mem::drop(
+//! startup_tree! {
+//!     // `next_wave.next()` runs twice right here, the systems print waves 0 and 1
+//!     spawn_wave(next_wave.next().unwrap()) => spawn_wave(next_wave.next().unwrap()),
+//! }
+//! # );
+//! ```
+//!
+//! Work that must happen when the tree runs belongs in the body of a system. The macro can't tell
+//! which expressions have side effects, so it doesn't warn about them.
+//!
+//! Evaluating the nodes up front is what makes factory systems work, though. A method that only
+//! builds a system from its object, like a config that isn't a resource, can be called right in
+//! the tree. The object is only needed while the tree is created, not when the systems run:
+//!
+//! ```rust no_run
+//! # use bevy::prelude::*;
+//! # use bevy_startup_tree::{startup_tree, AddStartupTree};
+//! # #[derive(Component)]
+//! # struct Enemy;
+//! # #[derive(Component)]
+//! # struct Boss;
+//! struct EnemyConfig {
+//!     count: usize,
+//! }
+//!
+//! impl EnemyConfig {
+//!     fn spawn_enemies(&self) -> impl FnMut(Commands) + Send + Sync + 'static {
+//!         let count = self.count;
+//!         move |mut commands| {
+//!             for _ in 0..count {
+//!                 commands.spawn(Enemy);
+//!             }
+//!         }
+//!     }
+//!
+//!     fn spawn_boss(&self) -> impl FnMut(Commands) + Send + Sync + 'static {
+//!         let has_boss = self.count > 5;
+//!         move |mut commands| {
+//!             if has_boss {
+//!                 commands.spawn(Boss);
+//!             }
+//!         }
+//!     }
+//! }
+//!
+//! let config = EnemyConfig { count: 10 };
+//! App::new().add_startup_tree(startup_tree! {
+//!     config.spawn_enemies() => config.spawn_boss(),
+//! });
+//! ```
+//!
+//! Unlike `next_wave.next()` above, a factory method has no effects of its own, so it doesn't
+//! matter that it's called when the tree is created rather than when it runs.
+//!
+//! A node can also convert into more than one system. A helper that groups systems, like a tuple
+//! of systems or a function that returns one as [`SystemConfigs`], is a single node whose systems
+//! all run in that node's layer, in parallel unless they are ordered among themselves. The nodes
+//! after it run once all of its systems are done:
+//!
+//! ```rust no_run
+//! # use bevy::{ecs::schedule::SystemConfigs, prelude::*};
+//! # use bevy_startup_tree::startup_tree;
+//! # fn spawn_player() {}
+//! # fn spawn_camera() {}
+//! # fn spawn_lights() {}
+//! # fn spawn_hud() {}
+//! fn spawn_scene() -> SystemConfigs {
+//!     (spawn_camera, spawn_lights).into_configs()
+//! }
+//!
+//! # std::mem::drop(
+//! startup_tree! {
+//!     spawn_scene() => (spawn_player, spawn_hud.after(spawn_player)),
+//! }
+//! # );
+//! ```
+//!
+//! Such a node counts as one node wherever nodes are counted, e.g. by [`startup_tree_stats`].
+//!
+//! # Chains
+//!
+//! Long linear branches can be written with `chain![...]` in place of a node. The nodes of a chain
+//! each run at the depth after the previous one. Any children of the chain are children of its last
+//! node. The following trees are equivalent:
+//!
+//! ```rust no_run
+//! # use bevy_startup_tree::startup_tree;
+//! # fn a() {}
+//! # fn b() {}
+//! # fn c() {}
+//! # fn d() {}
+//! # fn e() {}
+//! # std::mem::drop((
+//! startup_tree! {
+//!     chain![a, b, c] => { d, e },
+//! }
+//! # ,
+//! startup_tree! {
+//!     a => b => c => { d, e },
+//! }
+//! # ));
+//! ```
+//!
+//! # Forests
+//!
+//! Independent groups of branches, e.g. the startup systems of different subsystems, can be
+//! separated with `;` instead of `,` at the top level of the tree. The groups are merged by depth
+//! as if they were separated by `,`, so the following trees are equivalent:
+//!
+//! ```rust no_run
+//! # use bevy_startup_tree::startup_tree;
+//! # fn ui_a() {}
+//! # fn ui_b() {}
+//! # fn physics_a() {}
+//! # fn physics_b() {}
+//! # std::mem::drop((
+//! startup_tree! {
+//!     ui_a => ui_b;
+//!     physics_a => physics_b;
+//! }
+//! # ,
+//! startup_tree! {
+//!     ui_a => ui_b,
+//!     physics_a => physics_b,
+//! }
+//! # ));
+//! ```
+//!
+//! # Fan-In
+//!
+//! A tree may end with `=>>` followed by a node, which runs in a final layer of its own after all
+//! other nodes of the tree, e.g. to finish setup that depends on many independent systems. The
+//! following trees are equivalent:
+//!
+//! ```rust no_run
+//! # use bevy_startup_tree::startup_tree;
+//! # fn load_a() {}
+//! # fn load_b() {}
+//! # fn load_c() {}
+//! # fn done() {}
+//! # std::mem::drop((
+//! startup_tree! {
+//!     load_a => load_b,
+//!     load_c
+//!     =>> done
+//! }
+//! # ,
+//! startup_tree! {
+//!     load_a => load_b => done,
+//!     load_c,
+//! }
+//! # ));
+//! ```
+//!
+//! # Pipes
+//!
+//! A `pipe { .. }` node pipes its systems together, so that the output of each system is the
+//! [`In`] of the next, like [`IntoSystem::pipe`]. The output of the last system must be `()`.
+//!
+//! ```rust no_run
+//! # use bevy::prelude::*;
+//! # use bevy_startup_tree::startup_tree;
+//! # fn load_config() {}
+//! # fn read_level() -> u32 { 1 }
+//! # fn spawn_level(In(level): In<u32>) {}
+//! # fn spawn_ui() {}
+//! # std::mem::drop(
+//! startup_tree! {
+//!     load_config => pipe { read_level => spawn_level } => spawn_ui,
+//! }
+//! # );
+//! ```
+//!
+//! The piped systems are a single system in the layer of the node. They run one after the other
+//! on the same thread, with the combined access of all of them, in parallel with the other
+//! systems of the layer that they don't conflict with. Their commands are applied together at the
+//! flush after the layer.
+//!
+//! A system linked with `=?>` instead of `=>` is an optional step: it returns a `Result` of its
+//! input type, and if it fails the error is logged as a warning and its input is passed on to the
+//! next system instead. See [`optional_step`].
+//!
+//! ```rust no_run
+//! # use bevy::prelude::*;
+//! # use bevy_startup_tree::startup_tree;
+//! # fn read_level() -> u32 { 1 }
+//! # fn apply_mods(In(level): In<u32>) -> Result<u32, String> { Ok(level) }
+//! # fn spawn_level(In(level): In<u32>) {}
+//! # std::mem::drop(
+//! startup_tree! {
+//!     pipe { read_level =?> apply_mods => spawn_level },
+//! }
+//! # );
+//! ```
+//!
+//! # Groups
+//!
+//! Braces group branches so that a node can run after all of them, at the depth after the
+//! deepest node of the group. This also works for the children of a node.
+//!
+//! ```rust no_run
+//! # use bevy_startup_tree::startup_tree;
+//! # fn load_a() {}
+//! # fn load_b() {}
+//! # fn parse_b() {}
+//! # fn build() {}
+//! # fn setup() {}
+//! # fn check() {}
+//! # fn finish() {}
+//! # std::mem::drop(
+//! startup_tree! {
+//!     { load_a, load_b => parse_b } => build,
+//!     setup => { check } => finish,
+//! }
+//! # );
+//! ```
+//!
+//! Here `build` runs at depth 2, after `load_a` and `parse_b`.
+//!
+//! A group of nodes that all run at the same depth can also be written inline as `par![...]`,
+//! e.g. `par![load_a, load_b] => build` is the same as `{ load_a, load_b } => build`. Unlike
+//! braces, `par!` only takes nodes, not branches.
+//!
+//! # Subtrees
+//!
+//! A node written as `@subtree`, where `subtree` is any expression that evaluates to a tree like
+//! the output of [`startup_tree`], splices the layers of that tree into this one. The first layer
+//! of the subtree runs at the depth of the node, the next one at the depth after it and so on.
+//! Large trees can be assembled from named fragments this way:
+//!
+//! ```rust no_run
+//! # use bevy::prelude::*;
+//! # use bevy_startup_tree::{startup_tree, startup_tree_fn, AddStartupTree};
+//! # fn load_config() {}
+//! # fn spawn_root() {}
+//! # fn spawn_menu() {}
+//! # fn spawn_panel() {}
+//! # fn fill_panel() {}
+//! startup_tree_fn!(panel => { spawn_panel => fill_panel });
+//!
+//! let menu = startup_tree! { spawn_menu => @panel() };
+//! App::new().add_startup_tree(startup_tree! {
+//!     load_config => spawn_root => { @menu, @panel() },
+//! });
+//! ```
+//!
+//! Systems can't be cloned, so a subtree is moved into the tree that splices it. To splice the same
+//! fragment more than once, make it a function with [`startup_tree_fn`] and splice a call to it
+//! each time, every call creates fresh systems. The subtree is only evaluated when the outer tree
+//! is, and the `each:` suffix of the outer tree doesn't apply to it.
+//!
+//! The depth of a subtree is only known at runtime, so a spliced node can't have children, and a
+//! tree with a spliced node can't have a fan-in node. A group can contain spliced nodes, but then
+//! it can't be followed by `=>`.
+//!
+//! # Async Nodes
+//!
+//! A node may be marked `async` to make the next depth of the tree wait for asynchronous work that
+//! the node spawns into the [`StartupTasks`] resource, e.g. loading a file on the
+//! [`AsyncComputeTaskPool`].
+//!
+//! ```rust no_run
+//! # use bevy_startup_tree::startup_tree;
+//! # fn load_assets() {}
+//! # fn spawn_scene() {}
+//! # std::mem::drop(
+//! startup_tree! {
+//!     async load_assets => spawn_scene,
+//! }
+//! # );
+//! ```
+//!
+//! See [`StartupTasks`] for more information.
+//!
+//! # Pure Nodes
+//!
+//! By default, the deferred commands of each depth are applied in a flush before the next depth
+//! runs. A node may be marked `pure` to declare that its system doesn't use [`Commands`] or any
+//! other deferred system parameter. No flush is inserted after a depth whose nodes are all `pure`.
+//!
+//! ```rust no_run
+//! # use bevy_startup_tree::startup_tree;
+//! # fn read_config() {}
+//! # fn validate_config() {}
+//! # fn spawn_scene() {}
+//! # std::mem::drop(
+//! startup_tree! {
+//!     pure read_config => pure validate_config => spawn_scene,
+//! }
+//! # );
+//! ```
+//!
+//! Note that Bevy still inserts its own sync points between systems that need them, so a `pure`
+//! annotation on a system that does use commands only removes the explicit flush.
+//!
+//! In a wide depth where only some nodes use commands, mark those nodes `flush_after` instead. The
+//! commands of a `flush_after` node are applied as soon as it has run, and the depth is only
+//! flushed as a whole if it has another node that is neither `pure` nor `flush_after`.
+//!
+//! ```rust no_run
+//! # use bevy_startup_tree::startup_tree;
+//! # fn spawn_player() {}
+//! # fn log_settings() {}
+//! # fn check_gamepads() {}
+//! # fn spawn_hud() {}
+//! # std::mem::drop(
+//! startup_tree! {
+//!     flush_after spawn_player => spawn_hud,
+//!     pure log_settings,
+//!     pure check_gamepads,
+//! }
+//! # );
+//! ```
+//!
+//! # Placeholders
+//!
+//! A `_` node takes up a position in the tree without running a system. Its layer is still ordered
+//! and flushed like any other, so a placeholder can be used to push the rest of a branch down a
+//! depth, e.g. to make a system wait for a deeper layer of another branch or to force an extra
+//! flush between two systems.
+//!
+//! ```rust no_run
+//! # use bevy_startup_tree::startup_tree;
+//! # fn spawn_map() {}
+//! # fn spawn_tiles() {}
+//! # fn spawn_units() {}
+//! # fn spawn_camera() {}
+//! # std::mem::drop(
+//! startup_tree! {
+//!     spawn_map => spawn_tiles => spawn_units,
+//!     _ => _ => spawn_camera,
+//! }
+//! # );
+//! ```
+//!
+//! Here `spawn_camera` runs at depth 2, alongside `spawn_units`, after the tiles are spawned.
+//!
+//! # Pinned Depths
+//!
+//! Prefixing a node with `@depth(n)` puts it at depth `n` regardless of its position in the tree,
+//! and its children at the depths after it. Any layers in between that have no nodes are still
+//! added, so two trees can be aligned by depth, e.g. to run their depth 2 work at the same time.
+//!
+//! ```rust no_run
+//! # use bevy_startup_tree::startup_tree;
+//! # fn load_config() {}
+//! # fn spawn_world() {}
+//! # fn spawn_camera() {}
+//! # std::mem::drop(
+//! startup_tree! {
+//!     load_config => @depth(2) spawn_world => spawn_camera,
+//! }
+//! # );
+//! ```
+//!
+//! Here depth 1 is empty, `spawn_world` runs at depth 2 and `spawn_camera` at depth 3. A node
+//! can't be pinned to the depth of its parent or before it, and a `{ .. } => child` group can't
+//! contain pinned nodes, since its children only wait for the depths that the group spans.
+//!
+//! # Layer Names
+//!
+//! The system set of each depth is labeled with its depth by default. A depth may be given a name
+//! instead by prefixing one of its nodes with a string literal and a colon, which makes the sets
+//! easier to recognize, e.g. in schedule graphs. Each name may only be used for one depth.
+//!
+//! ```rust no_run
+//! # use bevy_startup_tree::startup_tree;
+//! # fn load_config() {}
+//! # fn load_assets() {}
+//! # fn spawn_world() {}
+//! # std::mem::drop(
+//! startup_tree! {
+//!     "load": load_config => "build": spawn_world,
+//!     load_assets,
+//! }
+//! # );
+//! ```
+//!
+//! # Conditional Compilation
+//!
+//! An arrow can be guarded by a `#[cfg(...)]` attribute. When the predicate is false, the children
+//! of the arrow are siblings of its parent instead, at the same depth.
+//!
+//! ```rust no_run
+//! # use bevy_startup_tree::startup_tree;
+//! # fn load_assets() {}
+//! # fn spawn_world() {}
+//! # std::mem::drop(
+//! startup_tree! {
+//!     load_assets #[cfg(feature = "embedded_assets")] => spawn_world,
+//! }
+//! # );
+//! ```
+//!
+//! A top-level branch can be guarded by a `#[cfg(...)]` attribute as well, e.g. to choose between
+//! alternative trees for different platforms. When the predicate is false, the branch is left out
+//! along with any layers that only it would have had. The attribute of a group applies to all of
+//! its branches.
+//!
+//! ```rust no_run
+//! # use bevy_startup_tree::startup_tree;
+//! # fn load_web_assets() {}
+//! # fn load_desktop_assets() {}
+//! # fn spawn_world() {}
+//! # std::mem::drop(
+//! startup_tree! {
+//!     #[cfg(target_family = "wasm")] load_web_assets => spawn_world,
+//!     #[cfg(not(target_family = "wasm"))] load_desktop_assets => spawn_world,
+//! }
+//! # );
+//! ```
+//!
+//! The macro expands to a tree for each combination of the predicates, so each distinct predicate
+//! doubles the size of the expansion. A tree can have at most 8 distinct predicates.
+//!
+//! # Common Configuration
+//!
+//! A tree may start with an `each:` header followed by method calls and a `;`. The calls are
+//! appended to the systems of every node, after any configuration of the node itself.
+//!
+//! ```rust no_run
+//! # use bevy::prelude::*;
+//! # use bevy_startup_tree::startup_tree;
+//! # #[derive(Resource)]
+//! # struct Headless;
+//! # fn spawn_window() {}
+//! # fn spawn_ui() {}
+//! # std::mem::drop(
+//! startup_tree! {
+//!     each: .run_if(not(resource_exists::<Headless>));
+//!     spawn_window => spawn_ui,
+//! }
+//! # );
+//! ```
+//!
+//! # Node Labels
+//!
+//! There is no order between the systems at the same depth. A node can be labeled with `as label`
+//! so that other nodes can be ordered against it with `.before(label)` and `.after(label)`.
+//!
+//! ```rust no_run
+//! # use bevy_startup_tree::startup_tree;
+//! # fn load_config() {}
+//! # fn load_assets() {}
+//! # fn spawn_world() {}
+//! # std::mem::drop(
+//! startup_tree! {
+//!     load_config as config,
+//!     load_assets.after(config) => spawn_world,
+//! }
+//! # );
+//! ```
+//!
+//! Labels are shared by the whole tree, so nodes in independent branches can be ordered as well,
+//! e.g. when `spawn_enemies` needs the navmesh of the other branch:
+//!
+//! ```rust no_run
+//! # use bevy_startup_tree::startup_tree;
+//! # fn load_level() {}
+//! # fn build_navmesh() {}
+//! # fn load_enemies() {}
+//! # fn spawn_enemies() {}
+//! # std::mem::drop(
+//! startup_tree! {
+//!     load_level => build_navmesh as navmesh,
+//!     load_enemies => spawn_enemies.after(navmesh),
+//! }
+//! # );
+//! ```
+//!
+//! Orderings against nodes at other depths must agree with the tree, and the orderings within a
+//! depth may not form a cycle, otherwise the macro reports an error. A node in a `#[cfg]` branch
+//! can only be ordered against by nodes that the same predicate guards, since it may not be
+//! compiled. Arguments that aren't labels of the tree are left as regular Bevy orderings, e.g.
+//! against a system function.
+//!
+//! # Node Sets
+//!
+//! Labels are only known within the tree. To order systems outside of the tree against a single
+//! node, rather than its whole layer, the node can be added to a system set with `in Set`. The set
+//! may come before or after the node's label.
+//!
+//! ```rust no_run
+//! # use bevy::prelude::*;
+//! # use bevy_startup_tree::{startup_tree, AddStartupTree};
+//! # fn load_assets() {}
+//! # fn spawn_player() {}
+//! # fn spawn_world() {}
+//! # fn attach_camera() {}
+//! #[derive(SystemSet, Clone, PartialEq, Eq, Hash, Debug)]
+//! struct PlayerSet;
+//!
+//! App::new()
+//!     .add_startup_tree(startup_tree! {
+//!         load_assets => {
+//!             spawn_player in PlayerSet,
+//!             spawn_world,
+//!         },
+//!     })
+//!     .add_systems(Startup, attach_camera.after(PlayerSet));
+//! ```
+//!
+//! # Testing
+//!
+//! The `test-util` feature enables `run_startup_tree_once`, which runs a tree in a minimal app and
+//! returns the resulting world so that integration tests can make assertions about it. It also
+//! enables `startup_tree_layer_labels`, which lists the layers that the trees of an app were split
+//! into, in order, without running them.
+//!
 //! # Example
 //!
 //! The following is an example Bevy `App` with a startup tree. Note that the app will go through
@@ -118,84 +643,907 @@
 //!
 //! The `begin` and `end` systems demonstrates when the tree runs during startup. To run a system
 //! before the tree, insert it into the [`PreStartup` schedule][`PreStartup`]. To run a system after
-//! the tree, insert it into the [`PostStartup` schedule][`PostStartup`].
+//! the tree, insert it into the [`PostStartup` schedule][`PostStartup`]. The tree only starts once
+//! all `PreStartup` systems have run and their commands have been applied, but it may run at the
+//! same time as other systems in the `Startup` schedule. Use [`StartupTreeBuilder::after`] to make
+//! the tree wait for those.
 //!
 //! [`App`]: https://docs.rs/bevy/~0.14/bevy/app/struct.App.html
+//! [`In`]: https://docs.rs/bevy/~0.14/bevy/ecs/system/struct.In.html
+//! [`IntoSystem::pipe`]: https://docs.rs/bevy/~0.14/bevy/ecs/system/trait.IntoSystem.html#method.pipe
+//! [`AsyncComputeTaskPool`]: https://docs.rs/bevy/~0.14/bevy/tasks/struct.AsyncComputeTaskPool.html
+//! [`Commands`]: https://docs.rs/bevy/~0.14/bevy/ecs/system/struct.Commands.html
 //! [`PostStartup`]: https://docs.rs/bevy/~0.14/bevy/app/struct.PostStartup.html
 //! [`PreStartup`]: https://docs.rs/bevy/~0.14/bevy/app/struct.PreStartup.html
 //! [`Startup`]: https://docs.rs/bevy/~0.14/bevy/app/struct.Startup.html
+//! [`SystemConfigs`]: https://docs.rs/bevy/~0.14/bevy/ecs/schedule/type.SystemConfigs.html
 //! [`SystemSet`]: https://docs.rs/bevy/~0.14/bevy/ecs/schedule/trait.SystemSet.html
 
-use std::fmt::Write;
+use bevy_app::App;
+use bevy_core::{TaskPoolOptions, TaskPoolPlugin};
+use bevy_ecs::schedule::{IntoSystemConfigs, IntoSystemSet, ScheduleLabel, SystemSet};
+use bevy_state::state::{OnEnter, States};
 
-use bevy_app::{App, Startup};
-use bevy_ecs::schedule::{IntoSystemConfigs, IntoSystemSetConfigs, SystemConfigs};
-use rand::distributions::{Alphanumeric, DistString};
+extern crate self as bevy_startup_tree;
 
+mod builder;
+mod error;
+#[cfg(test)]
+mod fixtures;
+mod handle;
+mod layers;
+mod manifest;
+mod named;
+mod node;
+mod panics;
+mod pipeline;
 mod rng;
 mod schedule;
+mod stats;
+mod tasks;
+#[cfg(feature = "test-util")]
+mod test_util;
+mod timings;
+
+pub use self::{
+    builder::{build_startup_schedule, insert_startup_tree, StartupTreeBuilder},
+    error::TreeError,
+    handle::StartupTreeHandle,
+    layers::{splice_startup_tree, startup_tree_layers, startup_tree_with_finalizer},
+    manifest::StartupTreeManifest,
+    named::StartupTreeRegistry,
+    node::StartupTreeNode,
+    pipeline::{optional_step, run_system_pipeline},
+    schedule::{
+        ScopedStartupTreeLayer, StartupTreeFlush, StartupTreeLayer, StartupTreeNodeLabel,
+        StartupTreeSystems,
+    },
+    stats::{count_systems, describe_layers, startup_tree_stats, validate_startup_tree, TreeStats},
+    tasks::{await_startup_tasks, StartupTasks},
+    timings::StartupTimings,
+};
 
-use self::rng::get_rng;
-use self::schedule::StartupTreeLayer;
+#[cfg(feature = "test-util")]
+pub use self::test_util::{
+    assert_same_layering, run_startup_tree_once, set_namespace_seed, startup_tree_layer_labels,
+    StartupTreeInsertionLog,
+};
 
 /// Generate a tree of startup systems that can be consumed by [`AddStartupTree::add_startup_tree`].
 ///
 /// See the [module docs](crate) for more information.
 pub use bevy_startup_tree_macros::startup_tree;
 
-const NAMESPACE_LEN: usize = 6;
-
-/// An extension trait for [`bevy::app::App`][`App`].
+/// Generate a tree of startup systems whose nodes may only be paths to systems.
 ///
-/// [`App`]: https://docs.rs/bevy/~0.14/bevy/app/struct.App.html
-pub trait AddStartupTree {
-    /// Add a dependency tree of startup systems to the [`App`].
-    ///
-    /// The input is an iterator over a 2-D array describing a tree where each row (inner iterator
-    /// `I`) with index `i` contains the nodes at depth `i` in the tree. There is *no guarantee*
-    /// that systems at the same depth with run in any specific order. It is strongly recommended
-    /// that the [`startup_tree` macro](startup_tree) is used to generate the tree.
-    ///
-    /// See the [module docs](crate) for more information.
-    ///
-    /// [`App`]: https://docs.rs/bevy/~0.14/bevy/app/struct.App.html
-    fn add_startup_tree<I2, I>(&mut self, startup_tree: I2) -> &mut Self
-    where
-        I2: IntoIterator<Item = I>,
-        I: IntoIterator<Item = SystemConfigs>;
-}
+/// The input is the same as the input of [`startup_tree`], but the macro fails to compile if a
+/// node is anything other than a path, like `spawn_player` or `spawn::<Enemy>`, e.g. a closure or
+/// a method call like `.run_if(condition)`. This keeps trees readable by moving any logic into
+/// named systems.
+///
+/// ```rust no_run
+/// # use bevy_startup_tree::strict_startup_tree;
+/// # fn load_config() {}
+/// # fn spawn_world() {}
+/// # std::mem::drop(
+/// strict_startup_tree! {
+///     load_config => spawn_world,
+/// }
+/// # );
+/// ```
+///
+/// ```rust compile_fail
+/// # use bevy_startup_tree::strict_startup_tree;
+/// # fn load_config() {}
+/// # std::mem::drop(
+/// strict_startup_tree! {
+///     load_config => || {},
+/// }
+/// # );
+/// ```
+///
+/// Annotations, layer names, labels, placeholders and the systems of `pipe { .. }` blocks are
+/// allowed, as are orderings against labels of the tree like `.after(label)`, since those are part
+/// of the tree's syntax rather than expressions.
+pub use bevy_startup_tree_macros::strict_startup_tree;
 
-impl AddStartupTree for App {
-    fn add_startup_tree<I2, I>(&mut self, startup_tree: I2) -> &mut Self
-    where
-        I2: IntoIterator<Item = I>,
-        I: IntoIterator<Item = SystemConfigs>,
-    {
-        let mut rng = get_rng();
-        let namespace = Alphanumeric.sample_string(&mut rng, NAMESPACE_LEN);
-        let label_base = format!("__startup_tree_{namespace}");
+/// Generate a tree of startup systems whose branches don't need to be separated by commas.
+///
+/// The input is the same as the input of [`startup_tree`], but a `,` between two branches is
+/// optional, at any depth. Since macros don't see newlines, a branch simply ends where the
+/// expression of its node ends, and the next branch starts right after it.
+///
+/// ```rust no_run
+/// # use bevy_startup_tree::relaxed_startup_tree;
+/// # fn load_config() {}
+/// # fn load_assets() {}
+/// # fn spawn_world() {}
+/// # fn spawn_player() {}
+/// # std::mem::drop(
+/// relaxed_startup_tree! {
+///     load_config
+///     load_assets => {
+///         spawn_world
+///         spawn_player
+///     }
+/// }
+/// # );
+/// ```
+///
+/// This is ambiguous when a node could continue the expression of the previous one, so a `,` is
+/// still needed before a branch that starts with `{`, `(`, `[`, `.` or `|`, e.g. a group or a
+/// closure, and after a system named like an annotation, e.g. `pure`. The nodes of `chain![..]`,
+/// `par![..]` and `pipe { .. }` are still separated by `,` and `=>`.
+pub use bevy_startup_tree_macros::relaxed_startup_tree;
 
-        startup_tree.into_iter().enumerate().fold(None, |last_layer_set, (i, level)| {
-            let mut label = label_base.clone();
-            write!(label, "_layer_{i}").unwrap();
-            let label: &str = label.leak();
+/// Generate a tree of startup systems and a teardown tree of the same shape.
+///
+/// The input is like the input of [`startup_tree`], but each node may pair its system with a
+/// teardown system, e.g. `spawn_world / despawn_world`. The macro evaluates to a tuple of the
+/// startup tree of the setup systems and the teardown tree of the paired systems, where a node
+/// without a teardown system is a placeholder. Inserted with
+/// [`add_teardown_tree_to_schedule`](AddStartupTree::add_teardown_tree_to_schedule), the teardown
+/// tree runs its layers in reverse, so each system is torn down after everything that was set up
+/// after it.
+///
+/// ```rust no_run
+/// # use bevy::prelude::*;
+/// # use bevy_startup_tree::{startup_teardown_tree, AddStartupTree};
+/// # use bevy_state::state::{OnEnter, OnExit, States};
+/// # #[derive(States, Clone, Debug, Default, PartialEq, Eq, Hash)]
+/// # enum AppState { #[default] InGame }
+/// # fn load_assets() {}
+/// # fn spawn_world() {}
+/// # fn despawn_world() {}
+/// # fn spawn_player() {}
+/// # fn despawn_player() {}
+/// let (startup, teardown) = startup_teardown_tree! {
+///     load_assets => spawn_world / despawn_world => spawn_player / despawn_player,
+/// };
+/// App::new()
+///     .add_startup_tree_to_schedule(OnEnter(AppState::InGame), startup)
+///     .add_teardown_tree_to_schedule(OnExit(AppState::InGame), teardown);
+/// ```
+///
+/// Annotations like `pure` or `in Set` only apply to the setup system. The layer name, label, and
+/// depth pin of a node apply to both, and the label can also be given after the teardown system,
+/// e.g. `spawn_world / despawn_world as world`. Closures must be wrapped in parentheses to be
+/// paired. Spliced trees can't be paired, since their shape is only known at runtime.
+pub use bevy_startup_tree_macros::startup_teardown_tree;
 
-            let layer_set = StartupTreeLayer(label);
+/// Generate a function that returns a tree of startup systems.
+///
+/// The output of [`startup_tree`] can't be stored in a `const` or `static` because
+/// [`SystemConfigs`] can't be created in a const context, nor can it be cloned. Instead, this macro
+/// wraps the tree in a function definition that can be called to create the tree anywhere it's
+/// needed, e.g. when building multiple apps with the same tree.
+///
+/// ```rust no_run
+/// # use bevy::prelude::*;
+/// # use bevy_startup_tree::{startup_tree_fn, AddStartupTree};
+/// # fn sys_1_a() {}
+/// # fn sys_1_b() {}
+/// # fn sys_2() {}
+/// startup_tree_fn!(pub my_tree => {
+///     sys_1_a,
+///     sys_1_b => sys_2,
+/// });
+///
+/// App::new().add_startup_tree(my_tree());
+/// App::new().add_startup_tree(my_tree());
+/// ```
+///
+/// [`SystemConfigs`]: https://docs.rs/bevy/~0.14/bevy/ecs/schedule/type.SystemConfigs.html
+pub use bevy_startup_tree_macros::startup_tree_fn;
 
-            let layer_config = if let Some(last_layer_set) = last_layer_set {
-                layer_set.after(last_layer_set)
-            } else {
-                layer_set.into_configs()
-            };
-            self.configure_sets(Startup, layer_config);
+/// Define a startup tree by its layers, as a list of bracketed lists of systems.
+///
+/// Each list is a layer, and each layer runs after the previous one. This produces the same tree
+/// as [`startup_tree`] with an arrow between every system of a layer and the systems of the next,
+/// so it's a compact alternative for trees that are only wide, not deep.
+///
+/// ```rust no_run
+/// # use bevy::prelude::*;
+/// # use bevy_startup_tree::{startup_layers, AddStartupTree};
+/// # fn load_config() {}
+/// # fn load_assets() {}
+/// # fn spawn_world() {}
+/// # fn spawn_player() {}
+/// # fn spawn_ui() {}
+/// App::new().add_startup_tree(startup_layers![
+///     [load_config, load_assets],
+///     [spawn_world],
+///     [spawn_player, spawn_ui],
+/// ]);
+/// ```
+///
+/// Neither the list of layers nor any layer may be empty.
+pub use bevy_startup_tree_macros::startup_layers;
 
-            for system in level {
-                self.add_systems(Startup, system.in_set(layer_set));
-            }
+/// Generate the names of the systems of [`startup_layers`] by layer, like [`startup_tree_meta`].
+///
+/// The output of the two macros is the same for equivalent trees, so they can be compared to check
+/// that rewriting a tree as explicit layers didn't change it:
+///
+/// ```rust
+/// # use bevy_startup_tree::{startup_layers_meta, startup_tree_meta};
+/// assert_eq!(
+///     startup_tree_meta! { load_config => spawn_world, load_assets },
+///     startup_layers_meta![[load_config, load_assets], [spawn_world]],
+/// );
+/// ```
+pub use bevy_startup_tree_macros::startup_layers_meta;
 
-            Some(layer_set)
-        });
+/// Generate an enum with a system set for each layer of a startup tree.
+///
+/// The enum has a variant for each layer, `L0` for the first layer and so on, and derives
+/// `SystemSet`. Its associated `tree` function returns the tree with the systems of each layer in
+/// the layer's set, so that other systems can be ordered relative to a layer by name. `LAYERS`
+/// holds the sets of all layers, indexed by depth.
+///
+/// ```rust no_run
+/// # use bevy::prelude::*;
+/// # use bevy_startup_tree::{startup_tree_sets, AddStartupTree};
+/// # fn load_assets() {}
+/// # fn spawn_world() {}
+/// # fn spawn_player() {}
+/// # fn log_world() {}
+/// startup_tree_sets!(pub StartupLayers => {
+///     load_assets => spawn_world => spawn_player,
+/// });
+///
+/// assert_eq!(StartupLayers::LAYERS.len(), 3);
+/// App::new()
+///     .add_startup_tree(StartupLayers::tree())
+///     .add_systems(Startup, log_world.after(StartupLayers::L1));
+/// ```
+///
+/// The sets only contain the systems of the nodes, not the flushes between the layers. A tree
+/// with `#[cfg]` attributes has a variant for each layer of its deepest combination of
+/// predicates, and it can't contain spliced trees, since their layers are only known at runtime.
+/// To use sets that are defined elsewhere, see [`StartupTreeBuilder::layer_sets`].
+pub use bevy_startup_tree_macros::startup_tree_sets;
+
+/// Generate the names of the nodes of a tree by depth, for inspecting its structure at compile
+/// time.
+///
+/// The input is the same as the input of [`startup_tree`], but instead of the systems the macro
+/// expands to a `&'static [&'static [&'static str]]` with one slice of names per layer. It can be
+/// used in a const context, e.g. to assert the number of layers of a tree:
+///
+/// ```rust
+/// # use bevy_startup_tree::startup_tree_meta;
+/// const LAYERS: &[&[&str]] = startup_tree_meta! {
+///     load_config => spawn_world => {
+///         spawn_player,
+///         spawn_hud,
+///     },
+/// };
+///
+/// const _: () = assert!(LAYERS.len() == 3);
+/// assert_eq!(LAYERS[2], ["spawn_player", "spawn_hud"]);
+/// ```
+///
+/// The systems are not referenced, so they don't have to be in scope. Only nodes that are paths,
+/// like `spawn_player` or `spawn::<Enemy>`, get meaningful names. The name of any other node is
+/// the tokens of its expression, e.g. `spawn_hud . run_if (has_hud)`, and placeholders are named
+/// `_`.
+pub use bevy_startup_tree_macros::startup_tree_meta;
+
+/// Assert the number of systems in each layer of a startup tree at compile time.
+///
+/// The first argument is the expected number of nodes at each depth, the second is the input of
+/// [`startup_tree`] in braces. The macro fails to compile if the layers of the tree have a
+/// different shape, e.g. after a refactor moved a system to the wrong depth.
+///
+/// ```rust
+/// # use bevy_startup_tree::assert_startup_tree_shape;
+/// assert_startup_tree_shape!([2, 1, 3], {
+///     load_config,
+///     load_assets => spawn_world => {
+///         spawn_player,
+///         spawn_enemies,
+///         spawn_hud,
+///     },
+/// });
+/// ```
+///
+/// ```rust compile_fail
+/// # use bevy_startup_tree::assert_startup_tree_shape;
+/// assert_startup_tree_shape!([2, 1], { load_config => spawn_world });
+/// ```
+///
+/// Like [`startup_tree_meta`], the systems are not referenced, so they don't have to be in scope.
+/// For a tree with `#[cfg]` arrows, the shape is checked for the configuration that is compiled.
+/// Trees with spliced subtrees are not supported since their shape is only known at runtime.
+pub use bevy_startup_tree_macros::assert_startup_tree_shape;
+
+/// Generate a tree of startup systems from a RON file at compile time.
+///
+/// The path of the file is relative to the root of the crate, i.e. the directory containing its
+/// `Cargo.toml`. A tree is a list of branches. A branch is either the name of a system, or a map
+/// from the name of a system to its children, which are a tree themselves. The systems must be in
+/// scope where the macro is used.
+///
+/// ```ron
+/// [
+///     "load_config",
+///     { "spawn_world": ["spawn_player", { "spawn_hud": ["spawn_minimap"] }] },
+/// ]
+/// ```
+///
+/// Given this file, the following trees are equivalent:
+///
+/// ```rust ignore
+/// startup_tree_from_file!("assets/startup.ron");
+///
+/// startup_tree! {
+///     load_config,
+///     spawn_world => {
+///         spawn_player,
+///         spawn_hud => spawn_minimap,
+///     },
+/// };
+/// ```
+///
+/// Annotations and layer names are not supported in tree files. Requires the `file` feature.
+#[cfg(feature = "file")]
+pub use bevy_startup_tree_macros::startup_tree_from_file;
+
+/// An extension trait for [`bevy::app::App`][`App`].
+///
+/// [`App`]: https://docs.rs/bevy/~0.14/bevy/app/struct.App.html
+pub trait AddStartupTree {
+    /// Add a dependency tree of startup systems to the [`App`].
+    ///
+    /// The input is an iterator over a 2-D array describing a tree where each row (inner iterator
+    /// `I`) with index `i` contains the nodes at depth `i` in the tree. There is *no guarantee*
+    /// that systems at the same depth with run in any specific order. It is strongly recommended
+    /// that the [`startup_tree` macro](startup_tree) is used to generate the tree. Each node is
+    /// anything that converts into a [`StartupTreeNode`], like `SystemConfigs`.
+    ///
+    /// Use a [`StartupTreeBuilder`] to configure the tree before inserting it with
+    /// [`add_startup_tree_with`](AddStartupTree::add_startup_tree_with).
+    ///
+    /// The `Startup` schedule only runs once, so a tree added after the app was updated for the
+    /// first time, e.g. by a plugin that is reloaded, never runs and a warning is logged. Such a
+    /// tree can be run right away with [`build_startup_schedule`] and [`Schedule::run`] instead.
+    ///
+    /// See the [module docs](crate) for more information.
+    ///
+    /// [`App`]: https://docs.rs/bevy/~0.14/bevy/app/struct.App.html
+    /// [`Schedule::run`]: https://docs.rs/bevy/~0.14/bevy/ecs/schedule/struct.Schedule.html#method.run
+    fn add_startup_tree<I2, I>(&mut self, startup_tree: I2) -> &mut Self
+    where
+        I2: IntoIterator<Item = I>,
+        I: IntoIterator<Item: Into<StartupTreeNode>>;
+
+    /// Add a dependency tree of startup systems, created by calling `factory`, to the [`App`].
+    ///
+    /// [`SystemConfigs`] can't be cloned, so a tree can only be added to a single app. Taking a
+    /// factory instead of the tree itself means that the same factory, like a function defined with
+    /// [`startup_tree_fn`], can be passed to this method for any number of apps. Each call creates
+    /// a fresh tree.
+    ///
+    /// ```rust no_run
+    /// # use bevy::prelude::*;
+    /// # use bevy_startup_tree::{startup_tree_fn, AddStartupTree};
+    /// # fn sys_1() {}
+    /// # fn sys_2() {}
+    /// startup_tree_fn!(my_tree => { sys_1 => sys_2 });
+    ///
+    /// for _ in 0..3 {
+    ///     App::new().add_startup_tree_from(my_tree);
+    /// }
+    /// ```
+    ///
+    /// See [`add_startup_tree`](AddStartupTree::add_startup_tree) for more information.
+    ///
+    /// [`App`]: https://docs.rs/bevy/~0.14/bevy/app/struct.App.html
+    /// [`SystemConfigs`]: https://docs.rs/bevy/~0.14/bevy/ecs/schedule/type.SystemConfigs.html
+    fn add_startup_tree_from<I2, I>(&mut self, factory: impl FnOnce() -> I2) -> &mut Self
+    where
+        I2: IntoIterator<Item = I>,
+        I: IntoIterator<Item: Into<StartupTreeNode>>;
+
+    /// Add a dependency tree of systems that was configured with a [`StartupTreeBuilder`] to the
+    /// [`App`].
+    ///
+    /// The builder has an option for everything that isn't the default, including the options
+    /// that the other methods of this trait are shorthands for, and they can be combined.
+    ///
+    /// ```rust no_run
+    /// # use bevy::prelude::*;
+    /// # use bevy_startup_tree::{startup_tree, AddStartupTree, StartupTreeBuilder};
+    /// # use bevy_state::state::{OnEnter, States};
+    /// # #[derive(States, Clone, Debug, Default, PartialEq, Eq, Hash)]
+    /// # enum AppState { #[default] InGame }
+    /// # fn spawn_level() {}
+    /// # fn spawn_enemies() {}
+    /// App::new().add_startup_tree_with(
+    ///     StartupTreeBuilder::new(startup_tree! { spawn_level => spawn_enemies })
+    ///         .in_schedule(OnEnter(AppState::InGame))
+    ///         .timed()
+    ///         .flush_every(2),
+    /// );
+    /// ```
+    ///
+    /// This is equivalent to [`StartupTreeBuilder::insert`], which also returns a handle to the
+    /// inserted tree. See [`add_startup_tree`](AddStartupTree::add_startup_tree) for more
+    /// information.
+    ///
+    /// [`App`]: https://docs.rs/bevy/~0.14/bevy/app/struct.App.html
+    fn add_startup_tree_with(&mut self, builder: StartupTreeBuilder) -> &mut Self;
+
+    /// Add a dependency tree of systems to the `schedule` of the [`App`] instead of the
+    /// [`Startup` schedule][`Startup`].
+    ///
+    /// See [`add_startup_tree`](AddStartupTree::add_startup_tree) for more information.
+    ///
+    /// [`App`]: https://docs.rs/bevy/~0.14/bevy/app/struct.App.html
+    /// [`Startup`]: https://docs.rs/bevy/~0.14/bevy/app/struct.Startup.html
+    fn add_startup_tree_to_schedule<I2, I>(
+        &mut self,
+        schedule: impl ScheduleLabel,
+        startup_tree: I2,
+    ) -> &mut Self
+    where
+        I2: IntoIterator<Item = I>,
+        I: IntoIterator<Item: Into<StartupTreeNode>>;
+
+    /// Add a dependency tree of systems to the `schedule` of the [`App`] whose layers run in
+    /// reverse, the deepest layer first and the roots last.
+    ///
+    /// This tears down what a startup tree of the same shape set up, e.g. despawning children
+    /// before their parents in [`OnExit`] of a state.
+    ///
+    /// See [`StartupTreeBuilder::reversed`] and
+    /// [`add_startup_tree`](AddStartupTree::add_startup_tree) for more information.
+    ///
+    /// [`App`]: https://docs.rs/bevy/~0.14/bevy/app/struct.App.html
+    /// [`OnExit`]: https://docs.rs/bevy/~0.14/bevy/state/state/struct.OnExit.html
+    fn add_teardown_tree_to_schedule<I2, I>(
+        &mut self,
+        schedule: impl ScheduleLabel,
+        startup_tree: I2,
+    ) -> &mut Self
+    where
+        I2: IntoIterator<Item = I>,
+        I: IntoIterator<Item: Into<StartupTreeNode>>;
+
+    /// Add a dependency tree of systems that runs the first time `state` is entered.
+    ///
+    /// The tree is added to the [`OnEnter`] schedule of `state` with
+    /// [`StartupTreeBuilder::run_once`], so entering the state again doesn't run the tree again.
+    ///
+    /// See [`add_startup_tree`](AddStartupTree::add_startup_tree) for more information.
+    ///
+    /// [`OnEnter`]: https://docs.rs/bevy/~0.14/bevy/state/state/struct.OnEnter.html
+    fn add_startup_tree_once_on_enter<S, I2, I>(&mut self, state: S, startup_tree: I2) -> &mut Self
+    where
+        S: States,
+        I2: IntoIterator<Item = I>,
+        I: IntoIterator<Item: Into<StartupTreeNode>>;
+
+    /// Add a dependency tree of startup systems to the [`App`] that only runs while the app is in
+    /// `state`.
+    ///
+    /// Since the `Startup` schedule only runs once, the tree only runs if the app starts in
+    /// `state`. See [`StartupTreeBuilder::in_state`] for how this interacts with the [`OnEnter`]
+    /// and [`OnExit`] schedules, and [`add_startup_tree`](AddStartupTree::add_startup_tree) for
+    /// more information.
+    ///
+    /// [`App`]: https://docs.rs/bevy/~0.14/bevy/app/struct.App.html
+    /// [`OnEnter`]: https://docs.rs/bevy/~0.14/bevy/state/state/struct.OnEnter.html
+    /// [`OnExit`]: https://docs.rs/bevy/~0.14/bevy/state/state/struct.OnExit.html
+    fn add_startup_tree_in_state<S, I2, I>(&mut self, state: S, startup_tree: I2) -> &mut Self
+    where
+        S: States,
+        I2: IntoIterator<Item = I>,
+        I: IntoIterator<Item: Into<StartupTreeNode>>;
+
+    /// Add a dependency tree of startup systems to the [`App`] whose layers are tagged with
+    /// `scope`.
+    ///
+    /// See [`StartupTreeBuilder::scope`] and
+    /// [`add_startup_tree`](AddStartupTree::add_startup_tree) for more information.
+    ///
+    /// [`App`]: https://docs.rs/bevy/~0.14/bevy/app/struct.App.html
+    fn add_scoped_startup_tree<I2, I>(
+        &mut self,
+        scope: &'static str,
+        startup_tree: I2,
+    ) -> &mut Self
+    where
+        I2: IntoIterator<Item = I>,
+        I: IntoIterator<Item: Into<StartupTreeNode>>;
+
+    /// Add a dependency tree of startup systems to the [`App`] whose layers are all ambiguous with
+    /// `set`.
+    ///
+    /// See [`StartupTreeBuilder::ambiguous_with`] and
+    /// [`add_startup_tree`](AddStartupTree::add_startup_tree) for more information.
+    ///
+    /// [`App`]: https://docs.rs/bevy/~0.14/bevy/app/struct.App.html
+    fn add_startup_tree_ambiguous_with<M, I2, I>(
+        &mut self,
+        set: impl IntoSystemSet<M>,
+        startup_tree: I2,
+    ) -> &mut Self
+    where
+        I2: IntoIterator<Item = I>,
+        I: IntoIterator<Item: Into<StartupTreeNode>>;
+
+    /// Add a dependency tree of startup systems to the [`App`], making sure that there are at
+    /// least `min_threads` compute threads to run the systems of each depth in parallel.
+    ///
+    /// If the app does not have a [`TaskPoolPlugin`] yet, one is added whose compute pool has at
+    /// least `min_threads` threads, and never fewer than its default minimum. Otherwise, the
+    /// existing task pool configuration is left alone. This means that this method only takes
+    /// effect if it is called *before* `TaskPoolPlugin` is added, and that adding `TaskPoolPlugin`
+    /// (or a plugin group containing it, like `DefaultPlugins`) afterwards will panic because the
+    /// plugin was already added. Disable the plugin in the group in that case.
+    ///
+    /// See [`add_startup_tree`](AddStartupTree::add_startup_tree) for more information.
+    ///
+    /// [`App`]: https://docs.rs/bevy/~0.14/bevy/app/struct.App.html
+    /// [`TaskPoolPlugin`]: https://docs.rs/bevy/~0.14/bevy/core/struct.TaskPoolPlugin.html
+    fn add_startup_tree_with_min_threads<I2, I>(
+        &mut self,
+        min_threads: usize,
+        startup_tree: I2,
+    ) -> &mut Self
+    where
+        I2: IntoIterator<Item = I>,
+        I: IntoIterator<Item: Into<StartupTreeNode>>;
+
+    /// Add a dependency tree of startup systems to the [`App`] whose layers are in the sets
+    /// returned by `sets`.
+    ///
+    /// `sets(i)` is the set for the layer at depth `i`. Other systems can be ordered relative to
+    /// the layers of the tree with these sets, e.g. the variants of a `#[derive(SystemSet)]` enum.
+    ///
+    /// ```rust no_run
+    /// # use bevy::prelude::*;
+    /// # use bevy_startup_tree::{startup_tree, AddStartupTree};
+    /// # fn load() {}
+    /// # fn build() {}
+    /// # fn log_loaded() {}
+    /// #[derive(SystemSet, Clone, Debug, PartialEq, Eq, Hash)]
+    /// enum MyStartup {
+    ///     Load,
+    ///     Build,
+    /// }
+    ///
+    /// App::new()
+    ///     .add_startup_tree_with_sets(
+    ///         |depth| if depth == 0 { MyStartup::Load } else { MyStartup::Build },
+    ///         startup_tree! { load => build },
+    ///     )
+    ///     .add_systems(Startup, log_loaded.after(MyStartup::Load));
+    /// ```
+    ///
+    /// See [`StartupTreeBuilder::layer_sets`] and
+    /// [`add_startup_tree`](AddStartupTree::add_startup_tree) for more information.
+    ///
+    /// [`App`]: https://docs.rs/bevy/~0.14/bevy/app/struct.App.html
+    fn add_startup_tree_with_sets<S, I2, I>(
+        &mut self,
+        sets: impl Fn(usize) -> S + 'static,
+        startup_tree: I2,
+    ) -> &mut Self
+    where
+        S: SystemSet,
+        I2: IntoIterator<Item = I>,
+        I: IntoIterator<Item: Into<StartupTreeNode>>;
+
+    /// Add a dependency tree of startup systems to the [`App`] and record how long each of its
+    /// layers takes to run in the [`StartupTimings`] resource.
+    ///
+    /// See [`StartupTreeBuilder::timed`] and
+    /// [`add_startup_tree`](AddStartupTree::add_startup_tree) for more information.
+    ///
+    /// [`App`]: https://docs.rs/bevy/~0.14/bevy/app/struct.App.html
+    fn add_startup_tree_timed<I2, I>(&mut self, startup_tree: I2) -> &mut Self
+    where
+        I2: IntoIterator<Item = I>,
+        I: IntoIterator<Item: Into<StartupTreeNode>>;
+
+    /// Add a dependency tree of startup systems to the [`App`] and record its systems and their
+    /// layers in the [`StartupTreeManifest`] resource.
+    ///
+    /// See [`StartupTreeBuilder::manifest`] and
+    /// [`add_startup_tree`](AddStartupTree::add_startup_tree) for more information.
+    ///
+    /// [`App`]: https://docs.rs/bevy/~0.14/bevy/app/struct.App.html
+    fn add_startup_tree_reflected<I2, I>(&mut self, startup_tree: I2) -> &mut Self
+    where
+        I2: IntoIterator<Item = I>,
+        I: IntoIterator<Item: Into<StartupTreeNode>>;
+
+    /// Add a dependency tree of startup systems to the [`App`] whose commands are only flushed
+    /// after every `n`th layer and after the last one.
+    ///
+    /// A layer doesn't see the commands of the layers since the last flush applied, see
+    /// [`StartupTreeBuilder::flush_every`] and
+    /// [`add_startup_tree`](AddStartupTree::add_startup_tree) for more information.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is zero.
+    ///
+    /// [`App`]: https://docs.rs/bevy/~0.14/bevy/app/struct.App.html
+    fn add_startup_tree_flush_every<I2, I>(&mut self, n: usize, startup_tree: I2) -> &mut Self
+    where
+        I2: IntoIterator<Item = I>,
+        I: IntoIterator<Item: Into<StartupTreeNode>>;
+
+    /// Add a dependency tree of startup systems to the [`App`] that runs `flush_system` between
+    /// its layers instead of [`apply_deferred`].
+    ///
+    /// See [`StartupTreeBuilder::flush_with`] and
+    /// [`add_startup_tree`](AddStartupTree::add_startup_tree) for more information.
+    ///
+    /// [`App`]: https://docs.rs/bevy/~0.14/bevy/app/struct.App.html
+    /// [`apply_deferred`]: https://docs.rs/bevy/~0.14/bevy/ecs/schedule/fn.apply_deferred.html
+    fn add_startup_tree_with_flush<M, I2, I>(
+        &mut self,
+        flush_system: impl IntoSystemConfigs<M> + Clone + 'static,
+        startup_tree: I2,
+    ) -> &mut Self
+    where
+        I2: IntoIterator<Item = I>,
+        I: IntoIterator<Item: Into<StartupTreeNode>>;
+
+    /// Add a dependency tree of startup systems to the [`App`] under `name`, so that it can be
+    /// extended later with [`extend_named_startup_tree`](AddStartupTree::extend_named_startup_tree).
+    ///
+    /// See [`add_startup_tree`](AddStartupTree::add_startup_tree) for more information.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a tree named `name` was already added to the app.
+    ///
+    /// [`App`]: https://docs.rs/bevy/~0.14/bevy/app/struct.App.html
+    fn add_named_startup_tree<I2, I>(&mut self, name: &'static str, startup_tree: I2) -> &mut Self
+    where
+        I2: IntoIterator<Item = I>,
+        I: IntoIterator<Item: Into<StartupTreeNode>>;
+
+    /// Whether a tree named `name` was added to the [`App`] with
+    /// [`add_named_startup_tree`](AddStartupTree::add_named_startup_tree).
+    ///
+    /// Plugins can use this to skip adding a tree that another plugin already added. The names
+    /// are tracked in the [`StartupTreeRegistry`] resource.
+    ///
+    /// [`App`]: https://docs.rs/bevy/~0.14/bevy/app/struct.App.html
+    fn has_named_startup_tree(&self, name: &str) -> bool;
+
+    /// Append the layers of a dependency tree of startup systems to the tree named `name`.
+    ///
+    /// The first layer of `startup_tree` runs after the last layer of the named tree (and its
+    /// flush). This lets a plugin add more startup steps to the named tree of another plugin. If
+    /// the app has no tree named `name` yet, `startup_tree` is added under that name.
+    ///
+    /// ```rust no_run
+    /// # use bevy::prelude::*;
+    /// # use bevy_startup_tree::{startup_tree, AddStartupTree};
+    /// # fn spawn_root() {}
+    /// # fn spawn_menu() {}
+    /// # fn spawn_settings_menu() {}
+    /// App::new()
+    ///     .add_named_startup_tree("ui", startup_tree! { spawn_root => spawn_menu })
+    ///     .extend_named_startup_tree("ui", startup_tree! { spawn_settings_menu });
+    /// ```
+    ///
+    /// See [`add_startup_tree`](AddStartupTree::add_startup_tree) for more information.
+    fn extend_named_startup_tree<I2, I>(
+        &mut self,
+        name: &'static str,
+        startup_tree: I2,
+    ) -> &mut Self
+    where
+        I2: IntoIterator<Item = I>,
+        I: IntoIterator<Item: Into<StartupTreeNode>>;
+}
+
+impl AddStartupTree for App {
+    fn add_startup_tree<I2, I>(&mut self, startup_tree: I2) -> &mut Self
+    where
+        I2: IntoIterator<Item = I>,
+        I: IntoIterator<Item: Into<StartupTreeNode>>,
+    {
+        self.add_startup_tree_with(StartupTreeBuilder::new(startup_tree))
+    }
+
+    fn add_startup_tree_from<I2, I>(&mut self, factory: impl FnOnce() -> I2) -> &mut Self
+    where
+        I2: IntoIterator<Item = I>,
+        I: IntoIterator<Item: Into<StartupTreeNode>>,
+    {
+        self.add_startup_tree(factory())
+    }
+
+    fn add_startup_tree_with(&mut self, builder: StartupTreeBuilder) -> &mut Self {
+        builder.insert(self);
+        self
+    }
+
+    fn add_startup_tree_to_schedule<I2, I>(
+        &mut self,
+        schedule: impl ScheduleLabel,
+        startup_tree: I2,
+    ) -> &mut Self
+    where
+        I2: IntoIterator<Item = I>,
+        I: IntoIterator<Item: Into<StartupTreeNode>>,
+    {
+        self.add_startup_tree_with(StartupTreeBuilder::new(startup_tree).in_schedule(schedule))
+    }
+
+    fn add_teardown_tree_to_schedule<I2, I>(
+        &mut self,
+        schedule: impl ScheduleLabel,
+        startup_tree: I2,
+    ) -> &mut Self
+    where
+        I2: IntoIterator<Item = I>,
+        I: IntoIterator<Item: Into<StartupTreeNode>>,
+    {
+        self.add_startup_tree_with(
+            StartupTreeBuilder::new(startup_tree).in_schedule(schedule).reversed(),
+        )
+    }
+
+    fn add_startup_tree_once_on_enter<S, I2, I>(&mut self, state: S, startup_tree: I2) -> &mut Self
+    where
+        S: States,
+        I2: IntoIterator<Item = I>,
+        I: IntoIterator<Item: Into<StartupTreeNode>>,
+    {
+        self.add_startup_tree_with(
+            StartupTreeBuilder::new(startup_tree).in_schedule(OnEnter(state)).run_once(),
+        )
+    }
+
+    fn add_startup_tree_in_state<S, I2, I>(&mut self, state: S, startup_tree: I2) -> &mut Self
+    where
+        S: States,
+        I2: IntoIterator<Item = I>,
+        I: IntoIterator<Item: Into<StartupTreeNode>>,
+    {
+        self.add_startup_tree_with(StartupTreeBuilder::new(startup_tree).in_state(state))
+    }
+
+    fn add_scoped_startup_tree<I2, I>(&mut self, scope: &'static str, startup_tree: I2) -> &mut Self
+    where
+        I2: IntoIterator<Item = I>,
+        I: IntoIterator<Item: Into<StartupTreeNode>>,
+    {
+        self.add_startup_tree_with(StartupTreeBuilder::new(startup_tree).scope(scope))
+    }
+
+    fn add_startup_tree_ambiguous_with<M, I2, I>(
+        &mut self,
+        set: impl IntoSystemSet<M>,
+        startup_tree: I2,
+    ) -> &mut Self
+    where
+        I2: IntoIterator<Item = I>,
+        I: IntoIterator<Item: Into<StartupTreeNode>>,
+    {
+        self.add_startup_tree_with(StartupTreeBuilder::new(startup_tree).ambiguous_with(set))
+    }
+
+    fn add_startup_tree_with_min_threads<I2, I>(
+        &mut self,
+        min_threads: usize,
+        startup_tree: I2,
+    ) -> &mut Self
+    where
+        I2: IntoIterator<Item = I>,
+        I: IntoIterator<Item: Into<StartupTreeNode>>,
+    {
+        if !self.is_plugin_added::<TaskPoolPlugin>() {
+            let mut task_pool_options = TaskPoolOptions::default();
+            let compute = &mut task_pool_options.compute;
+            compute.min_threads = min_threads.max(compute.min_threads);
+            self.add_plugins(TaskPoolPlugin { task_pool_options });
+        }
+        self.add_startup_tree(startup_tree)
+    }
+
+    fn add_startup_tree_with_sets<S, I2, I>(
+        &mut self,
+        sets: impl Fn(usize) -> S + 'static,
+        startup_tree: I2,
+    ) -> &mut Self
+    where
+        S: SystemSet,
+        I2: IntoIterator<Item = I>,
+        I: IntoIterator<Item: Into<StartupTreeNode>>,
+    {
+        self.add_startup_tree_with(StartupTreeBuilder::new(startup_tree).layer_sets(sets))
+    }
+
+    fn add_startup_tree_timed<I2, I>(&mut self, startup_tree: I2) -> &mut Self
+    where
+        I2: IntoIterator<Item = I>,
+        I: IntoIterator<Item: Into<StartupTreeNode>>,
+    {
+        self.add_startup_tree_with(StartupTreeBuilder::new(startup_tree).timed())
+    }
+
+    fn add_startup_tree_reflected<I2, I>(&mut self, startup_tree: I2) -> &mut Self
+    where
+        I2: IntoIterator<Item = I>,
+        I: IntoIterator<Item: Into<StartupTreeNode>>,
+    {
+        self.add_startup_tree_with(StartupTreeBuilder::new(startup_tree).manifest())
+    }
+
+    fn add_startup_tree_flush_every<I2, I>(&mut self, n: usize, startup_tree: I2) -> &mut Self
+    where
+        I2: IntoIterator<Item = I>,
+        I: IntoIterator<Item: Into<StartupTreeNode>>,
+    {
+        self.add_startup_tree_with(StartupTreeBuilder::new(startup_tree).flush_every(n))
+    }
+
+    fn add_startup_tree_with_flush<M, I2, I>(
+        &mut self,
+        flush_system: impl IntoSystemConfigs<M> + Clone + 'static,
+        startup_tree: I2,
+    ) -> &mut Self
+    where
+        I2: IntoIterator<Item = I>,
+        I: IntoIterator<Item: Into<StartupTreeNode>>,
+    {
+        self.add_startup_tree_with(StartupTreeBuilder::new(startup_tree).flush_with(flush_system))
+    }
+
+    fn add_named_startup_tree<I2, I>(&mut self, name: &'static str, startup_tree: I2) -> &mut Self
+    where
+        I2: IntoIterator<Item = I>,
+        I: IntoIterator<Item: Into<StartupTreeNode>>,
+    {
+        let named_trees =
+            self.world_mut().get_resource_or_insert_with(StartupTreeRegistry::default);
+        assert!(
+            !named_trees.0.contains_key(name),
+            "a startup tree named \"{name}\" was already added to the app"
+        );
+        let handle = StartupTreeBuilder::new(startup_tree).insert(self);
+        self.world_mut().resource_mut::<StartupTreeRegistry>().0.insert(name, handle);
+        self
+    }
+
+    fn has_named_startup_tree(&self, name: &str) -> bool {
+        self.world().get_resource::<StartupTreeRegistry>().is_some_and(|trees| trees.contains(name))
+    }
+
+    fn extend_named_startup_tree<I2, I>(
+        &mut self,
+        name: &'static str,
+        startup_tree: I2,
+    ) -> &mut Self
+    where
+        I2: IntoIterator<Item = I>,
+        I: IntoIterator<Item: Into<StartupTreeNode>>,
+    {
+        let existing = self
+            .world_mut()
+            .get_resource_or_insert_with(StartupTreeRegistry::default)
+            .0
+            .remove(name);
+        let Some(mut handle) = existing else {
+            return self.add_named_startup_tree(name, startup_tree);
+        };
 
+        let builder = StartupTreeBuilder::new(startup_tree).in_schedule(handle.schedule());
+        let builder = handle.end().iter().fold(builder, |builder, &end| builder.after(end));
+        handle.extend(builder.insert(self));
+        self.world_mut().resource_mut::<StartupTreeRegistry>().0.insert(name, handle);
         self
     }
 }
@@ -204,9 +1552,13 @@ impl AddStartupTree for App {
 mod tests {
     use std::collections::HashSet;
 
-    use bevy::prelude::{App, Schedules, Startup};
+    use bevy::prelude::{
+        App, IntoSystemConfigs, Schedules, Startup, TaskPoolOptions, TaskPoolPlugin,
+    };
 
-    use crate::{rng::reset_rng, startup_tree, AddStartupTree};
+    use crate::{
+        rng::reset_rng, startup_tree, startup_tree_fn, startup_tree_layers, AddStartupTree,
+    };
 
     fn get_app_startup_tree_labels(app: &App) -> impl Iterator<Item = String> + '_ {
         let schedules = app.world().resource::<Schedules>();
@@ -249,8 +1601,11 @@ mod tests {
 
         let expected_labels = HashSet::from([
             "__startup_tree_zujxzB_layer_0".into(),
+            "__startup_tree_zujxzB_flush_0".into(),
             "__startup_tree_zujxzB_layer_1".into(),
+            "__startup_tree_zujxzB_flush_1".into(),
             "__startup_tree_zujxzB_layer_2".into(),
+            "__startup_tree_zujxzB_flush_2".into(),
         ]);
         let actual_labels = HashSet::from_iter(get_app_startup_tree_labels(&app));
         assert_eq!(actual_labels, expected_labels);
@@ -276,9 +1631,13 @@ mod tests {
 
         let expected_labels = HashSet::from([
             "__startup_tree_zujxzB_layer_0".into(),
+            "__startup_tree_zujxzB_flush_0".into(),
             "__startup_tree_zujxzB_layer_1".into(),
+            "__startup_tree_zujxzB_flush_1".into(),
             "__startup_tree_zujxzB_layer_2".into(),
+            "__startup_tree_zujxzB_flush_2".into(),
             "__startup_tree_zujxzB_layer_3".into(),
+            "__startup_tree_zujxzB_flush_3".into(),
         ]);
         let actual_labels = HashSet::from_iter(get_app_startup_tree_labels(&app));
         assert_eq!(actual_labels, expected_labels);
@@ -295,16 +1654,339 @@ mod tests {
 
         let expected_labels = HashSet::from([
             "__startup_tree_zujxzB_layer_0".into(),
+            "__startup_tree_zujxzB_flush_0".into(),
             "__startup_tree_ql3QHx_layer_0".into(),
+            "__startup_tree_ql3QHx_flush_0".into(),
         ]);
         let actual_labels = HashSet::from_iter(get_app_startup_tree_labels(&app));
         assert_eq!(actual_labels, expected_labels);
     }
 
+    #[test]
+    fn omits_flush_after_all_pure_layer() {
+        reset_rng();
+
+        let mut app = App::new();
+
+        app.add_startup_tree(startup_tree! {
+            pure system => system,
+            pure system => pure system,
+        });
+
+        let expected_labels = HashSet::from([
+            "__startup_tree_zujxzB_layer_0".into(),
+            "__startup_tree_zujxzB_layer_1".into(),
+            "__startup_tree_zujxzB_flush_1".into(),
+        ]);
+        let actual_labels = HashSet::from_iter(get_app_startup_tree_labels(&app));
+        assert_eq!(actual_labels, expected_labels);
+    }
+
+    #[test]
+    fn adds_targeted_flush_for_flush_after_node() {
+        reset_rng();
+
+        let mut app = App::new();
+
+        app.add_startup_tree(startup_tree! {
+            pure system => system,
+            flush_after system,
+        });
+
+        let expected_labels = HashSet::from([
+            "__startup_tree_zujxzB_layer_0".into(),
+            "__startup_tree_zujxzB_flush_0_1".into(),
+            "__startup_tree_zujxzB_layer_1".into(),
+            "__startup_tree_zujxzB_flush_1".into(),
+        ]);
+        let actual_labels = HashSet::from_iter(get_app_startup_tree_labels(&app));
+        assert_eq!(actual_labels, expected_labels);
+    }
+
+    #[test]
+    fn adds_flush_only_layer_for_placeholder() {
+        reset_rng();
+
+        let mut app = App::new();
+
+        app.add_startup_tree(startup_tree! { system => _ => system });
+
+        let expected_labels = HashSet::from([
+            "__startup_tree_zujxzB_layer_0".into(),
+            "__startup_tree_zujxzB_flush_0".into(),
+            "__startup_tree_zujxzB_layer_1".into(),
+            "__startup_tree_zujxzB_flush_1".into(),
+            "__startup_tree_zujxzB_layer_2".into(),
+            "__startup_tree_zujxzB_flush_2".into(),
+        ]);
+        let actual_labels = HashSet::from_iter(get_app_startup_tree_labels(&app));
+        assert_eq!(actual_labels, expected_labels);
+    }
+
+    #[test]
+    fn keeps_cfg_arrow_when_predicate_is_true() {
+        reset_rng();
+
+        let mut app = App::new();
+
+        app.add_startup_tree(startup_tree! { system #[cfg(all())] => system });
+
+        let expected_labels = HashSet::from([
+            "__startup_tree_zujxzB_layer_0".into(),
+            "__startup_tree_zujxzB_flush_0".into(),
+            "__startup_tree_zujxzB_layer_1".into(),
+            "__startup_tree_zujxzB_flush_1".into(),
+        ]);
+        let actual_labels = HashSet::from_iter(get_app_startup_tree_labels(&app));
+        assert_eq!(actual_labels, expected_labels);
+    }
+
+    #[test]
+    fn flattens_cfg_arrow_when_predicate_is_false() {
+        reset_rng();
+
+        let mut app = App::new();
+
+        app.add_startup_tree(startup_tree! { system #[cfg(any())] => system });
+
+        let expected_labels = HashSet::from([
+            "__startup_tree_zujxzB_layer_0".into(),
+            "__startup_tree_zujxzB_flush_0".into(),
+        ]);
+        let actual_labels = HashSet::from_iter(get_app_startup_tree_labels(&app));
+        assert_eq!(actual_labels, expected_labels);
+    }
+
+    #[test]
+    fn omits_cfg_branch_when_predicate_is_false() {
+        reset_rng();
+
+        let mut app = App::new();
+
+        app.add_startup_tree(startup_tree! {
+            #[cfg(any())] system => system => system,
+            system,
+        });
+        app.add_startup_tree(startup_tree! { #[cfg(any())] system });
+
+        let expected_labels = HashSet::from([
+            "__startup_tree_zujxzB_layer_0".into(),
+            "__startup_tree_zujxzB_flush_0".into(),
+        ]);
+        let actual_labels = HashSet::from_iter(get_app_startup_tree_labels(&app));
+        assert_eq!(actual_labels, expected_labels);
+    }
+
+    #[test]
+    fn uses_layer_names_in_labels() {
+        reset_rng();
+
+        let mut app = App::new();
+
+        app.add_startup_tree(startup_tree! {
+            "load": system => system => "build": system,
+            system,
+        });
+
+        let expected_labels = HashSet::from([
+            "__startup_tree_zujxzB_load".into(),
+            "__startup_tree_zujxzB_flush_0".into(),
+            "__startup_tree_zujxzB_layer_1".into(),
+            "__startup_tree_zujxzB_flush_1".into(),
+            "__startup_tree_zujxzB_build".into(),
+            "__startup_tree_zujxzB_flush_2".into(),
+        ]);
+        let actual_labels = HashSet::from_iter(get_app_startup_tree_labels(&app));
+        assert_eq!(actual_labels, expected_labels);
+    }
+
+    fn generic_system<T>() {}
+
+    #[test]
+    fn adds_generic_systems() {
+        reset_rng();
+
+        let mut app = App::new();
+
+        app.add_startup_tree(startup_tree! {
+            generic_system::<u8> => generic_system::<Vec<u16>>,
+        });
+
+        let expected_labels = HashSet::from([
+            "__startup_tree_zujxzB_layer_0".into(),
+            "__startup_tree_zujxzB_flush_0".into(),
+            "__startup_tree_zujxzB_layer_1".into(),
+            "__startup_tree_zujxzB_flush_1".into(),
+        ]);
+        let actual_labels = HashSet::from_iter(get_app_startup_tree_labels(&app));
+        assert_eq!(actual_labels, expected_labels);
+    }
+
+    #[test]
+    fn adds_tree_from_array_layers() {
+        reset_rng();
+
+        let mut app = App::new();
+
+        app.add_startup_tree(startup_tree_layers([
+            (system, system).into_configs(),
+            system.into_configs(),
+        ]));
+
+        let expected_labels = HashSet::from([
+            "__startup_tree_zujxzB_layer_0".into(),
+            "__startup_tree_zujxzB_flush_0".into(),
+            "__startup_tree_zujxzB_layer_1".into(),
+            "__startup_tree_zujxzB_flush_1".into(),
+        ]);
+        let actual_labels = HashSet::from_iter(get_app_startup_tree_labels(&app));
+        assert_eq!(actual_labels, expected_labels);
+    }
+
+    #[cfg(feature = "file")]
+    #[test]
+    fn adds_tree_from_file() {
+        use crate::startup_tree_from_file;
+
+        reset_rng();
+
+        let mut app = App::new();
+
+        app.add_startup_tree(startup_tree_from_file!("tests/trees/startup.ron"));
+
+        let expected_labels = HashSet::from([
+            "__startup_tree_zujxzB_layer_0".into(),
+            "__startup_tree_zujxzB_flush_0".into(),
+            "__startup_tree_zujxzB_layer_1".into(),
+            "__startup_tree_zujxzB_flush_1".into(),
+            "__startup_tree_zujxzB_layer_2".into(),
+            "__startup_tree_zujxzB_flush_2".into(),
+        ]);
+        let actual_labels = HashSet::from_iter(get_app_startup_tree_labels(&app));
+        assert_eq!(actual_labels, expected_labels);
+    }
+
+    #[test]
+    fn startup_tree_fn_can_be_added_to_multiple_apps() {
+        reset_rng();
+
+        startup_tree_fn!(shared_tree => { system => system });
+
+        let mut app_1 = App::new();
+        app_1.add_startup_tree(shared_tree());
+        let mut app_2 = App::new();
+        app_2.add_startup_tree(shared_tree());
+
+        let expected_labels_1 = HashSet::from([
+            "__startup_tree_zujxzB_layer_0".into(),
+            "__startup_tree_zujxzB_flush_0".into(),
+            "__startup_tree_zujxzB_layer_1".into(),
+            "__startup_tree_zujxzB_flush_1".into(),
+        ]);
+        let expected_labels_2 = HashSet::from([
+            "__startup_tree_ql3QHx_layer_0".into(),
+            "__startup_tree_ql3QHx_flush_0".into(),
+            "__startup_tree_ql3QHx_layer_1".into(),
+            "__startup_tree_ql3QHx_flush_1".into(),
+        ]);
+        assert_eq!(HashSet::from_iter(get_app_startup_tree_labels(&app_1)), expected_labels_1);
+        assert_eq!(HashSet::from_iter(get_app_startup_tree_labels(&app_2)), expected_labels_2);
+    }
+
+    #[test]
+    fn adds_fresh_tree_from_factory_to_each_app() {
+        reset_rng();
+
+        startup_tree_fn!(shared_tree => { system => system });
+
+        let apps: Vec<App> = (0..3)
+            .map(|_| {
+                let mut app = App::new();
+                app.add_startup_tree_from(shared_tree);
+                app
+            })
+            .collect();
+
+        for (app, namespace) in apps.iter().zip(["zujxzB", "ql3QHx", "ENyynv"]) {
+            let expected_labels = HashSet::from([
+                format!("__startup_tree_{namespace}_layer_0"),
+                format!("__startup_tree_{namespace}_flush_0"),
+                format!("__startup_tree_{namespace}_layer_1"),
+                format!("__startup_tree_{namespace}_flush_1"),
+            ]);
+            assert_eq!(HashSet::from_iter(get_app_startup_tree_labels(app)), expected_labels);
+        }
+    }
+
+    #[test]
+    fn adds_task_pool_plugin_for_min_threads() {
+        let mut app = App::new();
+
+        app.add_startup_tree_with_min_threads(4, startup_tree! { system });
+
+        let plugins = app.get_added_plugins::<TaskPoolPlugin>();
+        assert_eq!(plugins[0].task_pool_options.compute.min_threads, 4);
+    }
+
+    #[test]
+    fn min_threads_doesnt_lower_default_minimum() {
+        let mut app = App::new();
+
+        app.add_startup_tree_with_min_threads(0, startup_tree! { system });
+
+        let plugins = app.get_added_plugins::<TaskPoolPlugin>();
+        let default_min_threads = TaskPoolOptions::default().compute.min_threads;
+        assert_eq!(plugins[0].task_pool_options.compute.min_threads, default_min_threads);
+        assert!(default_min_threads > 0);
+    }
+
+    #[test]
+    fn min_threads_keeps_existing_task_pool_plugin() {
+        let mut app = App::new();
+        app.add_plugins(TaskPoolPlugin::default());
+
+        app.add_startup_tree_with_min_threads(4, startup_tree! { system });
+
+        assert_eq!(app.get_added_plugins::<TaskPoolPlugin>().len(), 1);
+    }
+
+    mod hygiene {
+        #![allow(dead_code, unused_macros)]
+
+        use crate::{startup_tree, startup_tree_fn};
+
+        struct Tree;
+        struct Node;
+        struct StartupTreeNode;
+        struct SystemConfigs;
+
+        macro_rules! vec {
+            ($($tokens:tt)*) => {
+                compile_error!("the expansion used a `vec!` macro from the call site")
+            };
+        }
+
+        fn system() {}
+
+        startup_tree_fn!(annotated_tree => { "named": system => pure system, _ });
+
+        #[test]
+        fn expands_with_conflicting_names_in_scope() {
+            let tree = startup_tree! { system => { async system, flush_after system } };
+            assert_eq!(tree.len(), 2);
+            assert_eq!(annotated_tree().len(), 2);
+        }
+    }
+
     mod e2e {
-        use bevy::prelude::*;
+        use bevy::{
+            ecs::schedule::{ScheduleBuildSettings, SystemConfigs},
+            prelude::*,
+        };
 
-        use crate::{rng::reseed_rng, startup_tree, AddStartupTree};
+        use crate::{
+            rng::reseed_rng, startup_teardown_tree, startup_tree, startup_tree_sets, AddStartupTree,
+        };
 
         #[derive(Resource, Debug)]
         struct TestEventData(Vec<TestEvent>);
@@ -379,5 +2061,651 @@ mod tests {
                 ]
             );
         }
+
+        #[test]
+        fn fan_in_node_runs_after_all_other_nodes() {
+            reseed_rng();
+
+            let mut app = App::new();
+            app.add_plugins(TaskPoolPlugin::default());
+            app.insert_non_send_resource(TestEventData(Vec::with_capacity(5)));
+            app.add_startup_tree(startup_tree! {
+                sys_1_a,
+                sys_1_b => sys_2_a,
+                sys_1_c
+                =>> end
+            });
+
+            app.update();
+
+            assert_eq!(
+                app.world().non_send_resource::<TestEventData>().0,
+                &[TestEvent::One, TestEvent::One, TestEvent::One, TestEvent::Two, TestEvent::End]
+            );
+        }
+
+        #[test]
+        fn pipe_passes_outputs_between_systems() {
+            fn produce() -> u32 {
+                2
+            }
+
+            fn record(In(n): In<u32>, mut data: NonSendMut<TestEventData>) {
+                data.0.extend((0..n).map(|_| TestEvent::Two));
+            }
+
+            reseed_rng();
+
+            let mut app = App::new();
+            app.add_plugins(TaskPoolPlugin::default());
+            app.insert_non_send_resource(TestEventData(Vec::with_capacity(4)));
+            app.add_startup_tree(startup_tree! {
+                sys_1_a => pipe { produce => record } => end,
+            });
+
+            app.update();
+
+            assert_eq!(
+                app.world().non_send_resource::<TestEventData>().0,
+                &[TestEvent::One, TestEvent::Two, TestEvent::Two, TestEvent::End]
+            );
+        }
+
+        #[test]
+        fn teardown_tree_runs_deepest_layer_first() {
+            reseed_rng();
+
+            let mut app = App::new();
+            app.add_plugins(TaskPoolPlugin::default());
+            app.insert_non_send_resource(TestEventData(Vec::with_capacity(4)));
+            app.add_teardown_tree_to_schedule(
+                Update,
+                startup_tree! {
+                    sys_1_a => { sys_2_a => sys_3_a, sys_2_b },
+                },
+            );
+
+            app.update();
+
+            assert_eq!(
+                app.world().non_send_resource::<TestEventData>().0,
+                &[TestEvent::Three, TestEvent::Two, TestEvent::Two, TestEvent::One]
+            );
+        }
+
+        fn group_1() -> SystemConfigs {
+            (sys_1_a, sys_1_b).into_configs()
+        }
+
+        #[test]
+        fn system_group_is_one_node() {
+            reseed_rng();
+
+            let mut app = App::new();
+            app.add_plugins(TaskPoolPlugin::default());
+            app.insert_non_send_resource(TestEventData(Vec::with_capacity(5)));
+            let tree = startup_tree! {
+                group_1() => (sys_2_a, sys_2_b) => sys_3_a,
+            };
+            assert_eq!(tree.iter().map(Vec::len).collect::<Vec<_>>(), [1, 1, 1]);
+            app.add_startup_tree(tree);
+
+            app.update();
+
+            assert_eq!(
+                app.world().non_send_resource::<TestEventData>().0,
+                &[TestEvent::One, TestEvent::One, TestEvent::Two, TestEvent::Two, TestEvent::Three]
+            );
+        }
+
+        #[test]
+        fn startup_tree_sets_order_against_layers() {
+            startup_tree_sets!(Layers => {
+                sys_1_a => sys_2_a => sys_3_a,
+            });
+
+            reseed_rng();
+
+            let mut app = App::new();
+            app.add_plugins(TaskPoolPlugin::default());
+            app.insert_non_send_resource(TestEventData(Vec::with_capacity(4)));
+            app.add_startup_tree(Layers::tree());
+            app.add_systems(Startup, end.after(Layers::L1).before(Layers::L2));
+
+            app.update();
+
+            assert_eq!(Layers::LAYERS, [Layers::L0, Layers::L1, Layers::L2]);
+            assert_eq!(
+                app.world().non_send_resource::<TestEventData>().0,
+                &[TestEvent::One, TestEvent::Two, TestEvent::End, TestEvent::Three]
+            );
+        }
+
+        #[test]
+        fn startup_teardown_tree_tears_down_in_reverse() {
+            reseed_rng();
+
+            let mut app = App::new();
+            app.add_plugins(TaskPoolPlugin::default());
+            app.insert_non_send_resource(TestEventData(Vec::with_capacity(5)));
+            let (startup, teardown) = startup_teardown_tree! {
+                sys_1_a / sys_1_b => sys_2_a / sys_2_b => sys_3_a,
+            };
+            app.add_startup_tree(startup).add_teardown_tree_to_schedule(Update, teardown);
+
+            app.update();
+
+            assert_eq!(
+                app.world().non_send_resource::<TestEventData>().0,
+                &[TestEvent::One, TestEvent::Two, TestEvent::Three, TestEvent::Two, TestEvent::One]
+            );
+        }
+
+        #[test]
+        fn once_on_enter_tree_only_runs_on_first_entry() {
+            use bevy_state::{
+                app::{AppExtStates, StatesPlugin},
+                state::{NextState, States},
+            };
+
+            #[derive(States, Clone, Debug, Default, PartialEq, Eq, Hash)]
+            enum GameState {
+                #[default]
+                Menu,
+                InGame,
+            }
+
+            reseed_rng();
+
+            let mut app = App::new();
+            app.add_plugins((TaskPoolPlugin::default(), StatesPlugin));
+            app.init_state::<GameState>();
+            app.insert_non_send_resource(TestEventData(Vec::with_capacity(4)));
+            app.add_startup_tree_once_on_enter(
+                GameState::InGame,
+                startup_tree! {
+                    sys_1_a => sys_2_a,
+                },
+            );
+
+            for state in [GameState::InGame, GameState::Menu, GameState::InGame] {
+                app.world_mut().resource_mut::<NextState<GameState>>().set(state);
+                app.update();
+            }
+
+            assert_eq!(
+                app.world().non_send_resource::<TestEventData>().0,
+                &[TestEvent::One, TestEvent::Two]
+            );
+        }
+
+        #[test]
+        fn in_state_tree_only_runs_in_state() {
+            use bevy_state::{
+                app::{AppExtStates, StatesPlugin},
+                state::States,
+            };
+
+            #[derive(States, Clone, Debug, Default, PartialEq, Eq, Hash)]
+            enum GameState {
+                #[default]
+                Menu,
+                InGame,
+            }
+
+            reseed_rng();
+
+            let mut app = App::new();
+            app.add_plugins((TaskPoolPlugin::default(), StatesPlugin));
+            app.init_state::<GameState>();
+            app.insert_non_send_resource(TestEventData(Vec::with_capacity(2)));
+            app.add_startup_tree_in_state(GameState::InGame, startup_tree! { sys_1_a => sys_1_b });
+            app.add_startup_tree_in_state(GameState::Menu, startup_tree! { sys_2_a => sys_2_b });
+
+            app.update();
+
+            assert_eq!(
+                app.world().non_send_resource::<TestEventData>().0,
+                &[TestEvent::Two, TestEvent::Two]
+            );
+        }
+
+        #[test]
+        fn pipe_accepts_closures() {
+            fn produce() -> u32 {
+                1
+            }
+
+            reseed_rng();
+
+            let mut app = App::new();
+            app.add_plugins(TaskPoolPlugin::default());
+            app.insert_non_send_resource(TestEventData(Vec::with_capacity(4)));
+            let extra = 1;
+            app.add_startup_tree(startup_tree! {
+                sys_1_a => pipe {
+                    produce
+                    => move |In(n): In<u32>| n + extra
+                    => |In(n): In<u32>, mut data: NonSendMut<TestEventData>| {
+                        data.0.extend((0..n).map(|_| TestEvent::Two));
+                    }
+                } => end,
+            });
+
+            app.update();
+
+            assert_eq!(
+                app.world().non_send_resource::<TestEventData>().0,
+                &[TestEvent::One, TestEvent::Two, TestEvent::Two, TestEvent::End]
+            );
+        }
+
+        #[test]
+        fn pipe_accepts_generic_systems() {
+            fn produce() -> u32 {
+                1
+            }
+
+            fn times<const N: u32>(In(n): In<u32>) -> u32 {
+                n * N
+            }
+
+            fn record(In(n): In<u32>, mut data: NonSendMut<TestEventData>) {
+                data.0.extend((0..n).map(|_| TestEvent::Two));
+            }
+
+            reseed_rng();
+
+            let mut app = App::new();
+            app.add_plugins(TaskPoolPlugin::default());
+            app.insert_non_send_resource(TestEventData(Vec::with_capacity(4)));
+            app.add_startup_tree(startup_tree! {
+                sys_1_a => pipe { produce => times::<2> => record } => end,
+            });
+
+            app.update();
+
+            assert_eq!(
+                app.world().non_send_resource::<TestEventData>().0,
+                &[TestEvent::One, TestEvent::Two, TestEvent::Two, TestEvent::End]
+            );
+        }
+
+        #[test]
+        fn pipe_continues_after_failed_optional_step() {
+            fn produce() -> u32 {
+                2
+            }
+
+            fn fail(In(_): In<u32>) -> Result<u32, &'static str> {
+                Err("not today")
+            }
+
+            fn triple(In(n): In<u32>) -> Result<u32, &'static str> {
+                Ok(n * 3)
+            }
+
+            fn record(In(n): In<u32>, mut data: NonSendMut<TestEventData>) {
+                data.0.extend((0..n).map(|_| TestEvent::Two));
+            }
+
+            reseed_rng();
+
+            let mut app = App::new();
+            app.add_plugins(TaskPoolPlugin::default());
+            app.insert_non_send_resource(TestEventData(Vec::with_capacity(8)));
+            app.add_startup_tree(startup_tree! {
+                sys_1_a => pipe { produce =?> fail =?> triple => record } => end,
+            });
+
+            app.update();
+
+            assert_eq!(
+                app.world().non_send_resource::<TestEventData>().0,
+                &[
+                    TestEvent::One,
+                    TestEvent::Two,
+                    TestEvent::Two,
+                    TestEvent::Two,
+                    TestEvent::Two,
+                    TestEvent::Two,
+                    TestEvent::Two,
+                    TestEvent::End
+                ]
+            );
+        }
+
+        #[test]
+        fn spliced_subtree_matches_inline_tree() {
+            use crate::{describe_layers, startup_tree_fn};
+
+            startup_tree_fn!(fragment => { sys_2_a => sys_3_a });
+
+            let spliced = startup_tree! { sys_1_a => @fragment(), sys_1_b => @fragment() };
+            let inline =
+                startup_tree! { sys_1_a => sys_2_a => sys_3_a, sys_1_b => sys_2_a => sys_3_a };
+            assert_eq!(describe_layers(&spliced), describe_layers(&inline));
+
+            reseed_rng();
+
+            let mut app = App::new();
+            app.add_plugins(TaskPoolPlugin::default());
+            app.insert_non_send_resource(TestEventData(Vec::with_capacity(6)));
+            app.add_startup_tree(spliced);
+
+            app.update();
+
+            assert_eq!(
+                app.world().non_send_resource::<TestEventData>().0,
+                &[
+                    TestEvent::One,
+                    TestEvent::One,
+                    TestEvent::Two,
+                    TestEvent::Two,
+                    TestEvent::Three,
+                    TestEvent::Three
+                ]
+            );
+        }
+
+        #[test]
+        fn group_runs_successor_after_all_nodes() {
+            reseed_rng();
+
+            let mut app = App::new();
+            app.add_plugins(TaskPoolPlugin::default());
+            app.insert_non_send_resource(TestEventData(Vec::with_capacity(4)));
+            app.add_startup_tree(startup_tree! {
+                { sys_1_a => sys_2_a, sys_1_b } => end,
+            });
+
+            app.update();
+
+            assert_eq!(
+                app.world().non_send_resource::<TestEventData>().0,
+                &[TestEvent::One, TestEvent::One, TestEvent::Two, TestEvent::End]
+            );
+        }
+
+        #[test]
+        fn each_suffix_applies_to_every_node() {
+            reseed_rng();
+
+            let mut app = App::new();
+            app.add_plugins(TaskPoolPlugin::default());
+            app.insert_non_send_resource(TestEventData(Vec::new()));
+            app.add_startup_tree(startup_tree! {
+                each: .run_if(|| false);
+                sys_1_a => sys_2_a,
+                async sys_1_b,
+            });
+
+            app.update();
+
+            assert!(app.world().non_send_resource::<TestEventData>().0.is_empty());
+        }
+
+        #[test]
+        fn labeled_nodes_order_systems_within_layer() {
+            reseed_rng();
+
+            let mut app = App::new();
+            app.add_plugins(TaskPoolPlugin::default());
+            app.insert_non_send_resource(TestEventData(Vec::with_capacity(4)));
+            app.add_startup_tree(startup_tree! {
+                sys_1_a.after(two) => end,
+                sys_2_a as two,
+                begin.before(two),
+            });
+
+            app.update();
+
+            assert_eq!(
+                app.world().non_send_resource::<TestEventData>().0,
+                &[TestEvent::Begin, TestEvent::Two, TestEvent::One, TestEvent::End]
+            );
+        }
+
+        #[test]
+        fn labeled_nodes_order_systems_across_branches() {
+            reseed_rng();
+
+            let mut app = App::new();
+            app.add_plugins(TaskPoolPlugin::default());
+            app.insert_non_send_resource(TestEventData(Vec::with_capacity(4)));
+            app.add_startup_tree(startup_tree! {
+                sys_1_a => sys_3_a.after(two),
+                sys_1_b => sys_2_a as two,
+            });
+
+            app.update();
+
+            assert_eq!(
+                app.world().non_send_resource::<TestEventData>().0,
+                &[TestEvent::One, TestEvent::One, TestEvent::Two, TestEvent::Three]
+            );
+        }
+
+        #[test]
+        fn finalizer_runs_in_new_last_layer() {
+            use crate::startup_tree_with_finalizer;
+
+            reseed_rng();
+
+            let tree = startup_tree! {
+                sys_1_a => sys_2_a,
+                sys_1_b,
+            };
+            let tree = startup_tree_with_finalizer(tree, end);
+            assert_eq!(tree.iter().map(Vec::len).collect::<Vec<_>>(), [2, 1, 1]);
+
+            let mut app = App::new();
+            app.add_plugins(TaskPoolPlugin::default());
+            app.insert_non_send_resource(TestEventData(Vec::with_capacity(4)));
+            app.add_startup_tree(tree);
+
+            app.update();
+
+            assert_eq!(
+                app.world().non_send_resource::<TestEventData>().0,
+                &[TestEvent::One, TestEvent::One, TestEvent::Two, TestEvent::End]
+            );
+        }
+
+        #[test]
+        fn cfg_branches_select_alternative_trees() {
+            reseed_rng();
+
+            let mut app = App::new();
+            app.add_plugins(TaskPoolPlugin::default());
+            app.insert_non_send_resource(TestEventData(Vec::with_capacity(3)));
+            app.add_startup_tree(startup_tree! {
+                #[cfg(test)] sys_1_a => sys_2_a => end,
+                #[cfg(not(test))] sys_3_a => end,
+            });
+
+            app.update();
+
+            assert_eq!(
+                app.world().non_send_resource::<TestEventData>().0,
+                &[TestEvent::One, TestEvent::Two, TestEvent::End]
+            );
+        }
+
+        #[derive(SystemSet, Clone, PartialEq, Eq, Hash, Debug)]
+        struct OneSet;
+
+        #[test]
+        fn node_sets_order_systems_outside_of_tree() {
+            reseed_rng();
+
+            let mut app = App::new();
+            app.add_plugins(TaskPoolPlugin::default());
+            app.insert_non_send_resource(TestEventData(Vec::with_capacity(3)));
+            app.add_startup_tree(startup_tree! {
+                sys_1_a in OneSet => end,
+            });
+            app.add_systems(Startup, begin.before(OneSet));
+
+            app.update();
+
+            assert_eq!(
+                app.world().non_send_resource::<TestEventData>().0,
+                &[TestEvent::Begin, TestEvent::One, TestEvent::End]
+            );
+        }
+
+        #[test]
+        fn pinned_depths_align_branches() {
+            reseed_rng();
+
+            let mut app = App::new();
+            app.add_plugins(TaskPoolPlugin::default());
+            app.insert_non_send_resource(TestEventData(Vec::with_capacity(4)));
+            app.add_startup_tree(startup_tree! {
+                begin => @depth(3) end,
+                @depth(1) sys_1_a => sys_2_a,
+            });
+
+            app.update();
+
+            assert_eq!(
+                app.world().non_send_resource::<TestEventData>().0,
+                &[TestEvent::Begin, TestEvent::One, TestEvent::Two, TestEvent::End]
+            );
+        }
+
+        #[derive(Resource)]
+        struct Marker;
+
+        #[test]
+        fn flush_applies_commands_before_next_depth() {
+            fn insert_marker(mut commands: Commands) {
+                commands.insert_resource(Marker);
+            }
+
+            fn read_marker(_marker: Res<Marker>, mut data: NonSendMut<TestEventData>) {
+                data.0.push(TestEvent::Two);
+            }
+
+            reseed_rng();
+
+            let mut app = App::new();
+            app.add_plugins(TaskPoolPlugin::default());
+            app.insert_non_send_resource(TestEventData(Vec::with_capacity(3)));
+            // Only the tree's own flushes may apply the commands
+            app.edit_schedule(Startup, |schedule| {
+                schedule.set_build_settings(ScheduleBuildSettings {
+                    auto_insert_apply_deferred: false,
+                    ..default()
+                });
+            });
+            app.add_startup_tree(startup_tree! {
+                insert_marker => read_marker => end,
+            });
+
+            app.update();
+
+            assert_eq!(
+                app.world().non_send_resource::<TestEventData>().0,
+                &[TestEvent::Two, TestEvent::End]
+            );
+        }
+
+        #[derive(Component)]
+        struct Enemy;
+
+        struct EnemyConfig {
+            count: usize,
+        }
+
+        impl EnemyConfig {
+            fn spawn_enemies(&self) -> impl FnMut(Commands) + Send + Sync + 'static {
+                let count = self.count;
+                move |mut commands| {
+                    for _ in 0..count {
+                        commands.spawn(Enemy);
+                    }
+                }
+            }
+
+            fn check_enemies(
+                &self,
+            ) -> impl FnMut(Query<&Enemy>, NonSendMut<TestEventData>) + Send + Sync + 'static
+            {
+                let count = self.count;
+                move |q_enemies, mut data| {
+                    assert_eq!(q_enemies.iter().count(), count);
+                    data.0.push(TestEvent::Two);
+                }
+            }
+        }
+
+        #[test]
+        fn method_call_nodes_create_systems_from_factory() {
+            reseed_rng();
+
+            let mut app = App::new();
+            app.add_plugins(TaskPoolPlugin::default());
+            app.insert_non_send_resource(TestEventData(Vec::with_capacity(2)));
+            // The systems are created with the tree, the factory isn't needed to run them
+            let tree = {
+                let config = EnemyConfig { count: 3 };
+                startup_tree! { config.spawn_enemies() => config.check_enemies() => end }
+            };
+            app.add_startup_tree(tree);
+
+            app.update();
+
+            assert_eq!(
+                app.world().non_send_resource::<TestEventData>().0,
+                &[TestEvent::Two, TestEvent::End]
+            );
+        }
+
+        #[derive(Component)]
+        struct MenuLevel(u32);
+
+        fn spawn_menu_root(mut commands: Commands) {
+            commands.spawn(MenuLevel(0));
+        }
+
+        fn spawn_menu_children(mut commands: Commands, q_levels: Query<(Entity, &MenuLevel)>) {
+            let Some((parent, level)) = q_levels.iter().max_by_key(|(_, level)| level.0) else {
+                return;
+            };
+            let child_level = MenuLevel(level.0 + 1);
+            commands.entity(parent).with_children(|parent| {
+                parent.spawn(child_level);
+            });
+        }
+
+        #[test]
+        fn deep_tree_spawns_nested_hierarchy() {
+            reseed_rng();
+
+            let mut app = App::new();
+            app.add_plugins(TaskPoolPlugin::default());
+            app.add_startup_tree(startup_tree! {
+                spawn_menu_root
+                    => spawn_menu_children
+                    => spawn_menu_children
+                    => spawn_menu_children
+                    => spawn_menu_children
+                    => spawn_menu_children
+            });
+
+            app.update();
+
+            let world = app.world_mut();
+            let mut q_leaf = world.query_filtered::<Entity, Without<Children>>();
+            let leaf = q_leaf.single(world);
+            let mut q_parent = world.query::<&Parent>();
+            let hierarchy_depth = std::iter::successors(Some(leaf), |&e| {
+                q_parent.get(world, e).ok().map(|p| p.get())
+            })
+            .count();
+            assert_eq!(hierarchy_depth, 6);
+        }
     }
 }
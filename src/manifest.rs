@@ -0,0 +1,113 @@
+use std::borrow::Cow;
+
+use bevy_ecs::{schedule::Schedule, system::Resource};
+
+use crate::StartupTreeLayer;
+
+/// The systems of the startup trees with a manifest and the layers they run in.
+///
+/// Only trees that were inserted with
+/// [`add_startup_tree_reflected`](crate::AddStartupTree::add_startup_tree_reflected) or a
+/// [`StartupTreeBuilder`](crate::StartupTreeBuilder) with
+/// [`manifest`](crate::StartupTreeBuilder::manifest) are recorded. The systems are recorded when
+/// the tree is inserted, so the manifest describes the startup graph before it runs, e.g. for an
+/// in-game inspector.
+///
+/// ```rust no_run
+/// # use bevy::prelude::*;
+/// # use bevy_startup_tree::{startup_tree, AddStartupTree, StartupTreeManifest};
+/// # fn sys_1() {}
+/// # fn sys_2() {}
+/// fn inspect(manifest: Res<StartupTreeManifest>) {
+///     for (name, layer) in manifest.iter() {
+///         info!("{name} runs at depth {}", layer.index());
+///     }
+/// }
+///
+/// App::new()
+///     .add_startup_tree_reflected(startup_tree! { sys_1 => sys_2 })
+///     .add_systems(Update, inspect);
+/// ```
+#[derive(Resource, Debug, Default)]
+pub struct StartupTreeManifest {
+    systems: Vec<(Cow<'static, str>, StartupTreeLayer)>,
+}
+
+impl StartupTreeManifest {
+    /// The depth of the first recorded system named `name`, if any.
+    ///
+    /// System names are the type names of the systems, e.g. `my_game::setup::spawn_world`.
+    pub fn depth(&self, name: &str) -> Option<usize> {
+        self.systems.iter().find(|(system, _)| system == name).map(|(_, layer)| layer.index())
+    }
+
+    /// The names of the recorded systems and their layers.
+    ///
+    /// The systems are ordered by the order in which their trees were inserted, then by depth,
+    /// and in no particular order within a layer. A system that is part of multiple nodes is
+    /// listed once for each node.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, StartupTreeLayer)> + '_ {
+        self.systems.iter().map(|(name, layer)| (name.as_ref(), *layer))
+    }
+
+    /// Record the systems of each of the `layers` that were added to `schedule`.
+    pub(crate) fn record(&mut self, schedule: &Schedule, layers: &[StartupTreeLayer]) {
+        let graph = schedule.graph();
+        for &layer in layers {
+            let Some((layer_id, _, _)) = graph.system_sets().find(|&(_, set, _)| {
+                set.as_dyn_eq().as_any().downcast_ref::<StartupTreeLayer>() == Some(&layer)
+            }) else {
+                continue;
+            };
+            let systems = graph
+                .hierarchy()
+                .graph()
+                .neighbors(layer_id)
+                .filter_map(|id| graph.get_system_at(id))
+                .map(|system| (system.name(), layer));
+            self.systems.extend(systems);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+
+    use crate::{rng::reseed_rng, startup_tree, AddStartupTree, StartupTreeManifest};
+
+    fn load() {}
+
+    fn spawn() {}
+
+    fn finish() {}
+
+    #[test]
+    fn records_systems_by_depth() {
+        reseed_rng();
+
+        let mut app = App::new();
+        app.add_startup_tree_reflected(startup_tree! {
+            load => { spawn, finish },
+            finish,
+        });
+        app.add_startup_tree(startup_tree! { spawn });
+
+        let manifest = app.world().resource::<StartupTreeManifest>();
+        let mut systems =
+            manifest.iter().map(|(name, layer)| (name, layer.index())).collect::<Vec<_>>();
+        systems.sort();
+        let prefix = "bevy_startup_tree::manifest::tests::";
+        assert_eq!(
+            systems,
+            [
+                (&*format!("{prefix}finish"), 0),
+                (&*format!("{prefix}finish"), 1),
+                (&*format!("{prefix}load"), 0),
+                (&*format!("{prefix}spawn"), 1),
+            ]
+        );
+        assert_eq!(manifest.depth(&format!("{prefix}spawn")), Some(1));
+        assert_eq!(manifest.depth("spawn"), None);
+    }
+}
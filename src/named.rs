@@ -0,0 +1,132 @@
+use bevy_ecs::system::Resource;
+use bevy_utils::HashMap;
+
+use crate::StartupTreeHandle;
+
+/// The trees that were added with
+/// [`add_named_startup_tree`](crate::AddStartupTree::add_named_startup_tree), by name.
+///
+/// The resource is only in the world once a named tree was added. Use
+/// [`has_named_startup_tree`](crate::AddStartupTree::has_named_startup_tree) to check for a name
+/// without getting the resource first.
+#[derive(Resource, Default)]
+pub struct StartupTreeRegistry(pub(crate) HashMap<&'static str, StartupTreeHandle>);
+
+impl StartupTreeRegistry {
+    /// Whether a tree named `name` was added.
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.contains_key(name)
+    }
+
+    /// The handle of the tree named `name`, or `None` if no tree with that name was added.
+    pub fn get(&self, name: &str) -> Option<&StartupTreeHandle> {
+        self.0.get(name)
+    }
+
+    /// The names of all named trees, in no particular order.
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.0.keys().copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{ecs::schedule::ScheduleBuildSettings, prelude::*};
+
+    use crate::{
+        fixtures::{flush_runs_before, ran_app, sys_0, sys_1, sys_2, sys_3, Ran},
+        rng::reseed_rng,
+        startup_tree, AddStartupTree, StartupTreeHandle, StartupTreeRegistry,
+    };
+
+    #[test]
+    fn extended_tree_runs_after_named_tree() {
+        reseed_rng();
+
+        let mut app = ran_app();
+
+        app.add_named_startup_tree("ui", startup_tree! { sys_0 => sys_1 });
+        app.extend_named_startup_tree("ui", startup_tree! { sys_2 });
+        app.extend_named_startup_tree("ui", startup_tree! { sys_3 });
+
+        app.update();
+
+        assert_eq!(app.world().resource::<Ran>().0, [0, 1, 2, 3]);
+    }
+
+    #[derive(Component)]
+    struct Spawned;
+
+    fn spawn(mut commands: Commands) {
+        commands.spawn(Spawned);
+    }
+
+    fn count_spawned(q_spawned: Query<(), With<Spawned>>, mut ran: ResMut<Ran>) {
+        ran.0.push(q_spawned.iter().count() as u32);
+    }
+
+    #[test]
+    fn extended_tree_sees_commands_of_flush_after_node_in_last_layer() {
+        reseed_rng();
+
+        let mut app = ran_app();
+        app.edit_schedule(Startup, |schedule| {
+            schedule.set_build_settings(ScheduleBuildSettings {
+                auto_insert_apply_deferred: false,
+                ..default()
+            });
+        });
+
+        app.add_named_startup_tree("ui", startup_tree! { sys_0 => flush_after spawn });
+        app.extend_named_startup_tree("ui", startup_tree! { count_spawned });
+
+        assert!(flush_runs_before(&app, "_flush_1_0", "count_spawned"));
+        app.update();
+        assert_eq!(app.world().resource::<Ran>().0, [0, 1]);
+    }
+
+    #[test]
+    fn extending_missing_tree_adds_it() {
+        reseed_rng();
+
+        let mut app = ran_app();
+
+        app.extend_named_startup_tree("ui", startup_tree! { sys_0 });
+        app.extend_named_startup_tree("ui", startup_tree! { sys_1 });
+
+        app.update();
+
+        assert_eq!(app.world().resource::<Ran>().0, [0, 1]);
+    }
+
+    #[test]
+    fn second_registration_is_detected_and_skipped() {
+        reseed_rng();
+
+        let mut app = ran_app();
+
+        assert!(!app.has_named_startup_tree("ui"));
+        for _ in 0..2 {
+            if !app.has_named_startup_tree("ui") {
+                app.add_named_startup_tree("ui", startup_tree! { sys_0 => sys_1 });
+            }
+        }
+        assert!(app.has_named_startup_tree("ui"));
+        assert!(!app.has_named_startup_tree("hud"));
+
+        app.update();
+
+        assert_eq!(app.world().resource::<Ran>().0, [0, 1]);
+        let registry = app.world().resource::<StartupTreeRegistry>();
+        assert_eq!(registry.names().collect::<Vec<_>>(), ["ui"]);
+        assert_eq!(registry.get("ui").map(StartupTreeHandle::len), Some(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "a startup tree named \"ui\" was already added to the app")]
+    fn adding_duplicate_name_panics() {
+        let mut app = App::new();
+        app.add_named_startup_tree("ui", startup_tree! { sys_0 });
+        app.add_named_startup_tree("ui", startup_tree! { sys_1 });
+    }
+}
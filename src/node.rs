@@ -0,0 +1,147 @@
+use bevy_ecs::schedule::SystemConfigs;
+
+/// A node of a startup tree: the systems to add at the node's depth and the annotations that
+/// control how they are scheduled.
+///
+/// The [`startup_tree`](crate::startup_tree) macro emits nodes of this type when an annotation
+/// such as `pure` is used in the tree, otherwise it emits plain [`SystemConfigs`]. Both can be
+/// passed to [`add_startup_tree`](crate::AddStartupTree::add_startup_tree).
+///
+/// [`SystemConfigs`]: https://docs.rs/bevy/~0.14/bevy/ecs/schedule/type.SystemConfigs.html
+pub struct StartupTreeNode {
+    systems: Option<SystemConfigs>,
+    pure: bool,
+    flush_after: bool,
+    layer_name: Option<&'static str>,
+    label: Option<&'static str>,
+    after: Vec<&'static str>,
+    before: Vec<&'static str>,
+}
+
+impl StartupTreeNode {
+    /// Create a node for the given systems with no annotations.
+    pub fn new(systems: SystemConfigs) -> Self {
+        Self { systems: Some(systems), ..Self::placeholder() }
+    }
+
+    /// Create a node without systems that only makes sure that its layer exists.
+    ///
+    /// A layer of placeholders is still ordered between the layers before and after it, and is
+    /// still flushed, but runs no systems. This is equivalent to a `_` node in the
+    /// [`startup_tree`](crate::startup_tree) macro.
+    pub fn placeholder() -> Self {
+        Self {
+            systems: None,
+            pure: false,
+            flush_after: false,
+            layer_name: None,
+            label: None,
+            after: Vec::new(),
+            before: Vec::new(),
+        }
+    }
+
+    /// Whether the node is a [`placeholder`](Self::placeholder).
+    pub fn is_placeholder(&self) -> bool {
+        self.systems.is_none()
+    }
+
+    /// Declare that the systems of this node don't use [`Commands`] or any other deferred system
+    /// parameter.
+    ///
+    /// No flush is inserted after a layer whose nodes are all pure. This is equivalent to the
+    /// `pure` annotation in the [`startup_tree`](crate::startup_tree) macro.
+    ///
+    /// [`Commands`]: https://docs.rs/bevy/~0.14/bevy/ecs/system/struct.Commands.html
+    pub fn pure(mut self) -> Self {
+        self.pure = true;
+        self
+    }
+
+    /// Whether the node was declared [`pure`](Self::pure).
+    pub fn is_pure(&self) -> bool {
+        self.pure
+    }
+
+    /// Insert a flush that runs after the systems of this node and before the next layer.
+    ///
+    /// The deferred commands of the node are applied as soon as its systems have run instead of
+    /// after the whole layer, so the other systems of a wide layer are not held up by the flush.
+    /// The layer is only flushed as a whole if one of its other nodes is neither `pure` nor
+    /// `flush_after`. This is equivalent to the `flush_after` annotation in the
+    /// [`startup_tree`](crate::startup_tree) macro.
+    pub fn flush_after(mut self) -> Self {
+        self.flush_after = true;
+        self
+    }
+
+    /// Whether the node was declared [`flush_after`](Self::flush_after).
+    pub fn is_flush_after(&self) -> bool {
+        self.flush_after
+    }
+
+    /// Name the layer that contains this node.
+    ///
+    /// The name is used in the label of the layer's [`StartupTreeLayer`] in place of its depth.
+    /// Names must be unique within a tree, and if multiple nodes of a layer are named, they must
+    /// have the same name, otherwise the tree panics on insertion. This is equivalent to prefixing the node with `"name":` in the
+    /// [`startup_tree`](crate::startup_tree) macro.
+    ///
+    /// [`StartupTreeLayer`]: crate::StartupTreeLayer
+    pub fn with_layer_name(mut self, name: &'static str) -> Self {
+        self.layer_name = Some(name);
+        self
+    }
+
+    /// The name given to the layer with [`with_layer_name`](Self::with_layer_name).
+    pub fn layer_name(&self) -> Option<&'static str> {
+        self.layer_name
+    }
+
+    /// Label this node so that other nodes of the tree can be ordered against it.
+    ///
+    /// Labels must be unique within a tree. This is equivalent to suffixing the node with
+    /// `as label` in the [`startup_tree`](crate::startup_tree) macro.
+    pub fn with_label(mut self, label: &'static str) -> Self {
+        self.label = Some(label);
+        self
+    }
+
+    /// The label given to the node with [`with_label`](Self::with_label).
+    pub fn label(&self) -> Option<&'static str> {
+        self.label
+    }
+
+    /// Run the systems of this node after those of the node with the given label.
+    ///
+    /// The tree panics on insertion if none of its nodes has the label. This is equivalent to
+    /// `.after(label)` in the [`startup_tree`](crate::startup_tree) macro.
+    pub fn after_label(mut self, label: &'static str) -> Self {
+        self.after.push(label);
+        self
+    }
+
+    /// Run the systems of this node before those of the node with the given label.
+    ///
+    /// The tree panics on insertion if none of its nodes has the label. This is equivalent to
+    /// `.before(label)` in the [`startup_tree`](crate::startup_tree) macro.
+    pub fn before_label(mut self, label: &'static str) -> Self {
+        self.before.push(label);
+        self
+    }
+
+    /// The labels of the nodes that this node runs after and before, respectively.
+    pub(crate) fn label_orderings(&self) -> (&[&'static str], &[&'static str]) {
+        (&self.after, &self.before)
+    }
+
+    pub(crate) fn into_systems(self) -> Option<SystemConfigs> {
+        self.systems
+    }
+}
+
+impl From<SystemConfigs> for StartupTreeNode {
+    fn from(systems: SystemConfigs) -> Self {
+        Self::new(systems)
+    }
+}
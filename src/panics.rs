@@ -0,0 +1,275 @@
+use std::{
+    any::TypeId,
+    hash::Hasher,
+    panic::{self, AssertUnwindSafe},
+};
+
+use bevy_ecs::{
+    label::DynEq,
+    schedule::{
+        ExecutorKind, IntoSystemConfigs, NodeId, Schedule, ScheduleGraph, ScheduleLabel,
+        SystemConfigs, SystemSet,
+    },
+    world::World,
+};
+use bevy_utils::tracing::error;
+
+use crate::StartupTreeLayer;
+
+/// The label of the schedules that run the systems of a node of a tree that reports panics.
+#[derive(ScheduleLabel, Clone, Debug, PartialEq, Eq, Hash)]
+struct StartupTreeNodeSchedule;
+
+/// A set from the schedule of a node, which is equal to the set itself so that the wrapper of the
+/// node can be put in it or ordered against it.
+#[derive(Debug)]
+struct NodeSet(Box<dyn SystemSet>);
+
+impl SystemSet for NodeSet {
+    fn system_type(&self) -> Option<TypeId> {
+        self.0.system_type()
+    }
+
+    fn is_anonymous(&self) -> bool {
+        self.0.is_anonymous()
+    }
+
+    fn dyn_clone(&self) -> Box<dyn SystemSet> {
+        self.0.dyn_clone()
+    }
+
+    fn as_dyn_eq(&self) -> &dyn DynEq {
+        self.0.as_dyn_eq()
+    }
+
+    fn dyn_hash(&self, state: &mut dyn Hasher) {
+        self.0.dyn_hash(state);
+    }
+}
+
+/// How the systems of a node relate to a set.
+enum SetRelation {
+    In,
+    After,
+    Before,
+}
+
+/// The sets that the systems of a node are in or ordered against, except for anonymous sets and
+/// the sets of system types, which systems can't be added to.
+fn node_sets(graph: &ScheduleGraph) -> Vec<(SetRelation, NodeSet)> {
+    let mut relations = Vec::new();
+    let mut push = |relation, id: NodeId| {
+        let Some(set) = graph.get_set_at(id) else {
+            return;
+        };
+        let is_type_set = matches!(relation, SetRelation::In) && set.system_type().is_some();
+        if !set.is_anonymous() && !is_type_set {
+            relations.push((relation, NodeSet(set.dyn_clone())));
+        }
+    };
+    for (set, system, _) in graph.hierarchy().graph().all_edges() {
+        if system.is_system() {
+            push(SetRelation::In, set);
+        }
+    }
+    for (first, then, _) in graph.dependency().graph().all_edges() {
+        match (first.is_system(), then.is_system()) {
+            (false, true) => push(SetRelation::After, first),
+            (true, false) => push(SetRelation::Before, then),
+            _ => {}
+        }
+    }
+    relations
+}
+
+/// Wrap the `systems` of a node in `layer` in an exclusive system that logs the layer's label if
+/// one of them panics, then continues to unwind.
+///
+/// The systems run in a schedule of their own with the given `executor`, so that the panic can be
+/// caught around them. The wrapper is put in the sets that the systems are in and ordered against
+/// the sets that they are ordered against, so that it runs where they would have run.
+pub(crate) fn catch_node_panics(
+    systems: SystemConfigs,
+    layer: StartupTreeLayer,
+    executor: ExecutorKind,
+) -> SystemConfigs {
+    let mut schedule = Schedule::new(StartupTreeNodeSchedule);
+    schedule.set_executor_kind(executor);
+    schedule.add_systems(systems);
+    let sets = node_sets(schedule.graph());
+    let wrapper = (move |world: &mut World| {
+        if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(|| schedule.run(world))) {
+            error!("startup tree system panicked in layer `{}`", layer.label());
+            panic::resume_unwind(payload);
+        }
+    })
+    .into_configs();
+    sets.into_iter().fold(wrapper, |wrapper, (relation, set)| match relation {
+        SetRelation::In => wrapper.in_set(set),
+        SetRelation::After => wrapper.after(set),
+        SetRelation::Before => wrapper.before(set),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        panic::{catch_unwind, AssertUnwindSafe},
+        sync::{Arc, Mutex},
+    };
+
+    use bevy::{
+        ecs::schedule::{ExecutorKind, NodeId},
+        prelude::*,
+        utils::tracing::{
+            field::{Field, Visit},
+            span::{Attributes, Id, Record},
+            subscriber, Event, Metadata, Subscriber,
+        },
+    };
+
+    use crate::{
+        fixtures::{ran_app, sys_0, sys_1, sys_2, Ran},
+        rng::reseed_rng,
+        startup_tree, StartupTreeBuilder,
+    };
+
+    /// Collects the messages of all events.
+    #[derive(Clone, Default)]
+    struct Messages(Arc<Mutex<Vec<String>>>);
+
+    impl Visit for Messages {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            if field.name() == "message" {
+                self.0.lock().unwrap().push(format!("{value:?}"));
+            }
+        }
+    }
+
+    impl Subscriber for Messages {
+        fn enabled(&self, _: &Metadata) -> bool {
+            true
+        }
+
+        fn new_span(&self, _: &Attributes) -> Id {
+            Id::from_u64(1)
+        }
+
+        fn record(&self, _: &Id, _: &Record) {}
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, event: &Event) {
+            event.record(&mut self.clone());
+        }
+
+        fn enter(&self, _: &Id) {}
+
+        fn exit(&self, _: &Id) {}
+    }
+
+    fn ok() {}
+
+    fn fail() {
+        panic!("startup failed");
+    }
+
+    /// An app whose `Startup` schedule runs on this thread, so that the subscriber sees the logs.
+    fn single_threaded_app() -> App {
+        let mut app = App::new();
+        app.edit_schedule(Startup, |schedule| {
+            schedule.set_executor_kind(ExecutorKind::SingleThreaded);
+        });
+        app
+    }
+
+    #[test]
+    fn logs_layer_on_panic() {
+        reseed_rng();
+
+        let mut app = single_threaded_app();
+        let handle =
+            StartupTreeBuilder::new(startup_tree! { ok => fail }).report_panics().insert(&mut app);
+        let failing_layer = handle.last_layer().unwrap();
+
+        let messages = Messages::default();
+        let result = subscriber::with_default(messages.clone(), || {
+            catch_unwind(AssertUnwindSafe(|| app.update()))
+        });
+
+        assert!(result.is_err());
+        assert_eq!(
+            *messages.0.lock().unwrap(),
+            [format!("startup tree system panicked in layer `{}`", failing_layer.label())]
+        );
+    }
+
+    #[test]
+    fn only_logs_layer_of_panicking_node() {
+        reseed_rng();
+
+        let mut app = single_threaded_app();
+        StartupTreeBuilder::new(startup_tree! { ok => ok }).report_panics().insert(&mut app);
+        let handle =
+            StartupTreeBuilder::new(startup_tree! { fail }).report_panics().insert(&mut app);
+        let failing_layer = handle.last_layer().unwrap();
+
+        let messages = Messages::default();
+        let result = subscriber::with_default(messages.clone(), || {
+            catch_unwind(AssertUnwindSafe(|| app.update()))
+        });
+
+        assert!(result.is_err());
+        assert_eq!(
+            *messages.0.lock().unwrap(),
+            [format!("startup tree system panicked in layer `{}`", failing_layer.label())]
+        );
+    }
+
+    #[test]
+    fn doesnt_log_unrelated_panics() {
+        reseed_rng();
+
+        let mut app = single_threaded_app();
+        StartupTreeBuilder::new(startup_tree! { fail }).report_panics().insert(&mut app);
+
+        let messages = Messages::default();
+        subscriber::with_default(messages.clone(), || {
+            assert!(catch_unwind(AssertUnwindSafe(|| app.update())).is_err());
+            messages.0.lock().unwrap().clear();
+            assert!(catch_unwind(|| panic!("unrelated")).is_err());
+        });
+
+        assert!(messages.0.lock().unwrap().is_empty());
+    }
+
+    #[derive(SystemSet, Clone, Debug, PartialEq, Eq, Hash)]
+    struct Early;
+
+    #[test]
+    fn keeps_sets_and_orderings_of_nodes() {
+        reseed_rng();
+
+        let mut app = ran_app();
+        app.add_systems(Startup, (sys_0, sys_2.after(Early)));
+        StartupTreeBuilder::new(startup_tree! { sys_1.after(sys_0) in Early })
+            .report_panics()
+            .insert(&mut app);
+
+        let graph = app.get_schedule(Startup).unwrap().graph();
+        let (wrapper, _, _) =
+            graph.systems().find(|(_, sys, _)| sys.name().contains("catch_node_panics")).unwrap();
+        let has_edge = |edges: &[(NodeId, NodeId)], set: &str| {
+            let is_set = |id| graph.get_set_at(id).is_some_and(|s| format!("{s:?}").contains(set));
+            edges.iter().any(|&(from, to)| to == wrapper && is_set(from))
+        };
+        let hierarchy = graph.hierarchy().graph().all_edges().map(|(a, b, _)| (a, b));
+        let dependencies = graph.dependency().graph().all_edges().map(|(a, b, _)| (a, b));
+        assert!(has_edge(&hierarchy.collect::<Vec<_>>(), "Early"));
+        assert!(has_edge(&dependencies.collect::<Vec<_>>(), "sys_0"));
+
+        app.update();
+
+        assert_eq!(app.world().resource::<Ran>().0, [0, 1, 2]);
+    }
+}
@@ -0,0 +1,171 @@
+use std::{borrow::Cow, fmt::Display, marker::PhantomData};
+
+use bevy_ecs::{
+    system::{Adapt, AdapterSystem, BoxedSystem, IntoSystem, System},
+    world::World,
+};
+use bevy_utils::tracing::warn;
+
+/// Run `systems` one after the other, passing the output of each system as the [`In`] of the
+/// next, and return the output of the last one.
+///
+/// This is for pipelines whose steps are only known at runtime. Each system is run like with
+/// [`run_system_once_with`]: it is initialized, run, and its deferred commands are applied before
+/// the next system runs. Returns `input` unchanged if there are no systems.
+///
+/// Because the systems are passed as a list, they must all take and return the same type `T`. A
+/// step that doesn't need its input must still take `In<T>`, and pass it on if nothing else.
+///
+/// The pipeline is inherently exclusive: the access of the systems is only known once they are
+/// initialized on the world, and their commands must be applied between steps, both of which take
+/// `&mut World`. Queueing it as a command wouldn't help either, since commands are applied with
+/// exclusive access too. When the steps are known at compile time, prefer a `pipe { .. }` node or
+/// [`IntoSystem::pipe`], which combine the steps into a single regular system that runs in
+/// parallel with the systems it doesn't conflict with. Note that the commands of such a system are
+/// only applied after its last step.
+///
+/// ```rust
+/// # use bevy::prelude::*;
+/// # use bevy_startup_tree::run_system_pipeline;
+/// fn double(In(n): In<u32>) -> u32 {
+///     n * 2
+/// }
+///
+/// fn add_one(In(n): In<u32>) -> u32 {
+///     n + 1
+/// }
+///
+/// let steps: Vec<bevy::ecs::system::BoxedSystem<u32, u32>> =
+///     vec![Box::new(IntoSystem::into_system(double)), Box::new(IntoSystem::into_system(add_one))];
+/// assert_eq!(run_system_pipeline(&mut World::new(), steps, 3), 7);
+/// ```
+///
+/// [`In`]: https://docs.rs/bevy/~0.14/bevy/ecs/system/struct.In.html
+/// [`IntoSystem::pipe`]: https://docs.rs/bevy/~0.14/bevy/ecs/system/trait.IntoSystem.html#method.pipe
+/// [`run_system_once_with`]: https://docs.rs/bevy/~0.14/bevy/ecs/system/trait.RunSystemOnce.html#tymethod.run_system_once_with
+pub fn run_system_pipeline<T: 'static>(
+    world: &mut World,
+    systems: impl IntoIterator<Item = BoxedSystem<T, T>>,
+    input: T,
+) -> T {
+    systems.into_iter().fold(input, |input, mut system| {
+        system.initialize(world);
+        system.run(input, world)
+    })
+}
+
+/// Make `system` an optional step of a pipe, that passes on its input when it fails.
+///
+/// `system` takes `In<T>` and returns a `Result<T, E>`. If it returns `Ok`, the value is passed on
+/// to the next step. If it returns `Err`, the error is logged as a warning and a clone of the
+/// input is passed on instead, so the rest of the pipe still runs.
+///
+/// This is what a `=?>` link in a `pipe { .. }` node expands to, e.g. in
+/// `pipe { read_level =?> apply_mods => spawn_level }` a failure of `apply_mods` only skips the
+/// mods.
+///
+/// ```rust
+/// # use bevy::prelude::*;
+/// # use bevy::ecs::system::RunSystemOnce;
+/// # use bevy_startup_tree::optional_step;
+/// fn parse(In(n): In<u32>) -> Result<u32, String> {
+///     Err(format!("can't parse {n}"))
+/// }
+///
+/// let step = optional_step(parse);
+/// assert_eq!(World::new().run_system_once_with(3, step), 3);
+/// ```
+pub fn optional_step<T, E, M>(
+    system: impl IntoSystem<T, Result<T, E>, M>,
+) -> impl System<In = T, Out = T>
+where
+    T: Clone + Send + Sync + 'static,
+    E: Display + 'static,
+{
+    let system = IntoSystem::into_system(system);
+    let name = system.name();
+    AdapterSystem::new(
+        OptionalStep { name: name.clone(), marker: PhantomData },
+        system,
+        Cow::Owned(format!("optional_step({name})")),
+    )
+}
+
+/// The [`Adapt`] of [`optional_step`].
+struct OptionalStep<T, E> {
+    name: Cow<'static, str>,
+    marker: PhantomData<fn(T) -> E>,
+}
+
+impl<T, E, S> Adapt<S> for OptionalStep<T, E>
+where
+    T: Clone + Send + Sync + 'static,
+    E: Display + 'static,
+    S: System<In = T, Out = Result<T, E>>,
+{
+    type In = T;
+    type Out = T;
+
+    fn adapt(&mut self, input: T, run_system: impl FnOnce(T) -> Result<T, E>) -> T {
+        run_system(input.clone()).unwrap_or_else(|err| {
+            warn!("optional step {} failed, passing on its input: {err}", self.name);
+            input
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{ecs::system::BoxedSystem, prelude::*};
+
+    use super::{optional_step, run_system_pipeline};
+
+    #[derive(Component)]
+    struct Step;
+
+    fn spawn_step(In(n): In<u32>, mut commands: Commands) -> u32 {
+        commands.spawn(Step);
+        n + 1
+    }
+
+    fn count_steps(In(n): In<u32>, q_steps: Query<&Step>) -> u32 {
+        n + 10 * q_steps.iter().count() as u32
+    }
+
+    #[test]
+    fn passes_output_to_next_system_and_applies_commands() {
+        let mut world = World::new();
+        let steps: Vec<BoxedSystem<u32, u32>> = vec![
+            Box::new(IntoSystem::into_system(spawn_step)),
+            Box::new(IntoSystem::into_system(spawn_step)),
+            Box::new(IntoSystem::into_system(count_steps)),
+        ];
+
+        assert_eq!(run_system_pipeline(&mut world, steps, 0), 22);
+    }
+
+    #[test]
+    fn returns_input_without_systems() {
+        assert_eq!(run_system_pipeline(&mut World::new(), Vec::new(), 5), 5);
+    }
+
+    fn checked_half(In(n): In<u32>) -> Result<u32, String> {
+        if n % 2 == 0 {
+            Ok(n / 2)
+        } else {
+            Err(format!("{n} is odd"))
+        }
+    }
+
+    #[test]
+    fn optional_step_passes_on_input_when_it_fails() {
+        let mut world = World::new();
+        let steps: Vec<BoxedSystem<u32, u32>> = vec![
+            Box::new(optional_step(checked_half)),
+            Box::new(optional_step(checked_half)),
+            Box::new(optional_step(checked_half)),
+        ];
+
+        assert_eq!(run_system_pipeline(&mut world, steps, 12), 3);
+    }
+}
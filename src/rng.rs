@@ -1,47 +1,69 @@
-#[cfg(not(test))]
+#[cfg(not(any(test, feature = "test-util")))]
 pub fn get_rng() -> impl rand::Rng {
     rand::thread_rng()
 }
 
-#[cfg(test)]
-mod test_rng {
+#[cfg(any(test, feature = "test-util"))]
+pub use seeded_rng::*;
+
+#[cfg(any(test, feature = "test-util"))]
+mod seeded_rng {
     use std::{cell::RefCell, rc::Rc};
 
-    use delegate::delegate;
     use rand::{rngs::StdRng, Error, Rng, RngCore, SeedableRng};
 
+    #[cfg(test)]
     const TEST_RNG_SEED: u64 = 0;
 
     thread_local! {
-        static TEST_RNG_INNER: Rc<RefCell<StdRng>> =
-            Rc::new(RefCell::new(StdRng::seed_from_u64(TEST_RNG_SEED)));
+        static SEEDED_RNG_INNER: Rc<RefCell<StdRng>> = Rc::new(RefCell::new(initial_rng()));
+    }
+
+    #[cfg(test)]
+    fn initial_rng() -> StdRng {
+        StdRng::seed_from_u64(TEST_RNG_SEED)
     }
 
-    struct TestRng(Rc<RefCell<StdRng>>);
+    #[cfg(not(test))]
+    fn initial_rng() -> StdRng {
+        StdRng::from_entropy()
+    }
+
+    struct SeededRng(Rc<RefCell<StdRng>>);
+
+    impl RngCore for SeededRng {
+        fn next_u32(&mut self) -> u32 {
+            self.0.borrow_mut().next_u32()
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            self.0.borrow_mut().next_u64()
+        }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            self.0.borrow_mut().fill_bytes(dest);
+        }
 
-    impl RngCore for TestRng {
-        delegate! {
-            to self.0.borrow_mut() {
-                fn next_u32(&mut self) -> u32;
-                fn next_u64(&mut self) -> u64;
-                fn fill_bytes(&mut self, dest: &mut [u8]);
-                fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error>;
-            }
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+            self.0.borrow_mut().try_fill_bytes(dest)
         }
     }
 
+    pub fn seed_rng(seed: u64) {
+        SEEDED_RNG_INNER.with(|rng| *rng.borrow_mut() = StdRng::seed_from_u64(seed));
+    }
+
+    #[cfg(test)]
     pub fn reset_rng() {
-        TEST_RNG_INNER.with(|rng| *rng.borrow_mut() = StdRng::seed_from_u64(TEST_RNG_SEED));
+        seed_rng(TEST_RNG_SEED);
     }
 
+    #[cfg(test)]
     pub fn reseed_rng() {
-        TEST_RNG_INNER.with(|rng| *rng.borrow_mut() = StdRng::from_entropy());
+        SEEDED_RNG_INNER.with(|rng| *rng.borrow_mut() = StdRng::from_entropy());
     }
 
     pub fn get_rng() -> impl Rng {
-        TestRng(TEST_RNG_INNER.with(Rc::clone))
+        SeededRng(SEEDED_RNG_INNER.with(Rc::clone))
     }
 }
-
-#[cfg(test)]
-pub use test_rng::*;
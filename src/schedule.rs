@@ -2,15 +2,180 @@ use std::fmt;
 
 use bevy_ecs::schedule::SystemSet;
 
-#[derive(Clone, Copy, Hash, PartialEq, Eq, SystemSet)]
-pub struct StartupTreeLayer(pub &'static str);
+/// The system set containing the systems at one depth of a startup tree.
+///
+/// Layers are ordered by their depth in the tree, then by their label.
+// Field order matters for the derived `Ord`
+#[derive(Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, SystemSet)]
+pub struct StartupTreeLayer {
+    index: usize,
+    label: &'static str,
+}
+
+impl StartupTreeLayer {
+    pub(crate) fn new(label: &'static str, index: usize) -> Self {
+        Self { index, label }
+    }
+
+    /// The unique label of the layer.
+    pub fn label(&self) -> &'static str {
+        self.label
+    }
+
+    /// The depth of the layer in its tree.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
 
 impl fmt::Debug for StartupTreeLayer {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         #[cfg(test)]
         if f.alternate() {
-            return f.write_str(self.0);
+            return f.write_str(self.label);
+        }
+        f.debug_tuple("Set").field(&self.label).finish()
+    }
+}
+
+/// The system set that a [`StartupTreeLayer`] of a scoped startup tree is nested in.
+///
+/// Trees inserted with [`scope`](crate::StartupTreeBuilder::scope) tag each of their layers with
+/// the scope, e.g. the name of the world or sub-app the tree belongs to, so that identical trees in
+/// different scopes can be told apart in tooling and graph dumps. The set has the same label and
+/// index as the layer it contains.
+// Field order matters for the derived `Ord`
+#[derive(Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, SystemSet)]
+pub struct ScopedStartupTreeLayer {
+    index: usize,
+    scope: &'static str,
+    label: &'static str,
+}
+
+impl ScopedStartupTreeLayer {
+    pub(crate) fn new(scope: &'static str, layer: StartupTreeLayer) -> Self {
+        Self { index: layer.index, scope, label: layer.label }
+    }
+
+    /// The scope of the tree that the layer belongs to.
+    pub fn scope(&self) -> &'static str {
+        self.scope
+    }
+
+    /// The unique label of the layer.
+    pub fn label(&self) -> &'static str {
+        self.label
+    }
+
+    /// The depth of the layer in its tree.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+impl fmt::Debug for ScopedStartupTreeLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        #[cfg(test)]
+        if f.alternate() {
+            return write!(f, "{}::{}", self.scope, self.label);
         }
-        f.debug_tuple("Set").field(&self.0).finish()
+        f.debug_tuple("ScopedSet").field(&self.scope).field(&self.label).finish()
+    }
+}
+
+/// The system set containing the flush that applies the deferred commands of one depth of a
+/// startup tree.
+///
+/// The flush runs after the [`StartupTreeLayer`] with the same index and before the next one. It
+/// is omitted for layers whose nodes are all `pure`. The flush added by
+/// [`flush_at_end`](crate::StartupTreeBuilder::flush_at_end) has the index one past the last
+/// layer.
+#[derive(Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, SystemSet)]
+pub struct StartupTreeFlush {
+    index: usize,
+    label: &'static str,
+}
+
+impl StartupTreeFlush {
+    pub(crate) fn new(label: &'static str, index: usize) -> Self {
+        Self { index, label }
+    }
+
+    /// The unique label of the flush.
+    pub fn label(&self) -> &'static str {
+        self.label
+    }
+
+    /// The depth of the layer that is flushed.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+impl fmt::Debug for StartupTreeFlush {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        #[cfg(test)]
+        if f.alternate() {
+            return f.write_str(self.label);
+        }
+        f.debug_tuple("Flush").field(&self.label).finish()
+    }
+}
+
+/// The system set containing the systems of a labeled node of a startup tree.
+///
+/// Other nodes of the tree are ordered against this set when they refer to the node's label, e.g.
+/// `sys.after(setup)` for a node `other_sys as setup`.
+#[derive(Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, SystemSet)]
+pub struct StartupTreeNodeLabel {
+    label: &'static str,
+}
+
+impl StartupTreeNodeLabel {
+    pub(crate) fn new(label: &'static str) -> Self {
+        Self { label }
+    }
+
+    /// The unique label of the set.
+    pub fn label(&self) -> &'static str {
+        self.label
+    }
+}
+
+impl fmt::Debug for StartupTreeNodeLabel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        #[cfg(test)]
+        if f.alternate() {
+            return f.write_str(self.label);
+        }
+        f.debug_tuple("NodeLabel").field(&self.label).finish()
+    }
+}
+
+/// The system set containing the systems of every startup tree inserted with
+/// [`in_systems_set`](crate::StartupTreeBuilder::in_systems_set).
+///
+/// The set doesn't order anything, it only groups the layers and flushes of those trees, e.g. so
+/// that schedule graph tools like `bevy_mod_debugdump` render each tree as one cluster.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord, SystemSet)]
+pub struct StartupTreeSystems;
+
+#[cfg(test)]
+mod tests {
+    use super::StartupTreeLayer;
+
+    #[test]
+    fn layers_sort_by_depth() {
+        let mut layers = [
+            StartupTreeLayer::new("__startup_tree_zujxzB_layer_2", 2),
+            StartupTreeLayer::new("__startup_tree_zujxzB_layer_10", 10),
+            StartupTreeLayer::new("__startup_tree_zujxzB_layer_0", 0),
+            StartupTreeLayer::new("__startup_tree_zujxzB_layer_1", 1),
+        ];
+
+        layers.sort();
+
+        let indices = layers.iter().map(StartupTreeLayer::index).collect::<Vec<_>>();
+        assert_eq!(indices, [0, 1, 2, 10]);
     }
 }
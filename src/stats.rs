@@ -0,0 +1,222 @@
+use crate::TreeError;
+
+/// Structural statistics of a startup tree, returned by [`startup_tree_stats`] and
+/// [`validate_startup_tree`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeStats {
+    /// The number of layers in the tree.
+    pub depth: usize,
+    /// The total number of nodes in the tree.
+    pub node_count: usize,
+    /// The number of nodes in each layer, indexed by depth.
+    pub layer_node_counts: Vec<usize>,
+}
+
+impl TreeStats {
+    /// The depths of the layers that have no nodes, in order.
+    pub fn empty_layers(&self) -> impl Iterator<Item = usize> + '_ {
+        self.layer_node_counts.iter().enumerate().filter(|(_, &count)| count == 0).map(|(d, _)| d)
+    }
+}
+
+/// Count the nodes of each layer of a tree, without rejecting trees that aren't well-formed.
+///
+/// Unlike [`validate_startup_tree`], which stops at the first empty layer, this reports all of
+/// them, e.g. for a plugin that takes a tree built by hand and wants to explain what is wrong with
+/// it before inserting it. Whether the systems of a node are valid is checked by the compiler, so
+/// the counts are all that can be checked at runtime.
+///
+/// ```rust
+/// # use bevy::prelude::*;
+/// # use bevy_startup_tree::startup_tree_stats;
+/// # fn sys_1() {}
+/// # fn sys_3() {}
+/// let tree = vec![
+///     vec![sys_1.into_configs()],
+///     vec![
+///         #[cfg(feature = "debug_overlay")]
+///         spawn_overlay.into_configs(),
+///     ],
+///     vec![sys_3.into_configs()],
+/// ];
+/// let stats = startup_tree_stats(&tree);
+/// assert_eq!(stats.layer_node_counts, [1, 0, 1]);
+/// for depth in stats.empty_layers() {
+///     warn!("layer {depth} has 0 systems, a `#[cfg]` likely removed them all");
+/// }
+/// ```
+pub fn startup_tree_stats<L, N>(startup_tree: &[L]) -> TreeStats
+where
+    L: AsRef<[N]>,
+{
+    let layer_node_counts =
+        startup_tree.iter().map(|layer| layer.as_ref().len()).collect::<Vec<_>>();
+    TreeStats {
+        depth: layer_node_counts.len(),
+        node_count: layer_node_counts.iter().sum(),
+        layer_node_counts,
+    }
+}
+
+/// Check that a tree is well-formed without inserting it into an app.
+///
+/// A tree is well-formed if it has at least one layer and none of its layers are empty. The tree
+/// is only inspected structurally, the systems of its nodes can't be. Trees generated by the
+/// [`startup_tree`](crate::startup_tree) macro are always well-formed, this is mostly useful for
+/// trees that are built by hand.
+///
+/// ```rust
+/// # use bevy::prelude::*;
+/// # use bevy_startup_tree::{startup_tree, validate_startup_tree, TreeError};
+/// # fn sys_1_a() {}
+/// # fn sys_1_b() {}
+/// # fn sys_2() {}
+/// let tree = startup_tree! {
+///     sys_1_a,
+///     sys_1_b => sys_2,
+/// };
+/// let stats = validate_startup_tree(&tree).unwrap();
+/// assert_eq!(stats.depth, 2);
+/// assert_eq!(stats.node_count, 3);
+///
+/// let tree = vec![vec![sys_1_a.into_configs()], vec![]];
+/// assert_eq!(validate_startup_tree(&tree), Err(TreeError::EmptyLayer { depth: 1 }));
+/// ```
+pub fn validate_startup_tree<L, N>(startup_tree: &[L]) -> Result<TreeStats, TreeError>
+where
+    L: AsRef<[N]>,
+{
+    let stats = startup_tree_stats(startup_tree);
+    if stats.depth == 0 {
+        return Err(TreeError::EmptyTree);
+    }
+    if let Some(depth) = stats.empty_layers().next() {
+        return Err(TreeError::EmptyLayer { depth });
+    }
+    Ok(stats)
+}
+
+/// The total number of nodes in all layers of a tree.
+///
+/// Each node counts as one system, even if it adds a tuple of systems or is an `async` node with
+/// its startup task barrier. This is meant for log messages like "registered N startup systems",
+/// [`add_startup_tree`](crate::AddStartupTree::add_startup_tree) logs it at the debug level.
+///
+/// ```rust
+/// # use bevy_startup_tree::{count_systems, startup_tree};
+/// # fn sys_1_a() {}
+/// # fn sys_1_b() {}
+/// # fn sys_2() {}
+/// let tree = startup_tree! {
+///     sys_1_a,
+///     sys_1_b => sys_2,
+/// };
+/// assert_eq!(count_systems(&tree), 3);
+/// ```
+pub fn count_systems<L, N>(startup_tree: &[L]) -> usize
+where
+    L: AsRef<[N]>,
+{
+    startup_tree.iter().map(|layer| layer.as_ref().len()).sum()
+}
+
+/// A one-line description of the shape of a tree, with the number of nodes in each layer.
+///
+/// Like [`count_systems`], each node counts as one system. This is meant for logging the shape of
+/// a tree once at startup, e.g. to notice when a change to the tree moved systems between layers.
+///
+/// ```rust
+/// # use bevy_startup_tree::{describe_layers, startup_tree};
+/// # fn sys_1_a() {}
+/// # fn sys_1_b() {}
+/// # fn sys_2() {}
+/// let tree = startup_tree! {
+///     sys_1_a,
+///     sys_1_b => sys_2,
+/// };
+/// assert_eq!(describe_layers(&tree), "2 layers: [2, 1] systems");
+/// ```
+pub fn describe_layers<L, N>(startup_tree: &[L]) -> String
+where
+    L: AsRef<[N]>,
+{
+    let counts = startup_tree.iter().map(|layer| layer.as_ref().len()).collect::<Vec<_>>();
+    let layers = if counts.len() == 1 { "layer" } else { "layers" };
+    format!("{} {layers}: {counts:?} systems", counts.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+
+    use crate::{
+        count_systems, describe_layers, startup_tree, startup_tree_stats, validate_startup_tree,
+        TreeError, TreeStats,
+    };
+
+    fn system() {}
+
+    #[test]
+    fn returns_stats_of_valid_tree() {
+        let tree = startup_tree! {
+            system,
+            system => {
+                system => system,
+                system,
+            },
+        };
+
+        let expected = TreeStats { depth: 3, node_count: 5, layer_node_counts: vec![2, 2, 1] };
+        assert_eq!(validate_startup_tree(&tree), Ok(expected));
+    }
+
+    #[test]
+    fn rejects_empty_tree() {
+        let tree: Vec<Vec<bevy::ecs::schedule::SystemConfigs>> = Vec::new();
+        assert_eq!(validate_startup_tree(&tree), Err(TreeError::EmptyTree));
+    }
+
+    #[test]
+    fn rejects_empty_layer() {
+        let tree = vec![vec![system.into_configs()], vec![], vec![system.into_configs()]];
+        assert_eq!(validate_startup_tree(&tree), Err(TreeError::EmptyLayer { depth: 1 }));
+    }
+
+    #[test]
+    fn stats_report_all_empty_layers() {
+        let tree = vec![vec![], vec![system.into_configs()], vec![], vec![system.into_configs()]];
+
+        let stats = startup_tree_stats(&tree);
+        assert_eq!(
+            stats,
+            TreeStats { depth: 4, node_count: 2, layer_node_counts: vec![0, 1, 0, 1] }
+        );
+        assert_eq!(stats.empty_layers().collect::<Vec<_>>(), [0, 2]);
+    }
+
+    #[test]
+    fn counts_systems_of_all_layers() {
+        let tree = startup_tree! {
+            system => { system, system => system },
+            system,
+        };
+
+        assert_eq!(count_systems(&tree), 5);
+        assert_eq!(count_systems::<Vec<bevy::ecs::schedule::SystemConfigs>, _>(&[]), 0);
+    }
+
+    #[test]
+    fn describes_system_counts_by_layer() {
+        let tree = startup_tree! {
+            system => { system, system => system },
+            system,
+        };
+        assert_eq!(describe_layers(&tree), "3 layers: [2, 2, 1] systems");
+
+        let tree = startup_tree! { system };
+        assert_eq!(describe_layers(&tree), "1 layer: [1] systems");
+
+        let tree: Vec<Vec<bevy::ecs::schedule::SystemConfigs>> = Vec::new();
+        assert_eq!(describe_layers(&tree), "0 layers: [] systems");
+    }
+}
@@ -0,0 +1,137 @@
+use std::{future::Future, pin::Pin};
+
+use bevy_ecs::{
+    system::Resource,
+    world::{CommandQueue, World},
+};
+use bevy_tasks::{AsyncComputeTaskPool, TaskPool};
+use bevy_utils::synccell::SyncCell;
+
+type StartupTask = Pin<Box<dyn Future<Output = CommandQueue> + Send>>;
+
+/// Asynchronous work that must complete before the next depth of a startup tree runs.
+///
+/// Systems marked `async` in the [`startup_tree`](crate::startup_tree) macro may spawn tasks into
+/// this resource. Once the system has run, the tasks are driven to completion on the
+/// [`AsyncComputeTaskPool`] and the [`CommandQueue`] returned by each task is applied to the world.
+/// Only then do the systems at the next depth run.
+///
+/// ```rust no_run
+/// # use bevy::{ecs::world::CommandQueue, prelude::*};
+/// # use bevy_startup_tree::{startup_tree, StartupTasks};
+/// #[derive(Resource)]
+/// struct Config(String);
+///
+/// fn load_config(mut tasks: ResMut<StartupTasks>) {
+///     tasks.spawn(async {
+///         let config = Config(String::from("loaded asynchronously"));
+///         let mut queue = CommandQueue::default();
+///         queue.push(move |world: &mut World| {
+///             world.insert_resource(config);
+///         });
+///         queue
+///     });
+/// }
+///
+/// fn use_config(config: Res<Config>) {
+///     info!("{}", config.0);
+/// }
+///
+/// # std::mem::drop(
+/// startup_tree! {
+///     async load_config => use_config,
+/// }
+/// # );
+/// ```
+///
+/// [`AsyncComputeTaskPool`]: https://docs.rs/bevy/~0.14/bevy/tasks/struct.AsyncComputeTaskPool.html
+/// [`CommandQueue`]: https://docs.rs/bevy/~0.14/bevy/ecs/world/struct.CommandQueue.html
+#[derive(Resource)]
+pub struct StartupTasks(SyncCell<Vec<StartupTask>>);
+
+impl StartupTasks {
+    /// Spawn a task that must complete before the next depth of the startup tree runs.
+    ///
+    /// The returned [`CommandQueue`] is applied to the world once the task completes.
+    ///
+    /// [`CommandQueue`]: https://docs.rs/bevy/~0.14/bevy/ecs/world/struct.CommandQueue.html
+    pub fn spawn(&mut self, task: impl Future<Output = CommandQueue> + Send + 'static) {
+        self.0.get().push(Box::pin(task));
+    }
+}
+
+impl Default for StartupTasks {
+    fn default() -> Self {
+        Self(SyncCell::new(Vec::new()))
+    }
+}
+
+/// Block until all [`StartupTasks`] have completed and apply their commands.
+///
+/// This system is chained after each node that is marked `async` in the
+/// [`startup_tree`](crate::startup_tree) macro, it should not need to be added manually.
+pub fn await_startup_tasks(world: &mut World) {
+    let Some(mut tasks) = world.get_resource_mut::<StartupTasks>() else {
+        return;
+    };
+    let tasks = std::mem::take(tasks.0.get());
+    if tasks.is_empty() {
+        return;
+    }
+
+    let queues = AsyncComputeTaskPool::get_or_init(TaskPool::new).scope(|scope| {
+        for task in tasks {
+            scope.spawn(task);
+        }
+    });
+
+    for mut queue in queues {
+        queue.apply(world);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{ecs::world::CommandQueue, prelude::*};
+
+    use crate::{rng::reseed_rng, startup_tree, AddStartupTree, StartupTasks};
+
+    #[derive(Resource)]
+    struct Loaded(u32);
+
+    #[derive(Resource, Default)]
+    struct Observed(Vec<Option<u32>>);
+
+    fn load(mut tasks: ResMut<StartupTasks>) {
+        for value in [1, 2] {
+            tasks.spawn(async move {
+                let mut queue = CommandQueue::default();
+                queue.push(move |world: &mut World| {
+                    let total = world.get_resource::<Loaded>().map_or(0, |loaded| loaded.0);
+                    world.insert_resource(Loaded(total + value));
+                });
+                queue
+            });
+        }
+    }
+
+    fn observe(loaded: Option<Res<Loaded>>, mut observed: ResMut<Observed>) {
+        observed.0.push(loaded.map(|loaded| loaded.0));
+    }
+
+    #[test]
+    fn next_depth_runs_after_tasks_complete() {
+        reseed_rng();
+
+        let mut app = App::new();
+        app.add_plugins(TaskPoolPlugin::default());
+        app.init_resource::<Observed>();
+        app.add_startup_tree(startup_tree! {
+            async load => observe,
+        });
+
+        app.update();
+
+        assert_eq!(app.world().resource::<Observed>().0, [Some(3)]);
+    }
+}
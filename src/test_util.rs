@@ -0,0 +1,341 @@
+use std::borrow::Cow;
+
+use bevy_app::{App, Startup};
+use bevy_core::TaskPoolPlugin;
+use bevy_ecs::{
+    schedule::{Schedule, Schedules},
+    system::Resource,
+    world::World,
+};
+
+use crate::{rng::seed_rng, startup_tree_stats, AddStartupTree, StartupTreeLayer, StartupTreeNode};
+
+/// Run a startup tree once in a minimal [`App`] and return the resulting [`World`].
+///
+/// The app only has a [`TaskPoolPlugin`] and the tree. A single update is run, which runs the
+/// [`Startup` schedule][`Startup`] and with it the tree. This is meant to cut down on the
+/// boilerplate of integration tests that check the effects of a tree.
+///
+/// ```rust
+/// # use bevy::prelude::*;
+/// # use bevy_startup_tree::{run_startup_tree_once, startup_tree};
+/// #[derive(Resource)]
+/// struct Score(u32);
+///
+/// fn init_score(mut commands: Commands) {
+///     commands.insert_resource(Score(0));
+/// }
+///
+/// fn add_bonus(mut score: ResMut<Score>) {
+///     score.0 += 10;
+/// }
+///
+/// let world = run_startup_tree_once(startup_tree! { init_score => add_bonus });
+/// assert_eq!(world.resource::<Score>().0, 10);
+/// ```
+///
+/// Only available with the `test-util` feature.
+///
+/// [`App`]: https://docs.rs/bevy/~0.14/bevy/app/struct.App.html
+/// [`Startup`]: https://docs.rs/bevy/~0.14/bevy/app/struct.Startup.html
+/// [`TaskPoolPlugin`]: https://docs.rs/bevy/~0.14/bevy/core/struct.TaskPoolPlugin.html
+/// [`World`]: https://docs.rs/bevy/~0.14/bevy/ecs/world/struct.World.html
+pub fn run_startup_tree_once<I2, I>(startup_tree: I2) -> World
+where
+    I2: IntoIterator<Item = I>,
+    I: IntoIterator<Item: Into<StartupTreeNode>>,
+{
+    let mut app = App::new();
+    app.add_plugins(TaskPoolPlugin::default());
+    app.add_startup_tree(startup_tree);
+    app.update();
+    std::mem::take(app.world_mut())
+}
+
+/// The labels of the [`StartupTreeLayer`]s in the [`Startup` schedule][`Startup`] of `app`, in
+/// order of depth.
+///
+/// This makes it possible to check the shape of the trees that were added to an app without
+/// running them. Layers of the same depth from different trees are sorted by label. Returns an
+/// empty list if the app has no `Startup` schedule.
+///
+/// ```rust
+/// # use bevy::prelude::*;
+/// # use bevy_startup_tree::{startup_tree, startup_tree_layer_labels, AddStartupTree};
+/// # fn load_config() {}
+/// # fn spawn_world() {}
+/// let mut app = App::new();
+/// app.add_startup_tree(startup_tree! { "load": load_config => spawn_world });
+///
+/// let labels = startup_tree_layer_labels(&app);
+/// assert_eq!(labels.len(), 2);
+/// assert!(labels[0].ends_with("_load"));
+/// assert!(labels[1].ends_with("_layer_1"));
+/// ```
+///
+/// Only available with the `test-util` feature.
+///
+/// [`Startup`]: https://docs.rs/bevy/~0.14/bevy/app/struct.Startup.html
+pub fn startup_tree_layer_labels(app: &App) -> Vec<String> {
+    let Some(schedule) = app.world().resource::<Schedules>().get(Startup) else {
+        return Vec::new();
+    };
+    let graph = schedule.graph();
+
+    let mut layers = graph
+        .hierarchy()
+        .graph()
+        .nodes()
+        .filter_map(|id| graph.get_set_at(id))
+        .filter_map(|set| set.as_dyn_eq().as_any().downcast_ref::<StartupTreeLayer>())
+        .copied()
+        .collect::<Vec<_>>();
+    layers.sort();
+    layers.iter().map(|layer| layer.label().to_owned()).collect()
+}
+
+/// The systems of the startup trees in the order in which they were added to their schedules, with
+/// the depths of their layers.
+///
+/// Every tree that is inserted into an app is recorded, in the order the trees were inserted. The
+/// systems of a tree are recorded in the order they were added, which is by depth, then by the
+/// order of the nodes in the tree, then by the order of the systems of each node. Unlike the
+/// schedule graph, this order doesn't depend on the namespaces of the trees, so tests can compare
+/// it to a fixed list. Trees built into a schedule with
+/// [`build_schedule`](crate::StartupTreeBuilder::build_schedule) aren't part of an app and aren't
+/// recorded.
+///
+/// ```rust
+/// # use bevy::prelude::*;
+/// # use bevy_startup_tree::{startup_tree, AddStartupTree, StartupTreeInsertionLog};
+/// # fn load_config() {}
+/// # fn spawn_world() {}
+/// let mut app = App::new();
+/// app.add_startup_tree(startup_tree! { load_config => spawn_world });
+///
+/// let log = app.world().resource::<StartupTreeInsertionLog>();
+/// let systems = log.iter().map(|(depth, name)| (depth, name.rsplit("::").next().unwrap()));
+/// assert_eq!(systems.collect::<Vec<_>>(), [(0, "load_config"), (1, "spawn_world")]);
+/// ```
+///
+/// This is a facility for tests and debugging, only available with the `test-util` feature.
+#[derive(Resource, Debug, Default)]
+pub struct StartupTreeInsertionLog {
+    systems: Vec<(usize, Cow<'static, str>)>,
+}
+
+impl StartupTreeInsertionLog {
+    /// The depths of the recorded systems and their names, in the order they were added.
+    ///
+    /// System names are the type names of the systems, e.g. `my_game::setup::spawn_world`.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &str)> + '_ {
+        self.systems.iter().map(|(depth, name)| (*depth, name.as_ref()))
+    }
+
+    /// Forget the recorded systems, e.g. to only check the trees inserted after this call.
+    pub fn clear(&mut self) {
+        self.systems.clear();
+    }
+
+    /// Record the systems of the `layers` that were added to `schedule`, in the order they were
+    /// added.
+    pub(crate) fn record(&mut self, schedule: &Schedule, layers: &[StartupTreeLayer]) {
+        let graph = schedule.graph();
+        let layer_ids = graph
+            .system_sets()
+            .filter_map(|(id, set, _)| {
+                let layer = set.as_dyn_eq().as_any().downcast_ref::<StartupTreeLayer>()?;
+                layers.contains(layer).then_some((id, layer.index()))
+            })
+            .collect::<Vec<_>>();
+        let hierarchy = graph.hierarchy().graph();
+        // Systems are listed in the order they were added to the schedule
+        for (id, system, _) in graph.systems() {
+            let layer =
+                layer_ids.iter().find(|&&(layer_id, _)| hierarchy.contains_edge(layer_id, id));
+            if let Some(&(_, depth)) = layer {
+                self.systems.push((depth, system.name()));
+            }
+        }
+    }
+}
+
+/// Assert that two startup trees have the same number of layers and the same number of nodes in
+/// each layer.
+///
+/// This is a safety net for refactoring the syntax of a tree, e.g. from arrows to explicit layers
+/// with [`startup_layers`](crate::startup_layers). Systems can't be compared at runtime, so only
+/// the structure is. To compare the names of the nodes as well, compare the output of
+/// [`startup_tree_meta`](crate::startup_tree_meta) and
+/// [`startup_layers_meta`](crate::startup_layers_meta).
+///
+/// ```rust
+/// # use bevy_startup_tree::{assert_same_layering, startup_layers, startup_tree};
+/// # fn load_config() {}
+/// # fn load_assets() {}
+/// # fn spawn_world() {}
+/// assert_same_layering(
+///     &startup_tree! { load_config => spawn_world, load_assets },
+///     &startup_layers![[load_config, load_assets], [spawn_world]],
+/// );
+/// ```
+///
+/// # Panics
+///
+/// If the trees have a different number of layers, or any of their layers have a different number
+/// of nodes.
+///
+/// Only available with the `test-util` feature.
+#[track_caller]
+pub fn assert_same_layering<L, N>(a: &[L], b: &[L])
+where
+    L: AsRef<[N]>,
+{
+    let a = startup_tree_stats(a).layer_node_counts;
+    let b = startup_tree_stats(b).layer_node_counts;
+    assert!(a == b, "trees have different layerings, nodes per layer: {a:?} != {b:?}",);
+}
+
+/// Seed the generator of the namespaces that make the labels of startup trees unique.
+///
+/// The labels of trees that are added after this call are the same in every run, e.g. so that
+/// tests can compare them to fixed strings. Trees added with the same seed get the same labels,
+/// so only seed once per app unless the labels of its trees may collide. The generator is local to
+/// the current thread, other threads aren't affected.
+///
+/// ```rust
+/// # use bevy::prelude::*;
+/// # use bevy_startup_tree::{
+/// #     set_namespace_seed, startup_tree, startup_tree_layer_labels, AddStartupTree,
+/// # };
+/// # fn load_config() {}
+/// let labels = || {
+///     set_namespace_seed(42);
+///     let mut app = App::new();
+///     app.add_startup_tree(startup_tree! { load_config });
+///     startup_tree_layer_labels(&app)
+/// };
+///
+/// assert_eq!(labels(), labels());
+/// ```
+///
+/// Only available with the `test-util` feature.
+pub fn set_namespace_seed(seed: u64) {
+    seed_rng(seed);
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+
+    use crate::{
+        assert_same_layering,
+        rng::{reseed_rng, reset_rng},
+        run_startup_tree_once, set_namespace_seed, startup_layers, startup_tree,
+        startup_tree_layer_labels, AddStartupTree, StartupTreeInsertionLog,
+    };
+
+    #[derive(Component)]
+    struct Marker;
+
+    fn spawn_marker(mut commands: Commands) {
+        commands.spawn(Marker);
+    }
+
+    #[test]
+    fn returns_world_after_startup() {
+        reseed_rng();
+
+        let mut world = run_startup_tree_once(startup_tree! {
+            spawn_marker => spawn_marker => spawn_marker,
+        });
+
+        assert_eq!(world.query::<&Marker>().iter(&world).count(), 3);
+    }
+
+    fn other() {}
+
+    #[test]
+    fn lists_layer_labels_in_order() {
+        reset_rng();
+
+        let mut app = App::new();
+        app.add_startup_tree(startup_tree! { spawn_marker => { other, spawn_marker => other } });
+        app.add_startup_tree(startup_tree! { "named": other });
+
+        assert_eq!(
+            startup_tree_layer_labels(&app),
+            [
+                "__startup_tree_ql3QHx_named",
+                "__startup_tree_zujxzB_layer_0",
+                "__startup_tree_zujxzB_layer_1",
+                "__startup_tree_zujxzB_layer_2",
+            ]
+        );
+    }
+
+    #[test]
+    fn seeded_namespaces_are_reproducible() {
+        let labels = |seed| {
+            set_namespace_seed(seed);
+            let mut app = App::new();
+            app.add_startup_tree(startup_tree! { spawn_marker => other });
+            startup_tree_layer_labels(&app)
+        };
+
+        assert_eq!(labels(7), labels(7));
+        assert_ne!(labels(7), labels(8));
+    }
+
+    #[test]
+    fn logs_systems_in_insertion_order() {
+        reseed_rng();
+
+        let mut app = App::new();
+        app.add_startup_tree(startup_tree! { spawn_marker => other });
+        app.world_mut().resource_mut::<StartupTreeInsertionLog>().clear();
+        app.add_startup_tree(startup_tree! {
+            other => "named": (spawn_marker, other) => _ => spawn_marker,
+        });
+        app.add_startup_tree(startup_tree! { spawn_marker });
+
+        let log = app.world().resource::<StartupTreeInsertionLog>();
+        let prefix = "bevy_startup_tree::test_util::tests::";
+        let systems = log
+            .iter()
+            .map(|(depth, name)| (depth, name.strip_prefix(prefix).unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            systems,
+            [
+                (0, "other"),
+                (1, "spawn_marker"),
+                (1, "other"),
+                (3, "spawn_marker"),
+                (0, "spawn_marker")
+            ]
+        );
+    }
+
+    #[test]
+    fn lists_no_layer_labels_without_trees() {
+        assert!(startup_tree_layer_labels(&App::new()).is_empty());
+    }
+
+    #[test]
+    fn same_layering_of_equivalent_trees() {
+        assert_same_layering(
+            &startup_tree! { spawn_marker => { other, spawn_marker => other } },
+            &startup_layers![[spawn_marker], [other, spawn_marker], [other]],
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "nodes per layer: [1, 2, 1] != [1, 1, 2]")]
+    fn different_layering_of_trees_with_same_depth() {
+        assert_same_layering(
+            &startup_tree! { spawn_marker => { other, spawn_marker => other } },
+            &startup_layers![[spawn_marker], [other], [spawn_marker, other]],
+        );
+    }
+}
@@ -0,0 +1,57 @@
+use std::collections::BTreeMap;
+
+use bevy_ecs::system::Resource;
+use bevy_utils::{Duration, Instant};
+
+use crate::StartupTreeLayer;
+
+/// How long each layer of the timed startup trees took to run.
+///
+/// Layers are only timed if their tree was inserted with
+/// [`add_startup_tree_timed`](crate::AddStartupTree::add_startup_tree_timed) or a
+/// [`StartupTreeBuilder`](crate::StartupTreeBuilder) with [`timed`](crate::StartupTreeBuilder::timed).
+/// A system before each layer records when it starts, and one after the layer records the elapsed
+/// time. The timings are available once the tree has run, e.g. in `PostStartup`.
+///
+/// ```rust no_run
+/// # use bevy::prelude::*;
+/// # use bevy_startup_tree::{startup_tree, AddStartupTree, StartupTimings};
+/// # fn sys_1() {}
+/// # fn sys_2() {}
+/// fn report_timings(timings: Res<StartupTimings>) {
+///     for (layer, elapsed) in timings.iter() {
+///         info!("{} took {elapsed:?}", layer.label());
+///     }
+/// }
+///
+/// App::new()
+///     .add_startup_tree_timed(startup_tree! { sys_1 => sys_2 })
+///     .add_systems(PostStartup, report_timings);
+/// ```
+#[derive(Resource, Debug, Default)]
+pub struct StartupTimings {
+    started: BTreeMap<StartupTreeLayer, Instant>,
+    elapsed: BTreeMap<StartupTreeLayer, Duration>,
+}
+
+impl StartupTimings {
+    /// The time that `layer` took to run, if it is timed and has run.
+    pub fn get(&self, layer: &StartupTreeLayer) -> Option<Duration> {
+        self.elapsed.get(layer).copied()
+    }
+
+    /// The timed layers that have run and how long each took, ordered by depth.
+    pub fn iter(&self) -> impl Iterator<Item = (StartupTreeLayer, Duration)> + '_ {
+        self.elapsed.iter().map(|(&layer, &elapsed)| (layer, elapsed))
+    }
+
+    pub(crate) fn start(&mut self, layer: StartupTreeLayer) {
+        self.started.insert(layer, Instant::now());
+    }
+
+    pub(crate) fn finish(&mut self, layer: StartupTreeLayer) {
+        if let Some(started) = self.started.remove(&layer) {
+            self.elapsed.insert(layer, started.elapsed());
+        }
+    }
+}
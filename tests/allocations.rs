@@ -0,0 +1,79 @@
+//! Allocation budgets of inserting trees.
+//!
+//! These tests are a binary of their own since they replace the global allocator.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+use bevy::prelude::*;
+use bevy_startup_tree::{startup_tree, StartupTreeBuilder};
+
+/// Counts the allocations of each thread, so that tests running in parallel don't count each
+/// other's allocations.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn count() {
+    let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count();
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count();
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// The number of allocations and reallocations of the current thread so far.
+fn allocations() -> usize {
+    ALLOCATIONS.with(Cell::get)
+}
+
+fn load() {}
+fn spawn_a() {}
+fn spawn_b() {}
+fn finish() {}
+
+/// The allocations of inserting a tree into an existing schedule.
+fn insert_allocations() -> usize {
+    let mut schedule = Schedule::new(Startup);
+    let tree = startup_tree! {
+        load => {
+            spawn_a => finish,
+            spawn_b,
+        },
+    };
+    let builder = StartupTreeBuilder::new(tree);
+
+    let before = allocations();
+    builder.insert_into_schedule(&mut schedule);
+    allocations() - before
+}
+
+#[test]
+fn inserting_a_tree_stays_within_budget() {
+    // The first tree of a thread also initializes the thread's namespace generator
+    insert_allocations();
+
+    // Most of the allocations are Bevy's, so the budget may need to be raised for a new version of
+    // Bevy. Labels used to take 6 more allocations before they shared a buffer.
+    let allocations = insert_allocations();
+    assert!(allocations <= 101, "inserting a tree took {allocations} allocations");
+}
@@ -0,0 +1,12 @@
+use bevy_startup_tree::startup_tree;
+
+fn sys_good_a() {}
+fn sys_good_b() {}
+
+fn main() {
+    let not_a_system = 42;
+    let _tree = startup_tree! {
+        sys_good_a => not_a_system,
+        sys_good_b,
+    };
+}
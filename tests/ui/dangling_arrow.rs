@@ -0,0 +1,9 @@
+use bevy_startup_tree::startup_tree;
+
+fn sys_parent() {}
+
+fn main() {
+    let _tree = startup_tree! {
+        sys_parent =>
+    };
+}
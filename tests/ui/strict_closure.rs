@@ -0,0 +1,12 @@
+use bevy_startup_tree::{startup_tree, strict_startup_tree};
+
+fn sys_parent() {}
+
+fn main() {
+    let _tree = startup_tree! {
+        sys_parent => || {},
+    };
+    let _strict_tree = strict_startup_tree! {
+        sys_parent => || {},
+    };
+}
@@ -0,0 +1,13 @@
+use bevy_startup_tree::startup_tree;
+
+fn sys_a() {}
+fn sys_b() {}
+fn sys_c() {}
+
+fn main() {
+    let _tree = startup_tree! {
+        sys_a => {
+            sys_b,
+            sys_c,
+    };
+}
@@ -0,0 +1,8 @@
+use bevy_startup_tree::assert_startup_tree_shape;
+
+assert_startup_tree_shape!([1, 2], {
+    load_config => spawn_world,
+    load_assets,
+});
+
+fn main() {}